@@ -314,10 +314,30 @@ impl ClosedCollation {
         self.collation.requests.len()
     }
 
+    pub fn requests(&self) -> &Vec<SignedRequest> {
+        &self.collation.requests
+    }
+
     pub fn request_hashes(&self) -> Vec<H256> {
         self.collation.request_set.iter().cloned().collect()
     }
 
+    /// Re-signs the same transaction (same nonce, gas and data) with a new gas price
+    pub fn resign_with_gas_price(
+        &self,
+        secret: Secret,
+        chain_id: Option<u64>,
+        gas_price: &U256,
+    ) -> ClosedCollation {
+        let mut tx = self.transaction.as_unsigned().clone();
+        tx.gas_price = gas_price.clone();
+
+        ClosedCollation {
+            collation: self.collation.clone(),
+            transaction: tx.sign(&secret, chain_id),
+        }
+    }
+
     pub fn reopen(self) -> OpenCollation {
         OpenCollation::with_collation(self.collation)
     }
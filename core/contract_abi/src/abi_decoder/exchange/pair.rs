@@ -0,0 +1,57 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethabi::Token;
+use ethereum_types::{Address, U256};
+
+use super::error::{Error, ErrorKind};
+use super::EXCHANGE_PAIR_INTERFACE;
+
+pub struct ExchangePairAbiDecoder;
+
+impl ExchangePairAbiDecoder {
+    pub fn token0(data: &Vec<u8>) -> Result<Address, Error> {
+        Self::decode_address("token0", data)
+    }
+
+    pub fn token1(data: &Vec<u8>) -> Result<Address, Error> {
+        Self::decode_address("token1", data)
+    }
+
+    /// Decodes output of `getReserves` into (reserve0, reserve1)
+    pub fn get_reserves(data: &Vec<u8>) -> Result<(U256, U256), Error> {
+        let get_reserves_function = &EXCHANGE_PAIR_INTERFACE
+            .function("getReserves")
+            .expect("getReserves is always implemented; qed");
+
+        let mut tokens = get_reserves_function.decode_output(data)?.into_iter();
+        match (tokens.next(), tokens.next()) {
+            (Some(Token::Uint(reserve0)), Some(Token::Uint(reserve1))) => Ok((reserve0, reserve1)),
+            _ => Err(Error::from(ErrorKind::InvalidReturnValue)),
+        }
+    }
+
+    fn decode_address(function_name: &str, data: &Vec<u8>) -> Result<Address, Error> {
+        let function = &EXCHANGE_PAIR_INTERFACE
+            .function(function_name)
+            .expect("function is always implemented; qed");
+
+        let mut vec = function.decode_output(data)?;
+        match vec.pop() {
+            Some(Token::Address(address)) => Ok(address),
+            _ => Err(Error::from(ErrorKind::InvalidReturnValue)),
+        }
+    }
+}
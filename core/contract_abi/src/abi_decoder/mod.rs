@@ -14,13 +14,16 @@
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 mod dispatcher;
+mod exchange;
 mod token;
 
 pub use self::dispatcher::FstTokenTransferRequestDispatcherAbiDecoder;
 
+pub use self::exchange::ExchangePairAbiDecoder;
+
 pub use self::token::{ERC1376AbiDecoder, ERC20AbiDecoder};
 
 use super::error;
 
 // use super::FSTK_TOKEN_TRANSFER_REQUEST_DISPATCHER_INTERFACE;
-use super::{ERC1376_TOKEN_INTERFACE, ERC20_TOKEN_INTERFACE};
+use super::{ERC1376_TOKEN_INTERFACE, ERC20_TOKEN_INTERFACE, EXCHANGE_PAIR_INTERFACE};
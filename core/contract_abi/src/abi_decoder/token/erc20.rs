@@ -13,8 +13,8 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
-use ethabi::Token;
-use ethereum_types::U256;
+use ethabi::{RawLog, Token};
+use ethereum_types::{Address, H256, U256};
 
 use super::error::{Error, ErrorKind};
 use super::ERC20_TOKEN_INTERFACE;
@@ -34,6 +34,18 @@ impl ERC20AbiDecoder {
         }
     }
 
+    pub fn decimals(data: &Vec<u8>) -> Result<U256, Error> {
+        let decimals_function = &ERC20_TOKEN_INTERFACE
+            .function("decimals")
+            .expect("decimals is always implemented; qed");
+
+        let mut vec = decimals_function.decode_output(data)?;
+        match vec.pop() {
+            Some(Token::Uint(decimals)) => Ok(decimals),
+            _ => Err(Error::from(ErrorKind::InvalidReturnValue)),
+        }
+    }
+
     pub fn balance_of(data: &Vec<u8>) -> Result<U256, Error> {
         let balance_of_function = &ERC20_TOKEN_INTERFACE
             .function("balanceOf")
@@ -46,6 +58,37 @@ impl ERC20AbiDecoder {
         }
     }
 
+    /// Returns topic of `Transfer` event
+    pub fn transfer_event_topic() -> H256 {
+        ERC20_TOKEN_INTERFACE
+            .event("Transfer")
+            .expect("Transfer is always implemented; qed")
+            .signature()
+    }
+
+    /// Decodes a `Transfer` event log into (from, to, value)
+    pub fn transfer_event(
+        topics: &Vec<H256>,
+        data: &Vec<u8>,
+    ) -> Result<(Address, Address, U256), Error> {
+        let transfer_event = &ERC20_TOKEN_INTERFACE
+            .event("Transfer")
+            .expect("Transfer is always implemented; qed");
+
+        let log = transfer_event.parse_log(RawLog {
+            topics: topics.clone(),
+            data: data.clone(),
+        })?;
+
+        let mut params = log.params.into_iter().map(|param| param.value);
+        match (params.next(), params.next(), params.next()) {
+            (Some(Token::Address(from)), Some(Token::Address(to)), Some(Token::Uint(value))) => {
+                Ok((from, to, value))
+            }
+            _ => Err(Error::from(ErrorKind::InvalidReturnValue)),
+        }
+    }
+
     pub fn total_supply(data: &Vec<u8>) -> Result<U256, Error> {
         let total_supply_function = &ERC20_TOKEN_INTERFACE
             .function("totalSupply")
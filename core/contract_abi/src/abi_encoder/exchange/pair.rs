@@ -0,0 +1,43 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use super::EXCHANGE_PAIR_INTERFACE;
+
+pub struct ExchangePairAbiEncoder;
+
+impl ExchangePairAbiEncoder {
+    pub fn token0() -> Vec<u8> {
+        let token0_function = &EXCHANGE_PAIR_INTERFACE
+            .function("token0")
+            .expect("token0 is always implemented; qed");
+        token0_function.encode_input(&[]).expect("token0")
+    }
+
+    pub fn token1() -> Vec<u8> {
+        let token1_function = &EXCHANGE_PAIR_INTERFACE
+            .function("token1")
+            .expect("token1 is always implemented; qed");
+        token1_function.encode_input(&[]).expect("token1")
+    }
+
+    pub fn get_reserves() -> Vec<u8> {
+        let get_reserves_function = &EXCHANGE_PAIR_INTERFACE
+            .function("getReserves")
+            .expect("getReserves is always implemented; qed");
+        get_reserves_function
+            .encode_input(&[])
+            .expect("getReserves")
+    }
+}
@@ -14,13 +14,17 @@
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 mod dispatcher;
+mod exchange;
 mod token;
 
 use super::interfaces::{
-    ERC1376_TOKEN_INTERFACE, ERC20_TOKEN_INTERFACE, FST_TOKEN_TRANSFER_REQUEST_DISPATCHER_INTERFACE,
+    ERC1376_TOKEN_INTERFACE, ERC20_TOKEN_INTERFACE, EXCHANGE_PAIR_INTERFACE,
+    FST_TOKEN_TRANSFER_REQUEST_DISPATCHER_INTERFACE,
 };
 
 pub use self::dispatcher::FstTokenTransferRequestDispatcherAbiEncoder;
 
+pub use self::exchange::ExchangePairAbiEncoder;
+
 pub use self::token::ERC1376AbiEncoder;
 pub use self::token::ERC20AbiEncoder;
@@ -37,6 +37,13 @@ impl ERC20AbiEncoder {
         symbol_function.encode_input(&[]).expect("symbol")
     }
 
+    pub fn decimals() -> Vec<u8> {
+        let decimals_function = &ERC20_TOKEN_INTERFACE
+            .function("decimals")
+            .expect("decimals is always implemented; qed");
+        decimals_function.encode_input(&[]).expect("decimals")
+    }
+
     pub fn balance_of(address: &Address) -> Vec<u8> {
         let balance_of_function = &ERC20_TOKEN_INTERFACE
             .function("balanceOf")
@@ -0,0 +1,85 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethabi::{Contract, Event, Function, Param, ParamType};
+
+use std::collections::HashMap;
+
+lazy_static! {
+    pub static ref EXCHANGE_PAIR_INTERFACE: Contract = {
+        let mut functions: HashMap<String, Function> = HashMap::new();
+        let events: HashMap<String, Event> = HashMap::new();
+
+        // function token0() external view returns (address);
+        functions.insert(
+            "token0".into(),
+            Function {
+                name: "token0".into(),
+                constant: true,
+                inputs: vec![],
+                outputs: vec![Param {
+                    name: "".to_owned(),
+                    kind: ParamType::Address,
+                }],
+            },
+        );
+
+        // function token1() external view returns (address);
+        functions.insert(
+            "token1".into(),
+            Function {
+                name: "token1".into(),
+                constant: true,
+                inputs: vec![],
+                outputs: vec![Param {
+                    name: "".to_owned(),
+                    kind: ParamType::Address,
+                }],
+            },
+        );
+
+        // function getReserves() external view
+        //     returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+        functions.insert(
+            "getReserves".into(),
+            Function {
+                name: "getReserves".into(),
+                constant: true,
+                inputs: vec![],
+                outputs: vec![
+                    Param {
+                        name: "reserve0".to_owned(),
+                        kind: ParamType::Uint(112),
+                    },
+                    Param {
+                        name: "reserve1".to_owned(),
+                        kind: ParamType::Uint(112),
+                    },
+                    Param {
+                        name: "blockTimestampLast".to_owned(),
+                        kind: ParamType::Uint(32),
+                    },
+                ],
+            },
+        );
+
+        Contract {
+            constructor: None,
+            fallback: false,
+            events,
+            functions,
+        }
+    };
+}
@@ -14,7 +14,9 @@
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 mod dispatcher;
+mod exchange;
 mod token;
 
 pub use self::dispatcher::FST_TOKEN_TRANSFER_REQUEST_DISPATCHER_INTERFACE;
+pub use self::exchange::EXCHANGE_PAIR_INTERFACE;
 pub use self::token::{ERC1376_TOKEN_INTERFACE, ERC20_TOKEN_INTERFACE};
@@ -88,6 +88,20 @@ lazy_static! {
             },
         );
 
+        // function decimals() public constant returns (uint8);
+        functions.insert(
+            "decimals".into(),
+            Function {
+                name: "decimals".into(),
+                constant: true,
+                inputs: vec![],
+                outputs: vec![Param {
+                    name: "".to_owned(),
+                    kind: ParamType::Uint(8),
+                }],
+            },
+        );
+
         // function totalSupply() public constant returns (uint);
         functions.insert(
             "totalSupply".into(),
@@ -34,11 +34,14 @@ mod interfaces;
 pub use self::error::Error;
 
 pub use self::abi_decoder::{
-    ERC1376AbiDecoder, ERC20AbiDecoder, FstTokenTransferRequestDispatcherAbiDecoder,
+    ERC1376AbiDecoder, ERC20AbiDecoder, ExchangePairAbiDecoder,
+    FstTokenTransferRequestDispatcherAbiDecoder,
 };
 pub use self::abi_encoder::{
-    ERC1376AbiEncoder, ERC20AbiEncoder, FstTokenTransferRequestDispatcherAbiEncoder,
+    ERC1376AbiEncoder, ERC20AbiEncoder, ExchangePairAbiEncoder,
+    FstTokenTransferRequestDispatcherAbiEncoder,
 };
 
 pub use self::interfaces::FST_TOKEN_TRANSFER_REQUEST_DISPATCHER_INTERFACE;
+pub use self::interfaces::EXCHANGE_PAIR_INTERFACE;
 pub use self::interfaces::{ERC1376_TOKEN_INTERFACE, ERC20_TOKEN_INTERFACE};
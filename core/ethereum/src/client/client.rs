@@ -241,6 +241,46 @@ impl EthereumClient {
             })
     }
 
+    pub fn eth_get_block_hash(&self, block_number: &U256) -> impl Future<Item = H256, Error = Error> {
+        self.request(
+            "eth_getBlockByNumber",
+            vec![to_0xhex(block_number).into(), false.into()],
+        )
+        .and_then(extract_result)
+        .and_then(|value: JsonValue| match value["hash"].as_str() {
+            Some(hash) => {
+                H256::from_str(clean_0x(hash)).map_err(|_| Error::from(ErrorKind::ParseHex))
+            }
+            None => Err(Error::from(ErrorKind::NoSuchField("hash"))),
+        })
+    }
+
+    pub fn eth_gas_price(&self) -> impl Future<Item = U256, Error = Error> {
+        self.request("eth_gasPrice", vec![])
+            .and_then(extract_hex_value)
+    }
+
+    pub fn eth_get_block_gas_prices(
+        &self,
+        block_id: BlockId,
+    ) -> impl Future<Item = Vec<U256>, Error = Error> {
+        let block_id = block_id.to_string();
+
+        self.request("eth_getBlockByNumber", vec![block_id.into(), true.into()])
+            .and_then(extract_result)
+            .and_then(|block: JsonValue| match block["transactions"].as_array() {
+                Some(transactions) => transactions
+                    .iter()
+                    .map(|tx| match tx["gasPrice"].as_str() {
+                        Some(gas_price) => U256::from_str(clean_0x(gas_price))
+                            .map_err(|_| Error::from(ErrorKind::ParseHex)),
+                        None => Err(Error::from(ErrorKind::NoSuchField("gasPrice"))),
+                    })
+                    .collect(),
+                None => Err(Error::from(ErrorKind::NoSuchField("transactions"))),
+            })
+    }
+
     pub fn eth_block_number(&self) -> impl Future<Item = U256, Error = Error> {
         self.request("eth_blockNumber", vec![])
             .and_then(extract_hex_value)
@@ -302,6 +342,15 @@ impl EthereumClient {
             .from_err()
     }
 
+    pub fn token_decimals(
+        &self,
+        token_address: &Address,
+    ) -> impl Future<Item = U256, Error = Error> {
+        self.eth_read_contract(token_address, &ERC20AbiEncoder::decimals())
+            .and_then(|data| Ok(ERC20AbiDecoder::decimals(&data)?))
+            .from_err()
+    }
+
     pub fn token_delegate_enable(
         &self,
         token_address: &Address,
@@ -22,6 +22,7 @@ mod service;
 pub type WatcherId = usize;
 
 pub use self::error::{Error, ErrorKind};
+pub use self::poller::confirmations;
 pub use self::service::{Params, Service};
 
 use self::poller::TaskPoller;
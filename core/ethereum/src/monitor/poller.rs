@@ -13,8 +13,8 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
-use ethereum_types::U256;
-use futures::{Async, Future, IntoFuture, Poll};
+use ethereum_types::{H256, U256};
+use futures::{future, Async, Future, IntoFuture, Poll};
 use parking_lot::Mutex;
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
@@ -23,6 +23,63 @@ use tokio::timer::Delay;
 
 use traits::{EthereumMonitorResponse, EthereumMonitorTask, EthereumService};
 
+// a transaction is reorged once its receipt is missing in this many polls in a row, a single miss
+// may be caused by an endpoint which is lagging behind
+const MAX_RECEIPT_MISSES: u32 = 3;
+
+// the block which includes a watched transaction
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct IncludingBlock {
+    number: U256,
+    hash: H256,
+    // count of polls in a row which did not find the receipt
+    misses: u32,
+}
+
+impl IncludingBlock {
+    fn new(number: U256, hash: H256) -> IncludingBlock {
+        IncludingBlock {
+            number,
+            hash,
+            misses: 0,
+        }
+    }
+}
+
+/// Returns count of blocks mined on top of the block at `block_number`, a transaction is
+/// confirmed once this count reaches the confirmation count. Returns `None` if the block is not
+/// known at `current_block_number`.
+pub fn confirmations(block_number: U256, current_block_number: U256) -> Option<U256> {
+    match current_block_number >= block_number {
+        true => Some(current_block_number - block_number),
+        false => None,
+    }
+}
+
+/// Returns `true` if a transaction included in `including_block` is reorged given that its
+/// receipt is missing, `canonical_hash` is hash of the block with the same number on the
+/// canonical chain if it is known.
+fn is_reorged(including_block: &mut Option<IncludingBlock>, canonical_hash: Option<H256>) -> bool {
+    let reorged = match *including_block {
+        None => return false,
+        Some(ref mut block) => {
+            // the including block is still on the canonical chain, the endpoint is lagging behind
+            if canonical_hash == Some(block.hash) {
+                block.misses = 0;
+                return false;
+            }
+
+            block.misses += 1;
+            block.misses >= MAX_RECEIPT_MISSES
+        }
+    };
+
+    if reorged {
+        including_block.take();
+    }
+    reorged
+}
+
 pub struct TaskPoller<E>
 where
     E: EthereumService,
@@ -31,6 +88,9 @@ where
     task: EthereumMonitorTask,
     interval: Duration,
     future: Box<Future<Item = Option<EthereumMonitorResponse>, Error = ()> + Send>,
+
+    // number and hash of the block which includes the watched transaction
+    receipt_block: Arc<Mutex<Option<IncludingBlock>>>,
 }
 
 unsafe impl<E> Sync for TaskPoller<E> where E: EthereumService {}
@@ -47,18 +107,21 @@ where
         task: EthereumMonitorTask,
         interval: Duration,
     ) -> TaskPoller<E> {
-        let future = Self::new_future(ethereum.clone(), task.clone());
+        let receipt_block = Arc::new(Mutex::new(None));
+        let future = Self::new_future(ethereum.clone(), task.clone(), receipt_block.clone());
         TaskPoller {
             ethereum,
             task,
             interval,
             future,
+            receipt_block,
         }
     }
 
     fn new_future(
         ethereum: Arc<Mutex<E>>,
         task: EthereumMonitorTask,
+        receipt_block: Arc<Mutex<Option<IncludingBlock>>>,
     ) -> Box<Future<Item = Option<EthereumMonitorResponse>, Error = ()> + Send> {
         trace!(target: "ethereum_monitor", "create new polling future for task {:?}", task);
         match task {
@@ -69,22 +132,87 @@ where
                 ethereum
                     .lock()
                     .get_transaction_confirmation(hash)
-                    .map({
-                        move |confirmation| match confirmation.receipt {
-                            Some(receipt) => {
-                                let receipt_block_number = receipt.block_number?;
-                                let tx_hash = receipt.transaction_hash?;
-
-                                match confirmation.block_number - receipt_block_number
-                                    >= U256::from(confirmation_count)
-                                {
-                                    true => {
-                                        Some(EthereumMonitorResponse::Transaction(tx_hash.into()))
+                    .and_then({
+                        let ethereum = ethereum.clone();
+                        move |confirmation| -> Box<
+                            Future<Item = Option<EthereumMonitorResponse>, Error = E::Error>
+                                + Send,
+                        > {
+                            let receipt = match confirmation.receipt {
+                                Some(receipt) => receipt,
+                                None => {
+                                    let including_block = match *receipt_block.lock() {
+                                        Some(block) => block,
+                                        None => return Box::new(future::ok(None)),
+                                    };
+
+                                    // transaction was included in a block before, check whether
+                                    // the block is still on the canonical chain
+                                    return Box::new(
+                                        ethereum
+                                            .lock()
+                                            .get_block_hash(including_block.number)
+                                            .then(move |canonical_hash| {
+                                                let reorged = is_reorged(
+                                                    &mut receipt_block.lock(),
+                                                    canonical_hash.ok(),
+                                                );
+                                                if !reorged {
+                                                    return Ok(None);
+                                                }
+
+                                                warn!(target: "ethereum_monitor",
+                                                    "transaction {:?} in block #{} ({:?}) is reorged",
+                                                    hash, including_block.number, including_block.hash);
+                                                Ok::<_, E::Error>(Some(
+                                                    EthereumMonitorResponse::TransactionReorged(hash),
+                                                ))
+                                            }),
+                                    );
+                                }
+                            };
+
+                            let (receipt_block_number, receipt_block_hash): (U256, H256) =
+                                match (receipt.block_number, receipt.block_hash) {
+                                    (Some(number), Some(block_hash)) => {
+                                        (number.into(), block_hash.into())
                                     }
-                                    false => None,
+                                    _ => return Box::new(future::ok(None)),
+                                };
+
+                            {
+                                let mut receipt_block = receipt_block.lock();
+                                match *receipt_block {
+                                    Some(block) if block.hash != receipt_block_hash => {
+                                        info!(target: "ethereum_monitor",
+                                            "transaction {:?} is moved from block {:?} to block #{} ({:?})",
+                                            hash, block.hash, receipt_block_number, receipt_block_hash);
+                                    }
+                                    _ => {}
                                 }
+                                *receipt_block =
+                                    Some(IncludingBlock::new(receipt_block_number, receipt_block_hash));
+                            }
+
+                            match confirmations(receipt_block_number, confirmation.block_number) {
+                                Some(count) if count >= U256::from(confirmation_count) => {}
+                                _ => return Box::new(future::ok(None)),
                             }
-                            None => None,
+
+                            // re-check the including block at confirmation depth
+                            Box::new(ethereum.lock().get_block_hash(receipt_block_number).map(
+                                move |canonical_block_hash| {
+                                    if canonical_block_hash == receipt_block_hash {
+                                        return Some(EthereumMonitorResponse::Transaction(hash));
+                                    }
+
+                                    warn!(target: "ethereum_monitor",
+                                        "transaction {:?} in block #{} ({:?}) is reorged, canonical block: {:?}",
+                                        hash, receipt_block_number, receipt_block_hash, canonical_block_hash);
+                                    receipt_block.lock().take();
+                                    Some(EthereumMonitorResponse::TransactionReorged(hash))
+                                },
+                            ))
                         }
                     })
                     .map_err(|_| ()),
@@ -124,7 +252,11 @@ where
             Ok(Async::Ready(response)) => match response {
                 Some(response) => Ok(Async::Ready(response)),
                 None => {
-                    let future = Self::new_future(self.ethereum.clone(), self.task.clone());
+                    let future = Self::new_future(
+                        self.ethereum.clone(),
+                        self.task.clone(),
+                        self.receipt_block.clone(),
+                    );
                     let delay = Instant::now() + self.interval;
                     self.future =
                         Box::new(Delay::new(delay).then(move |_| future).into_future()) as Box<_>;
@@ -154,3 +286,272 @@ where
         self.task == other.task
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethcore_transaction::SignedTransaction;
+    use ethereum_types::Address;
+    use std::collections::HashMap;
+    use std::io;
+
+    use traits::{
+        AccountStateFuture, AccountStateProvider, BlockInfoProvider, BoolFuture, BytesFuture,
+        EthRpcTransactionConfirmationFuture, EthRpcTransactionFuture,
+        EthRpcTransactionReceiptFuture, GasEstimator, H256Future, StringFuture, TokenStateProvider,
+        TransactionBroadcaster, TransactionFetcher, U256Future, U256VecFuture,
+    };
+    use types::{
+        BlockId, Currency, EthRpcTransactionReceipt, EthTransactionConfirmation,
+        EthereumEndpointState, GasEstimation,
+    };
+
+    #[derive(Default)]
+    struct MockEthereum {
+        block_number: U256,
+        // number and hash of the block including the transaction, as seen by the endpoint
+        receipt: Option<(U256, H256)>,
+        block_hashes: HashMap<U256, H256>,
+    }
+
+    impl AccountStateProvider<io::Error> for MockEthereum {
+        fn balance_of(&self, _: Address, _: Currency) -> U256Future<io::Error> {
+            unimplemented!()
+        }
+        fn nonce_of(&self, _: Address, _: Currency) -> U256Future<io::Error> {
+            unimplemented!()
+        }
+        fn state_of(&self, _: Address, _: Currency) -> AccountStateFuture<io::Error> {
+            unimplemented!()
+        }
+        fn code_of(&self, _: Address) -> BytesFuture<io::Error> {
+            unimplemented!()
+        }
+        fn call_contract(&self, _: Address, _: Vec<u8>) -> BytesFuture<io::Error> {
+            unimplemented!()
+        }
+    }
+
+    impl TokenStateProvider<io::Error> for MockEthereum {
+        fn token_delegate_enable(&self, _: Address) -> BoolFuture<io::Error> {
+            unimplemented!()
+        }
+        fn token_symbol(&self, _: Address) -> StringFuture<io::Error> {
+            unimplemented!()
+        }
+        fn token_decimals(&self, _: Address) -> U256Future<io::Error> {
+            unimplemented!()
+        }
+    }
+
+    impl BlockInfoProvider<io::Error> for MockEthereum {
+        fn get_block_number(&self) -> U256Future<io::Error> {
+            Box::new(future::ok(self.block_number))
+        }
+        fn get_block_hash(&self, block_number: U256) -> H256Future<io::Error> {
+            match self.block_hashes.get(&block_number) {
+                Some(hash) => Box::new(future::ok(*hash)),
+                None => Box::new(future::err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "unknown block",
+                ))),
+            }
+        }
+    }
+
+    impl GasEstimator<io::Error> for MockEthereum {
+        fn block_gas_limit(&self, _: BlockId) -> U256Future<io::Error> {
+            unimplemented!()
+        }
+        fn estimate_gas(&self, _: GasEstimation) -> U256Future<io::Error> {
+            unimplemented!()
+        }
+        fn gas_price(&self) -> U256Future<io::Error> {
+            unimplemented!()
+        }
+        fn block_gas_prices(&self, _: BlockId) -> U256VecFuture<io::Error> {
+            unimplemented!()
+        }
+    }
+
+    impl TransactionBroadcaster<io::Error> for MockEthereum {
+        fn send_transaction(&self, _: SignedTransaction) -> H256Future<io::Error> {
+            unimplemented!()
+        }
+    }
+
+    impl TransactionFetcher<io::Error> for MockEthereum {
+        fn get_transaction_by_hash(&self, _: H256) -> EthRpcTransactionFuture<io::Error> {
+            unimplemented!()
+        }
+        fn get_transaction_receipt(&self, _: H256) -> EthRpcTransactionReceiptFuture<io::Error> {
+            unimplemented!()
+        }
+        fn get_transaction_confirmation(
+            &self,
+            _: H256,
+        ) -> EthRpcTransactionConfirmationFuture<io::Error> {
+            let receipt = self.receipt.map(|(number, hash)| EthRpcTransactionReceipt {
+                block_number: Some(number.into()),
+                block_hash: Some(hash.into()),
+                ..EthRpcTransactionReceipt::default()
+            });
+            Box::new(future::ok(EthTransactionConfirmation {
+                block_number: self.block_number,
+                receipt,
+            }))
+        }
+    }
+
+    impl EthereumService for MockEthereum {
+        type Error = io::Error;
+
+        fn add_endpoint(&mut self, _: String) -> bool {
+            unimplemented!()
+        }
+        fn remove_endpoint(&mut self, _: &String) -> bool {
+            unimplemented!()
+        }
+        fn contains_endpoint(&mut self, _: &String) -> bool {
+            unimplemented!()
+        }
+        fn endpoints(&self) -> Vec<String> {
+            unimplemented!()
+        }
+        fn endpoint_count(&self) -> usize {
+            unimplemented!()
+        }
+        fn endpoint_states(&self) -> Vec<EthereumEndpointState> {
+            unimplemented!()
+        }
+    }
+
+    struct Watch {
+        ethereum: Arc<Mutex<MockEthereum>>,
+        including_block: Arc<Mutex<Option<IncludingBlock>>>,
+    }
+
+    impl Watch {
+        fn new(ethereum: MockEthereum) -> Watch {
+            Watch {
+                ethereum: Arc::new(Mutex::new(ethereum)),
+                including_block: Arc::new(Mutex::new(None)),
+            }
+        }
+
+        fn poll(&self) -> Option<EthereumMonitorResponse> {
+            let task = EthereumMonitorTask::TransactionExecuted {
+                hash: H256::from(1),
+                confirmation_count: 2,
+            };
+            TaskPoller::new_future(self.ethereum.clone(), task, self.including_block.clone())
+                .wait()
+                .unwrap()
+        }
+    }
+
+    #[test]
+    fn test_confirmations() {
+        assert_eq!(
+            confirmations(U256::from(10), U256::from(12)),
+            Some(U256::from(2))
+        );
+        // the including block is the latest one
+        assert_eq!(
+            confirmations(U256::from(10), U256::from(10)),
+            Some(U256::zero())
+        );
+        assert_eq!(confirmations(U256::from(10), U256::from(9)), None);
+    }
+
+    #[test]
+    fn test_missing_receipt_of_lagging_endpoint_is_not_reorged() {
+        let mut including_block = Some(IncludingBlock::new(U256::from(10), H256::from(10)));
+        for _ in 0..MAX_RECEIPT_MISSES {
+            assert!(!is_reorged(&mut including_block, Some(H256::from(10))));
+        }
+        assert_eq!(including_block.map(|block| block.misses), Some(0));
+
+        // a transaction which is never included is not reorged
+        assert!(!is_reorged(&mut None, None));
+    }
+
+    #[test]
+    fn test_missing_receipt_in_a_row_is_reorged() {
+        let mut including_block = Some(IncludingBlock::new(U256::from(10), H256::from(10)));
+        assert!(!is_reorged(&mut including_block, Some(H256::from(11))));
+        assert!(!is_reorged(&mut including_block, None));
+
+        // misses are counted in a row only
+        assert!(!is_reorged(&mut including_block, Some(H256::from(10))));
+        for _ in 1..MAX_RECEIPT_MISSES {
+            assert!(!is_reorged(&mut including_block, Some(H256::from(11))));
+        }
+        assert!(is_reorged(&mut including_block, Some(H256::from(11))));
+        assert!(including_block.is_none());
+    }
+
+    #[test]
+    fn test_poll_reorged_transaction() {
+        let mut ethereum = MockEthereum {
+            block_number: U256::from(10),
+            receipt: Some((U256::from(10), H256::from(10))),
+            ..MockEthereum::default()
+        };
+        ethereum.block_hashes.insert(U256::from(10), H256::from(10));
+        let watch = Watch::new(ethereum);
+
+        // not enough confirmations
+        assert_eq!(watch.poll(), None);
+        assert_eq!(
+            *watch.including_block.lock(),
+            Some(IncludingBlock::new(U256::from(10), H256::from(10)))
+        );
+
+        // a lagging endpoint does not know the receipt yet
+        watch.ethereum.lock().receipt = None;
+        assert_eq!(watch.poll(), None);
+
+        // the including block is replaced on the canonical chain
+        watch
+            .ethereum
+            .lock()
+            .block_hashes
+            .insert(U256::from(10), H256::from(11));
+        for _ in 1..MAX_RECEIPT_MISSES {
+            assert_eq!(watch.poll(), None);
+        }
+        assert_eq!(
+            watch.poll(),
+            Some(EthereumMonitorResponse::TransactionReorged(H256::from(1)))
+        );
+        assert!(watch.including_block.lock().is_none());
+    }
+
+    #[test]
+    fn test_poll_reorged_transaction_at_confirmation_depth() {
+        let mut ethereum = MockEthereum {
+            block_number: U256::from(12),
+            receipt: Some((U256::from(10), H256::from(10))),
+            ..MockEthereum::default()
+        };
+        ethereum.block_hashes.insert(U256::from(10), H256::from(10));
+        let watch = Watch::new(ethereum);
+
+        assert_eq!(
+            watch.poll(),
+            Some(EthereumMonitorResponse::Transaction(H256::from(1)))
+        );
+
+        watch
+            .ethereum
+            .lock()
+            .block_hashes
+            .insert(U256::from(10), H256::from(11));
+        assert_eq!(
+            watch.poll(),
+            Some(EthereumMonitorResponse::TransactionReorged(H256::from(1)))
+        );
+        assert!(watch.including_block.lock().is_none());
+    }
+}
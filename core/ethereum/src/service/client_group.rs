@@ -13,45 +13,280 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::U256;
+use parking_lot::Mutex;
+use std::cmp;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use types::EthereumEndpointState;
 
 use super::error::{Error, ErrorKind};
 use super::ethereum_client::EthereumClient;
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthParams {
+    // an endpoint is ejected after this count of consecutive failed requests
+    pub max_consecutive_failures: u32,
+
+    // an endpoint is ejected once moving average of failed requests exceeds this rate
+    pub max_error_rate_percent: u32,
+
+    // an endpoint is ejected if it is behind the highest endpoint more than this count of blocks
+    pub max_block_lag: u64,
+
+    // how long an ejected endpoint is excluded before it is retried
+    pub ejection_duration: Duration,
+}
+
+impl Default for HealthParams {
+    fn default() -> HealthParams {
+        HealthParams {
+            max_consecutive_failures: 3,
+            max_error_rate_percent: 50,
+            max_block_lag: 5,
+            ejection_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct EndpointHealth {
+    latency_millis: u64,
+    error_rate_percent: u32,
+    request_count: u64,
+    error_count: u64,
+    consecutive_failures: u32,
+    block_number: Option<U256>,
+    block_lag: u64,
+    ejected_until: Option<Instant>,
+}
+
+impl EndpointHealth {
+    fn is_ejected(&self, now: Instant) -> bool {
+        match self.ejected_until {
+            Some(ejected_until) => ejected_until > now,
+            None => false,
+        }
+    }
+
+    fn eject(&mut self, endpoint: &str, duration: Duration, reason: &str) {
+        warn!(target: "system",
+            "Eject Ethereum endpoint {} for {} second(s), reason: {}",
+            endpoint, duration.as_secs(), reason);
+
+        // the endpoint is given a fresh start after ejection
+        self.ejected_until = Some(Instant::now() + duration);
+        self.consecutive_failures = 0;
+        self.error_rate_percent = 0;
+    }
+}
+
+/// Health of all endpoints, shared with in-flight requests
+#[derive(Clone)]
+pub struct HealthTable {
+    endpoints: Arc<Mutex<HashMap<String, EndpointHealth>>>,
+    params: HealthParams,
+}
+
+impl HealthTable {
+    fn new(params: HealthParams) -> HealthTable {
+        HealthTable {
+            endpoints: Arc::new(Mutex::new(HashMap::new())),
+            params,
+        }
+    }
+
+    /// Records the result of a request sent to the endpoint
+    pub fn report(&self, endpoint: &str, latency: Duration, succeeded: bool) {
+        let mut endpoints = self.endpoints.lock();
+        let health = match endpoints.get_mut(endpoint) {
+            Some(health) => health,
+            None => return,
+        };
+
+        let latency_millis = latency.as_secs() * 1000 + u64::from(latency.subsec_millis());
+        health.latency_millis = (health.latency_millis * 4 + latency_millis) / 5;
+        health.error_rate_percent =
+            (health.error_rate_percent * 9 + if succeeded { 0 } else { 100 }) / 10;
+        health.request_count += 1;
+
+        if succeeded {
+            health.consecutive_failures = 0;
+            return;
+        }
+
+        health.consecutive_failures += 1;
+        health.error_count += 1;
+        if health.is_ejected(Instant::now()) {
+            return;
+        }
+
+        if health.consecutive_failures >= self.params.max_consecutive_failures {
+            health.eject(
+                endpoint,
+                self.params.ejection_duration,
+                "too many consecutive failures",
+            );
+        } else if health.error_rate_percent > self.params.max_error_rate_percent {
+            health.eject(endpoint, self.params.ejection_duration, "high error rate");
+        }
+    }
+
+    /// Records the latest block number of the endpoint
+    pub fn report_block_number(&self, endpoint: &str, block_number: U256) {
+        if let Some(health) = self.endpoints.lock().get_mut(endpoint) {
+            health.block_number = Some(block_number);
+        }
+    }
+
+    /// Ejects endpoints which fall behind the highest endpoint
+    pub fn eject_lagging(&self) {
+        let mut endpoints = self.endpoints.lock();
+        let highest = match endpoints
+            .values()
+            .filter_map(|health| health.block_number)
+            .max()
+        {
+            Some(highest) => highest,
+            None => return,
+        };
+
+        let now = Instant::now();
+        for (endpoint, health) in endpoints.iter_mut() {
+            health.block_lag = match health.block_number {
+                Some(block_number) => (highest - block_number).low_u64(),
+                None => 0,
+            };
+
+            if health.block_lag > self.params.max_block_lag && !health.is_ejected(now) {
+                let reason = format!("{} block(s) behind", health.block_lag);
+                health.eject(endpoint, self.params.ejection_duration, &reason);
+            }
+        }
+    }
+
+    fn insert(&self, endpoint: String) {
+        self.endpoints.lock().insert(endpoint, EndpointHealth::default());
+    }
+
+    fn remove(&self, endpoint: &String) {
+        self.endpoints.lock().remove(endpoint);
+    }
+
+    fn states(&self) -> Vec<EthereumEndpointState> {
+        let now = Instant::now();
+        let mut states: Vec<_> = self
+            .endpoints
+            .lock()
+            .iter()
+            .map(|(endpoint, health)| EthereumEndpointState {
+                endpoint: endpoint.clone(),
+                healthy: !health.is_ejected(now),
+                latency_millis: health.latency_millis,
+                error_rate_percent: health.error_rate_percent,
+                request_count: health.request_count,
+                error_count: health.error_count,
+                block_number: health.block_number,
+                block_lag: health.block_lag,
+                ejected_secs: match health.ejected_until {
+                    Some(ejected_until) if ejected_until > now => {
+                        Some((ejected_until - now).as_secs())
+                    }
+                    _ => None,
+                },
+            })
+            .collect();
+
+        states.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+        states
+    }
+}
+
 pub struct ClientGroup {
     clients: HashMap<String, Arc<EthereumClient>>,
+    health: HealthTable,
+    next: AtomicUsize,
 }
 
 impl ClientGroup {
-    pub fn new(ethereum_nodes: Vec<String>) -> ClientGroup {
-        let clients = ethereum_nodes
-            .iter()
-            .fold(HashMap::new(), |mut clients, host| {
-                clients.insert(host.clone(), Arc::new(EthereumClient::new(host.clone())));
-                clients
-            });
+    pub fn new(ethereum_nodes: Vec<String>, params: HealthParams) -> ClientGroup {
+        let mut client_group = ClientGroup {
+            clients: HashMap::new(),
+            health: HealthTable::new(params),
+            next: AtomicUsize::new(0),
+        };
+
+        for host in ethereum_nodes {
+            client_group.add(host);
+        }
 
-        ClientGroup { clients }
+        client_group
     }
 
-    pub fn pick(&self) -> Result<Arc<EthereumClient>, Error> {
+    /// Picks an endpoint in round-robin order, see `pick_many`
+    pub fn pick(&self) -> Result<(String, Arc<EthereumClient>), Error> {
+        let mut picked = self.pick_many(1)?;
+        Ok(picked.remove(0))
+    }
+
+    /// Picks up to `count` distinct endpoints in round-robin order, ejected endpoints are skipped
+    /// unless all endpoints are ejected, in that case the endpoints which are ejected earliest
+    /// are picked
+    pub fn pick_many(&self, count: usize) -> Result<Vec<(String, Arc<EthereumClient>)>, Error> {
         if self.clients.is_empty() {
             return Err(Error::from(ErrorKind::EthereumClientGroupEmpty));
         }
 
-        // TODO better implementation
-        let client = self.clients.values().next().unwrap().clone();
-        Ok(client)
+        let mut endpoints: Vec<&String> = self.clients.keys().collect();
+        endpoints.sort();
+
+        let picked: Vec<&String> = {
+            let now = Instant::now();
+            let health = self.health.endpoints.lock();
+            let ejected_until =
+                |endpoint: &String| health.get(endpoint).and_then(|health| health.ejected_until);
+
+            let available: Vec<&String> = endpoints
+                .iter()
+                .cloned()
+                .filter(|endpoint| match ejected_until(endpoint) {
+                    Some(ejected_until) => ejected_until <= now,
+                    None => true,
+                })
+                .collect();
+
+            match available.is_empty() {
+                false => {
+                    let start = self.next.fetch_add(1, Ordering::Relaxed);
+                    (0..cmp::max(cmp::min(count, available.len()), 1))
+                        .map(|i| available[(start + i) % available.len()])
+                        .collect()
+                }
+                true => {
+                    endpoints.sort_by_key(|endpoint| ejected_until(endpoint));
+                    endpoints.into_iter().take(cmp::max(count, 1)).collect()
+                }
+            }
+        };
+
+        Ok(picked
+            .into_iter()
+            .map(|endpoint| (endpoint.clone(), self.clients[endpoint].clone()))
+            .collect())
     }
 
     pub fn add(&mut self, endpoint: String) -> bool {
+        self.health.insert(endpoint.clone());
         self.clients
             .insert(endpoint.clone(), Arc::new(EthereumClient::new(endpoint)))
             .is_some()
     }
 
     pub fn remove(&mut self, endpoint: &String) -> bool {
+        self.health.remove(endpoint);
         self.clients.remove(endpoint).is_some()
     }
 
@@ -63,7 +298,76 @@ impl ClientGroup {
         self.clients.keys().cloned().collect()
     }
 
+    pub fn clients(&self) -> Vec<(String, Arc<EthereumClient>)> {
+        self.clients
+            .iter()
+            .map(|(endpoint, client)| (endpoint.clone(), client.clone()))
+            .collect()
+    }
+
     pub fn len(&self) -> usize {
         self.clients.len()
     }
+
+    pub fn health(&self) -> HealthTable {
+        self.health.clone()
+    }
+
+    pub fn states(&self) -> Vec<EthereumEndpointState> {
+        self.health.states()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoints() -> Vec<String> {
+        vec!["http://127.0.0.1:8545".to_owned(), "http://127.0.0.1:8546".to_owned()]
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let group = ClientGroup::new(endpoints(), HealthParams::default());
+        let (first, _) = group.pick().unwrap();
+        let (second, _) = group.pick().unwrap();
+        let (third, _) = group.pick().unwrap();
+        assert_ne!(first, second);
+        assert_eq!(first, third);
+
+        let picked = group.pick_many(3).unwrap();
+        assert_eq!(picked.len(), 2);
+        assert_ne!(picked[0].0, picked[1].0);
+    }
+
+    #[test]
+    fn test_eject_failing_endpoint() {
+        let group = ClientGroup::new(endpoints(), HealthParams::default());
+        let failing = endpoints()[0].clone();
+        for _ in 0..3 {
+            group.health().report(&failing, Duration::from_millis(10), false);
+        }
+
+        for _ in 0..4 {
+            let (endpoint, _) = group.pick().unwrap();
+            assert_ne!(endpoint, failing);
+        }
+
+        let states = group.states();
+        assert!(!states[0].healthy);
+        assert!(states[1].healthy);
+    }
+
+    #[test]
+    fn test_eject_lagging_endpoint() {
+        let group = ClientGroup::new(endpoints(), HealthParams::default());
+        group.health().report_block_number(&endpoints()[0], U256::from(100));
+        group.health().report_block_number(&endpoints()[1], U256::from(90));
+        group.health().eject_lagging();
+
+        let states = group.states();
+        assert!(states[0].healthy);
+        assert!(!states[1].healthy);
+        assert_eq!(states[1].block_lag, 10);
+    }
 }
@@ -35,5 +35,15 @@ error_chain! {
             description("Ethereum client group is empty")
             display("Ethereum client group is empty")
         }
+
+        QuorumDisagreement(answers: String) {
+            description("Ethereum endpoints disagree")
+            display("Ethereum endpoints disagree, answers: {}", answers)
+        }
+
+        QuorumNotReached(matching: usize, required: usize) {
+            description("Not enough matching answers from Ethereum endpoints")
+            display("Got {} matching answer(s) from Ethereum endpoints, {} required", matching, required)
+        }
     }
 }
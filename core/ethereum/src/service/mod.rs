@@ -15,9 +15,12 @@
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 mod client_group;
 mod error;
+mod quorum;
 mod service;
 
 use super::client as ethereum_client;
 
 pub use self::error::*;
+pub use self::client_group::HealthParams;
+pub use self::quorum::QuorumParams;
 pub use self::service::{Params, Service};
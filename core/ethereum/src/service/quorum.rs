@@ -0,0 +1,164 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use futures::stream::FuturesUnordered;
+use futures::{Async, Future, Poll, Stream};
+use std::fmt::Debug;
+
+use super::error::{Error, ErrorKind};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuorumParams {
+    // count of endpoints a query is sent to
+    pub size: usize,
+
+    // count of matching answers required to resolve a query
+    pub threshold: usize,
+}
+
+/// Resolves once `threshold` answers from different endpoints match, fails once the threshold
+/// can not be reached anymore
+pub struct QuorumRead<T> {
+    queries: FuturesUnordered<Box<Future<Item = T, Error = Error> + Send>>,
+    remaining: usize,
+    threshold: usize,
+
+    // distinct answers and their counts
+    answers: Vec<(T, usize)>,
+    last_error: Option<Error>,
+}
+
+impl<T> QuorumRead<T>
+where
+    T: PartialEq + Debug,
+{
+    pub fn new(
+        queries: Vec<Box<Future<Item = T, Error = Error> + Send>>,
+        threshold: usize,
+    ) -> QuorumRead<T> {
+        QuorumRead {
+            remaining: queries.len(),
+            queries: queries.into_iter().collect(),
+            threshold,
+            answers: Vec::new(),
+            last_error: None,
+        }
+    }
+
+    fn add_answer(&mut self, answer: T) {
+        match self.answers.iter().position(|(value, _)| *value == answer) {
+            Some(index) => self.answers[index].1 += 1,
+            None => self.answers.push((answer, 1)),
+        }
+    }
+
+    fn best_count(&self) -> usize {
+        self.answers.iter().map(|(_, count)| *count).max().unwrap_or(0)
+    }
+
+    fn failure(&mut self) -> Error {
+        if self.answers.len() > 1 {
+            return Error::from(ErrorKind::QuorumDisagreement(format!("{:?}", self.answers)));
+        }
+
+        match self.last_error.take() {
+            Some(err) if self.answers.is_empty() => err,
+            _ => Error::from(ErrorKind::QuorumNotReached(self.best_count(), self.threshold)),
+        }
+    }
+}
+
+impl<T> Future for QuorumRead<T>
+where
+    T: PartialEq + Debug,
+{
+    type Item = T;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            if let Some(index) = self
+                .answers
+                .iter()
+                .position(|(_, count)| *count >= self.threshold)
+            {
+                return Ok(Async::Ready(self.answers.swap_remove(index).0));
+            }
+
+            if self.best_count() + self.remaining < self.threshold {
+                return Err(self.failure());
+            }
+
+            match self.queries.poll() {
+                Ok(Async::Ready(Some(answer))) => {
+                    self.remaining -= 1;
+                    self.add_answer(answer);
+                }
+                Err(err) => {
+                    self.remaining -= 1;
+                    self.last_error = Some(err);
+                }
+                Ok(Async::Ready(None)) => return Err(self.failure()),
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+
+    fn answers(
+        answers: Vec<Result<u32, Error>>,
+    ) -> Vec<Box<Future<Item = u32, Error = Error> + Send>> {
+        answers
+            .into_iter()
+            .map(|answer| {
+                Box::new(future::result(answer)) as Box<Future<Item = _, Error = _> + Send>
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_quorum_reached() {
+        let read = QuorumRead::new(answers(vec![Ok(1), Ok(2), Ok(1)]), 2);
+        assert_eq!(read.wait().unwrap(), 1);
+
+        let failed = Err(Error::from(ErrorKind::EthereumClientGroupEmpty));
+        let read = QuorumRead::new(answers(vec![Ok(1), failed, Ok(1)]), 2);
+        assert_eq!(read.wait().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_quorum_disagreement() {
+        let read = QuorumRead::new(answers(vec![Ok(1), Ok(2), Ok(3)]), 2);
+        match read.wait() {
+            Err(Error(ErrorKind::QuorumDisagreement(_), _)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_quorum_not_reached() {
+        let failed = || Err(Error::from(ErrorKind::EthereumClientGroupEmpty));
+        let read = QuorumRead::new(answers(vec![Ok(1), failed(), failed()]), 2);
+        match read.wait() {
+            Err(Error(ErrorKind::QuorumNotReached(1, 2), _)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
@@ -13,30 +13,49 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use parking_lot::Mutex;
+use std::fmt::Debug;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use ethereum_types::{Address, H256, U256};
-use futures::{self, future, Future};
+use futures::{future, Async, Future, Poll, Stream};
+use tokio::timer::Interval;
 
 use contract_abi::ERC1376AbiEncoder;
 use traits::{
     AccountStateFuture, BoolFuture, BytesFuture, EthRpcTransactionConfirmationFuture,
-    EthRpcTransactionFuture, EthRpcTransactionReceiptFuture, H256Future, U256Future,
+    EthRpcTransactionFuture, EthRpcTransactionReceiptFuture, H256Future, StringFuture,
+    U256Future, U256VecFuture,
 };
-use types::{BlockId, Currency, EthRpcCallRequest, GasEstimation};
+use types::{BlockId, Currency, EthRpcCallRequest, EthereumEndpointState, GasEstimation};
 
 use super::error::Error;
 
-use super::client_group::ClientGroup;
-use super::ethereum_client::EthereumClient;
+use super::client_group::{ClientGroup, HealthParams};
+use super::quorum::{QuorumParams, QuorumRead};
+use super::ethereum_client::{
+    EthereumClient, Error as EthereumClientError, ErrorKind as EthereumClientErrorKind,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Params {
     pub ethereum_nodes: Vec<String>,
+    pub health_check_interval: Duration,
+    pub health_params: HealthParams,
+
+    // security-relevant reads are sent to multiple endpoints if quorum is set
+    pub quorum: Option<QuorumParams>,
 }
 
+type HealthCheck = Box<Future<Item = (), Error = ()> + Send>;
+
 pub struct Service {
     client_group: ClientGroup,
+    quorum: Option<QuorumParams>,
+
+    health_check_ticker: Interval,
+    health_check: Mutex<Option<HealthCheck>>,
 }
 
 impl traits::EthereumService for Service {
@@ -61,30 +80,168 @@ impl traits::EthereumService for Service {
     fn endpoint_count(&self) -> usize {
         self.client_group.len()
     }
+
+    fn endpoint_states(&self) -> Vec<EthereumEndpointState> {
+        self.client_group.states()
+    }
 }
 
 impl Service {
     pub fn new(params: Params) -> Result<Service, Error> {
-        let client_group = ClientGroup::new(params.ethereum_nodes);
+        let client_group = ClientGroup::new(params.ethereum_nodes, params.health_params);
+
+        Ok(Service {
+            client_group,
+            quorum: params.quorum,
+            health_check_ticker: Interval::new_interval(params.health_check_interval),
+            health_check: Mutex::new(None),
+        })
+    }
+
+    /// Sends a request with a picked client
+    fn with_client<F, R>(&self, f: F) -> Box<Future<Item = R::Item, Error = Error> + Send>
+    where
+        F: FnOnce(Arc<EthereumClient>) -> R,
+        R: Future<Error = EthereumClientError> + Send + 'static,
+        R::Item: Send + 'static,
+    {
+        match self.client_group.pick() {
+            Ok((endpoint, client)) => self.report_health(endpoint, f(client)),
+            Err(err) => Box::new(future::err(err)),
+        }
+    }
+
+    /// Sends a request to endpoints of quorum and resolves with the answer agreed by them, falls
+    /// back to a single endpoint if quorum is not set
+    fn with_quorum<F, R>(&self, f: F) -> Box<Future<Item = R::Item, Error = Error> + Send>
+    where
+        F: Fn(Arc<EthereumClient>) -> R,
+        R: Future<Error = EthereumClientError> + Send + 'static,
+        R::Item: PartialEq + Debug + Send + 'static,
+    {
+        let quorum = match self.quorum {
+            Some(ref quorum) => quorum.clone(),
+            None => return self.with_client(f),
+        };
+
+        let picked = match self.client_group.pick_many(quorum.size) {
+            Ok(picked) => picked,
+            Err(err) => return Box::new(future::err(err)),
+        };
+
+        let queries = picked
+            .into_iter()
+            .map(|(endpoint, client)| self.report_health(endpoint, f(client)))
+            .collect();
 
-        Ok(Service { client_group })
+        Box::new(QuorumRead::new(queries, quorum.threshold))
     }
 
-    #[inline]
-    fn pick_client(&self) -> Result<Arc<EthereumClient>, Error> {
-        self.client_group.pick()
+    fn report_health<R>(
+        &self,
+        endpoint: String,
+        request: R,
+    ) -> Box<Future<Item = R::Item, Error = Error> + Send>
+    where
+        R: Future<Error = EthereumClientError> + Send + 'static,
+        R::Item: Send + 'static,
+    {
+        let health = self.client_group.health();
+        let started_at = Instant::now();
+        Box::new(request.then(move |result| {
+            let succeeded = match result {
+                Ok(_) => true,
+                Err(ref err) => !is_endpoint_failure(err),
+            };
+            health.report(&endpoint, started_at.elapsed(), succeeded);
+            result.map_err(Error::from)
+        }))
+    }
+
+    fn new_health_check(&self) -> HealthCheck {
+        let health = self.client_group.health();
+        let checks: Vec<_> = self
+            .client_group
+            .clients()
+            .into_iter()
+            .map(|(endpoint, client)| {
+                let health = health.clone();
+                let started_at = Instant::now();
+                client.eth_block_number().then(move |result| {
+                    match result {
+                        Ok(block_number) => {
+                            health.report(&endpoint, started_at.elapsed(), true);
+                            health.report_block_number(&endpoint, block_number);
+                        }
+                        Err(err) => {
+                            debug!(target: "system",
+                                "Health check of Ethereum endpoint {} failed, error: {}",
+                                endpoint, err);
+                            health.report(&endpoint, started_at.elapsed(), false);
+                        }
+                    }
+                    Ok::<(), ()>(())
+                })
+            })
+            .collect();
+
+        Box::new(future::join_all(checks).map(move |_| health.eject_lagging()))
+    }
+}
+
+// errors returned by Ethereum node itself do not mean the endpoint is unhealthy
+fn is_endpoint_failure(err: &EthereumClientError) -> bool {
+    match err.kind() {
+        EthereumClientErrorKind::JsonRpc(_)
+        | EthereumClientErrorKind::ContractAbi(_)
+        | EthereumClientErrorKind::FromHex(_)
+        | EthereumClientErrorKind::NoSuchField(_)
+        | EthereumClientErrorKind::ParseHex => false,
+        _ => true,
     }
+}
+
+impl Stream for Service {
+    type Item = ();
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            match self.health_check_ticker.poll() {
+                Ok(Async::Ready(Some(_))) => {
+                    // skip this tick if last health check is not finished
+                    if self.health_check.lock().is_none() {
+                        let health_check = self.new_health_check();
+                        *self.health_check.lock() = Some(health_check);
+                    }
+                }
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => break,
+                Err(err) => {
+                    warn!(target: "system", "Health check ticker error: {}", err);
+                    break;
+                }
+            }
+        }
+
+        let finished = match *self.health_check.lock() {
+            Some(ref mut health_check) => match health_check.poll() {
+                Ok(Async::NotReady) => false,
+                Ok(Async::Ready(_)) | Err(_) => true,
+            },
+            None => false,
+        };
+
+        if finished {
+            *self.health_check.lock() = None;
+        }
 
-    #[inline]
-    fn pick_client_future(&self) -> future::FutureResult<Arc<EthereumClient>, Error> {
-        futures::done(self.pick_client())
+        Ok(Async::NotReady)
     }
 }
 
 impl traits::GasEstimator<<Service as traits::EthereumService>::Error> for Service {
     fn block_gas_limit(&self, block_id: BlockId) -> U256Future<Error> {
-        let client = self.pick_client_future();
-        Box::new(client.and_then(move |client| client.eth_get_block_gas_limit(block_id).from_err()))
+        self.with_client(move |client| client.eth_get_block_gas_limit(block_id))
     }
 
     fn estimate_gas(
@@ -125,8 +282,18 @@ impl traits::GasEstimator<<Service as traits::EthereumService>::Error> for Servi
             }
         };
 
-        let client = self.pick_client_future();
-        Box::new(client.and_then(move |client| client.eth_estimate_gas(call).from_err()))
+        self.with_client(move |client| client.eth_estimate_gas(call))
+    }
+
+    fn gas_price(&self) -> U256Future<<Service as traits::EthereumService>::Error> {
+        self.with_client(move |client| client.eth_gas_price())
+    }
+
+    fn block_gas_prices(
+        &self,
+        block_id: BlockId,
+    ) -> U256VecFuture<<Service as traits::EthereumService>::Error> {
+        self.with_client(move |client| client.eth_get_block_gas_prices(block_id))
     }
 }
 
@@ -136,14 +303,11 @@ impl traits::AccountStateProvider<<Service as traits::EthereumService>::Error> f
         address: Address,
         currency: Currency,
     ) -> U256Future<<Service as traits::EthereumService>::Error> {
-        let client = self.pick_client_future();
         match currency {
-            Currency::Ether => {
-                Box::new(client.and_then(move |client| client.eth_balance_of(&address).from_err()))
-            }
-            Currency::Token(token_address) => Box::new(client.and_then(move |client| {
-                client.token_balance_of(&token_address, &address).from_err()
-            })),
+            Currency::Ether => self.with_quorum(move |client| client.eth_balance_of(&address)),
+            Currency::Token(token_address) => self.with_quorum(move |client| {
+                client.token_balance_of(&token_address, &address)
+            }),
         }
     }
 
@@ -152,14 +316,11 @@ impl traits::AccountStateProvider<<Service as traits::EthereumService>::Error> f
         address: Address,
         currency: Currency,
     ) -> U256Future<<Service as traits::EthereumService>::Error> {
-        let client_future = self.pick_client_future();
         match currency {
-            Currency::Ether => Box::new(
-                client_future.and_then(move |client| client.eth_nonce_of(&address).from_err()),
-            ),
-            Currency::Token(token_address) => Box::new(client_future.and_then(move |client| {
-                client.token_nonce_of(&token_address, &address).from_err()
-            })),
+            Currency::Ether => self.with_quorum(move |client| client.eth_nonce_of(&address)),
+            Currency::Token(token_address) => self.with_quorum(move |client| {
+                client.token_nonce_of(&token_address, &address)
+            }),
         }
     }
 
@@ -168,14 +329,11 @@ impl traits::AccountStateProvider<<Service as traits::EthereumService>::Error> f
         address: Address,
         currency: Currency,
     ) -> AccountStateFuture<<Service as traits::EthereumService>::Error> {
-        let client_future = self.pick_client_future();
         match currency {
-            Currency::Ether => Box::new(
-                client_future.and_then(move |client| client.eth_state_of(&address).from_err()),
-            ),
-            Currency::Token(token_address) => Box::new(client_future.and_then(move |client| {
-                client.token_state_of(&token_address, &address).from_err()
-            })),
+            Currency::Ether => self.with_client(move |client| client.eth_state_of(&address)),
+            Currency::Token(token_address) => self.with_client(move |client| {
+                client.token_state_of(&token_address, &address)
+            }),
         }
     }
 
@@ -183,10 +341,15 @@ impl traits::AccountStateProvider<<Service as traits::EthereumService>::Error> f
         &self,
         address: Address,
     ) -> BytesFuture<<Service as traits::EthereumService>::Error> {
-        Box::new(
-            self.pick_client_future()
-                .and_then(move |client| client.eth_code_of(&address).from_err()),
-        )
+        self.with_client(move |client| client.eth_code_of(&address))
+    }
+
+    fn call_contract(
+        &self,
+        contract: Address,
+        data: Vec<u8>,
+    ) -> BytesFuture<<Service as traits::EthereumService>::Error> {
+        self.with_client(move |client| client.eth_read_contract(&contract, &data))
     }
 }
 
@@ -195,19 +358,34 @@ impl traits::TokenStateProvider<<Service as traits::EthereumService>::Error> for
         &self,
         token_contract: Address,
     ) -> BoolFuture<<Service as traits::EthereumService>::Error> {
-        Box::new(
-            self.pick_client_future()
-                .and_then(move |client| client.token_delegate_enable(&token_contract).from_err()),
-        )
+        self.with_quorum(move |client| client.token_delegate_enable(&token_contract))
+    }
+
+    fn token_symbol(
+        &self,
+        token_contract: Address,
+    ) -> StringFuture<<Service as traits::EthereumService>::Error> {
+        self.with_client(move |client| client.token_symbol(&token_contract))
+    }
+
+    fn token_decimals(
+        &self,
+        token_contract: Address,
+    ) -> U256Future<<Service as traits::EthereumService>::Error> {
+        self.with_client(move |client| client.token_decimals(&token_contract))
     }
 }
 
 impl traits::BlockInfoProvider<<Service as traits::EthereumService>::Error> for Service {
     fn get_block_number(&self) -> U256Future<<Service as traits::EthereumService>::Error> {
-        Box::new(
-            self.pick_client_future()
-                .and_then(move |client| client.eth_block_number().from_err()),
-        )
+        self.with_client(move |client| client.eth_block_number())
+    }
+
+    fn get_block_hash(
+        &self,
+        block_number: U256,
+    ) -> H256Future<<Service as traits::EthereumService>::Error> {
+        self.with_client(move |client| client.eth_get_block_hash(&block_number))
     }
 }
 
@@ -216,31 +394,21 @@ impl traits::TransactionFetcher<<Service as traits::EthereumService>::Error> for
         &self,
         tx_hash: H256,
     ) -> EthRpcTransactionFuture<<Service as traits::EthereumService>::Error> {
-        Box::new(
-            self.pick_client_future()
-                .and_then(move |client| client.eth_get_transaction_by_hash(&tx_hash).from_err()),
-        )
+        self.with_client(move |client| client.eth_get_transaction_by_hash(&tx_hash))
     }
 
     fn get_transaction_receipt(
         &self,
         tx_hash: H256,
     ) -> EthRpcTransactionReceiptFuture<<Service as traits::EthereumService>::Error> {
-        Box::new(
-            self.pick_client_future()
-                .and_then(move |client| client.eth_get_transaction_receipt(&tx_hash).from_err()),
-        )
+        self.with_client(move |client| client.eth_get_transaction_receipt(&tx_hash))
     }
 
     fn get_transaction_confirmation(
         &self,
         tx_hash: H256,
     ) -> EthRpcTransactionConfirmationFuture<<Service as traits::EthereumService>::Error> {
-        Box::new(
-            self.pick_client_future().and_then(move |client| {
-                client.eth_get_transaction_confirmation(&tx_hash).from_err()
-            }),
-        )
+        self.with_client(move |client| client.eth_get_transaction_confirmation(&tx_hash))
     }
 }
 
@@ -249,9 +417,6 @@ impl traits::TransactionBroadcaster<<Service as traits::EthereumService>::Error>
         &self,
         tx: ethcore_transaction::SignedTransaction,
     ) -> H256Future<<Service as traits::EthereumService>::Error> {
-        Box::new(
-            self.pick_client_future()
-                .and_then(move |client| client.eth_send_raw_transaction(&tx).from_err()),
-        )
+        self.with_client(move |client| client.eth_send_raw_transaction(&tx))
     }
 }
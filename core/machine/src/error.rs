@@ -21,6 +21,8 @@ use pricer::Error as PricerError;
 
 error_chain! {
     foreign_links {
+        Io(std::io::Error);
+        Json(serde_json::Error);
         TimerError(tokio_timer::Error);
         Collation(CollationError);
         EthereumService(EthereumServiceError);
@@ -0,0 +1,592 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::{Address, H256, U256};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use collation::ClosedCollation;
+use types::{
+    CollationFilter, CollationOutcome, CollationPage, CollationRecord, CollationRecordList,
+    CollationRequestFailure,
+};
+
+use super::Error;
+
+// maximum number of records returned in a page
+const MAX_PAGE_LIMIT: usize = 100;
+
+// history file is not compacted at runtime until it has at least this many lines
+const MIN_COMPACTION_LINES: usize = 1024;
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+fn record_key(record: &CollationRecord) -> H256 {
+    match record.transaction_hashes.first() {
+        Some(hash) => *hash,
+        None => record.transaction_hash,
+    }
+}
+
+// fields of a record which are kept in memory for querying
+struct RecordIndex {
+    // offset of the latest version of the record in history file
+    offset: u64,
+    relayer: Address,
+    outcome: CollationOutcome,
+    broadcasted_at: u64,
+}
+
+impl RecordIndex {
+    fn new(record: &CollationRecord, offset: u64) -> RecordIndex {
+        RecordIndex {
+            offset,
+            relayer: record.relayer,
+            outcome: record.outcome,
+            broadcasted_at: record.broadcasted_at,
+        }
+    }
+
+    // conditions except request hash, which is only checked against the whole record
+    fn matches(&self, filter: &CollationFilter) -> bool {
+        filter
+            .relayer
+            .map_or(true, |relayer| self.relayer == relayer)
+            && filter
+                .outcome
+                .map_or(true, |outcome| self.outcome == outcome)
+            && filter
+                .from_time
+                .map_or(true, |time| self.broadcasted_at >= time)
+            && filter
+                .to_time
+                .map_or(true, |time| self.broadcasted_at <= time)
+    }
+}
+
+struct HistoryFile {
+    path: PathBuf,
+    reader: File,
+    writer: BufWriter<File>,
+    // length of the file
+    len: u64,
+    // count of lines including outdated versions of records
+    line_count: usize,
+}
+
+impl HistoryFile {
+    fn open(path: PathBuf) -> Result<HistoryFile, Error> {
+        let writer = OpenOptions::new().append(true).create(true).open(&path)?;
+        let reader = File::open(&path)?;
+        let len = reader.metadata()?.len();
+        Ok(HistoryFile {
+            path,
+            reader,
+            writer: BufWriter::new(writer),
+            len,
+            line_count: 0,
+        })
+    }
+
+    fn read(&self, offset: u64) -> Result<CollationRecord, Error> {
+        let mut reader = &self.reader;
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut line = String::new();
+        BufReader::new(reader).read_line(&mut line)?;
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    // returns offset of the appended record
+    fn append(&mut self, record: &CollationRecord) -> Result<u64, Error> {
+        let offset = self.len;
+        let written = write_record(&mut self.writer, record)?;
+        self.writer.flush()?;
+        self.len += written;
+        self.line_count += 1;
+        Ok(offset)
+    }
+}
+
+// returns count of written bytes
+fn write_record<W: Write>(writer: &mut W, record: &CollationRecord) -> Result<u64, Error> {
+    let mut line = serde_json::to_vec(record)?;
+    line.push(b'\n');
+    writer.write_all(&line)?;
+    Ok(line.len() as u64)
+}
+
+/// History of broadcasted collations.
+///
+/// If a history file is set, each change of a record is appended into the file as a JSON line,
+/// all records are kept in the file and only the latest `max_cached_records` records are
+/// cached in memory. The file is compacted once most of its lines are outdated. Otherwise only
+/// the latest `max_cached_records` records are kept.
+pub struct CollationHistory {
+    // records indexed by hash of the first broadcasted transaction
+    index: HashMap<H256, RecordIndex>,
+    // keys of records from the oldest to the newest
+    order: VecDeque<H256>,
+    // the latest records
+    cache: HashMap<H256, CollationRecord>,
+    max_cached_records: usize,
+    // hash of each broadcasted transaction to key of its record
+    by_transaction: HashMap<H256, H256>,
+    // relayer and nonce of each pending record to its key
+    pending: HashMap<(Address, U256), H256>,
+    file: Option<HistoryFile>,
+}
+
+impl CollationHistory {
+    /// Creates an in-memory history which keeps at most `max_records` records
+    pub fn new(max_records: usize) -> CollationHistory {
+        CollationHistory {
+            index: HashMap::new(),
+            order: VecDeque::new(),
+            cache: HashMap::new(),
+            max_cached_records: usize::max(max_records, 1),
+            by_transaction: HashMap::new(),
+            pending: HashMap::new(),
+            file: None,
+        }
+    }
+
+    /// Opens a history file and restores records in it, the latest `max_cached_records`
+    /// records are cached in memory.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        max_cached_records: usize,
+    ) -> Result<CollationHistory, Error> {
+        let path = path.as_ref().to_path_buf();
+        let mut history = CollationHistory::new(max_cached_records);
+        history.file = Some(HistoryFile::open(path.clone())?);
+        history.replay(&path)?;
+        history.compact()?;
+
+        let cached: Vec<H256> = history
+            .order
+            .iter()
+            .rev()
+            .take(history.max_cached_records)
+            .cloned()
+            .collect();
+        for key in cached {
+            if let Some(record) = history.load(&key)? {
+                history.cache.insert(key, record);
+            }
+        }
+
+        info!(target: "relayer",
+            "Open collation history {:?}, {} record(s) found", path, history.len());
+        Ok(history)
+    }
+
+    fn replay(&mut self, path: &PathBuf) -> Result<(), Error> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut offset = 0u64;
+        let mut line = String::new();
+        for line_number in 1.. {
+            line.clear();
+            let len = reader.read_line(&mut line)? as u64;
+            if len == 0 {
+                break;
+            }
+            let line_offset = offset;
+            offset += len;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // a broken line is usually caused by crashing while writing, just skip it
+            let mut record: CollationRecord = match serde_json::from_str(&line) {
+                Ok(record) => record,
+                Err(err) => {
+                    warn!(target: "relayer",
+                        "Skip broken record at line {} of collation history {:?}: {}",
+                        line_number, path, err);
+                    continue;
+                }
+            };
+
+            // pending transactions are not restored, their outcome can not be resolved anymore,
+            // the record is rewritten by compaction
+            if record.outcome == CollationOutcome::Pending {
+                record.outcome = CollationOutcome::Unknown;
+            }
+
+            let key = record_key(&record);
+            self.index_transactions(key, &record);
+            if self
+                .index
+                .insert(key, RecordIndex::new(&record, line_offset))
+                .is_none()
+            {
+                self.order.push_back(key);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rewrites the latest version of all records into a new history file
+    fn compact(&mut self) -> Result<(), Error> {
+        let path = match self.file {
+            Some(ref file) => file.path.clone(),
+            None => return Ok(()),
+        };
+
+        // write records into a temporary file and then replace the original one
+        let tmp_path = path.with_extension("tmp");
+        let mut offsets = Vec::with_capacity(self.order.len());
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            let mut offset = 0u64;
+            for key in self.order.iter() {
+                let mut record = match self.load(key)? {
+                    Some(record) => record,
+                    None => continue,
+                };
+                if record.outcome == CollationOutcome::Pending
+                    && !self.pending.contains_key(&(record.relayer, record.nonce))
+                {
+                    record.outcome = CollationOutcome::Unknown;
+                }
+
+                offsets.push((*key, RecordIndex::new(&record, offset)));
+                offset += write_record(&mut writer, &record)?;
+            }
+            writer.flush()?;
+        }
+        fs::rename(&tmp_path, &path)?;
+
+        let mut file = HistoryFile::open(path)?;
+        file.line_count = offsets.len();
+        self.file = Some(file);
+        for (key, index) in offsets {
+            self.index.insert(key, index);
+        }
+        Ok(())
+    }
+
+    fn need_compaction(&self) -> bool {
+        match self.file {
+            Some(ref file) => {
+                file.line_count >= usize::max(2 * self.order.len(), MIN_COMPACTION_LINES)
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the latest version of a record
+    fn load(&self, key: &H256) -> Result<Option<CollationRecord>, Error> {
+        if let Some(record) = self.cache.get(key) {
+            return Ok(Some(record.clone()));
+        }
+
+        match (self.index.get(key), self.file.as_ref()) {
+            (Some(index), Some(file)) => Ok(Some(file.read(index.offset)?)),
+            _ => Ok(None),
+        }
+    }
+
+    fn index_transactions(&mut self, key: H256, record: &CollationRecord) {
+        for hash in record.transaction_hashes.iter() {
+            self.by_transaction.insert(*hash, key);
+        }
+        self.by_transaction.insert(record.transaction_hash, key);
+    }
+
+    /// Stores a new version of a record
+    fn save(&mut self, key: H256, record: CollationRecord) {
+        let offset = match self.file {
+            Some(ref mut file) => match file.append(&record) {
+                Ok(offset) => offset,
+                Err(err) => {
+                    warn!(target: "relayer",
+                        "Failed to write collation history {:?}, error: {}", file.path, err);
+                    // the record is only kept in memory
+                    self.cache.insert(key, record.clone());
+                    self.index.get(&key).map_or(0, |index| index.offset)
+                }
+            },
+            None => 0,
+        };
+
+        let is_new = self
+            .index
+            .insert(key, RecordIndex::new(&record, offset))
+            .is_none();
+        if is_new {
+            self.order.push_back(key);
+            self.cache.insert(key, record);
+            self.shrink();
+        } else if self.cache.contains_key(&key) {
+            self.cache.insert(key, record);
+        }
+
+        if self.need_compaction() {
+            if let Err(err) = self.compact() {
+                warn!(target: "relayer", "Failed to compact collation history, error: {}", err);
+            }
+        }
+    }
+
+    fn update<F>(&mut self, key: H256, update: F)
+    where
+        F: FnOnce(&mut CollationRecord),
+    {
+        match self.load(&key) {
+            Ok(Some(mut record)) => {
+                update(&mut record);
+                self.save(key, record);
+            }
+            Ok(None) => {}
+            Err(err) => {
+                warn!(target: "relayer",
+                    "Failed to read collation record {:?} from history, error: {}", key, err);
+            }
+        }
+    }
+
+    // records out of cache are kept in history file, or removed if there is no history file
+    fn shrink(&mut self) {
+        while self.order.len() > self.max_cached_records {
+            let key = self.order[self.order.len() - self.max_cached_records - 1];
+            if self.file.is_some() {
+                self.cache.remove(&key);
+                return;
+            }
+
+            self.order.pop_front();
+            self.index.remove(&key);
+            if let Some(record) = self.cache.remove(&key) {
+                for hash in record.transaction_hashes.iter() {
+                    self.by_transaction.remove(hash);
+                }
+                self.by_transaction.remove(&record.transaction_hash);
+                if self.pending.get(&(record.relayer, record.nonce)) == Some(&key) {
+                    self.pending.remove(&(record.relayer, record.nonce));
+                }
+            }
+        }
+    }
+
+    /// Records a newly broadcasted collation transaction
+    pub fn record_broadcast(&mut self, relayer: &Address, collation: &ClosedCollation, hash: H256) {
+        let now = unix_now();
+        let transaction = collation.transaction();
+        let record = CollationRecord {
+            transaction_hash: hash,
+            transaction_hashes: vec![hash],
+            relayer: *relayer,
+            nonce: transaction.nonce,
+            gas_price: transaction.gas_price,
+            gas_limit: transaction.gas,
+            gas_used: None,
+            block_number: None,
+            request_hashes: collation.request_hashes(),
+            failed_requests: Vec::new(),
+            outcome: CollationOutcome::Pending,
+            reason: None,
+            broadcasted_at: now,
+            updated_at: now,
+            finished_at: None,
+        };
+
+        self.index_transactions(hash, &record);
+        self.pending.insert((*relayer, transaction.nonce), hash);
+        self.save(hash, record);
+    }
+
+    /// Records a pending collation transaction which is broadcasted again with a new hash
+    pub fn record_rebroadcast(
+        &mut self,
+        relayer: &Address,
+        collation: &ClosedCollation,
+        hash: H256,
+    ) {
+        let transaction = collation.transaction();
+        let key = match self.pending.get(&(*relayer, transaction.nonce)) {
+            Some(key) => *key,
+            None => return,
+        };
+
+        self.by_transaction.insert(hash, key);
+        self.update(key, |record| {
+            record.transaction_hash = hash;
+            if !record.transaction_hashes.contains(&hash) {
+                record.transaction_hashes.push(hash);
+            }
+            record.gas_price = transaction.gas_price;
+            record.gas_limit = transaction.gas;
+            record.updated_at = unix_now();
+        });
+    }
+
+    /// Records outcome of a mined collation transaction, the outcome is resolved from its
+    /// receipt
+    pub fn record_executed(
+        &mut self,
+        hash: &H256,
+        block_number: Option<U256>,
+        gas_used: Option<U256>,
+        outcomes: &[(H256, Option<String>)],
+    ) {
+        let key = match self.by_transaction.get(hash) {
+            Some(key) => *key,
+            None => return,
+        };
+
+        let hash = *hash;
+        let mut finished = None;
+        self.update(key, |record| {
+            let now = unix_now();
+            record.failed_requests = outcomes
+                .iter()
+                .filter_map(|(hash, reason)| {
+                    reason.as_ref().map(|reason| CollationRequestFailure {
+                        hash: *hash,
+                        reason: reason.clone(),
+                    })
+                })
+                .collect();
+            record.outcome = match record.failed_requests.len() < outcomes.len() {
+                true => CollationOutcome::Executed,
+                false => CollationOutcome::Failed,
+            };
+            record.transaction_hash = hash;
+            record.block_number = block_number;
+            record.gas_used = gas_used;
+            record.updated_at = now;
+            record.finished_at = Some(now);
+            finished = Some((record.relayer, record.nonce));
+        });
+        if let Some(pending) = finished {
+            self.pending.remove(&pending);
+        }
+    }
+
+    /// Records a mined collation transaction whose receipt can not be fetched
+    pub fn record_unresolved(&mut self, hash: &H256, reason: String) {
+        let key = match self.by_transaction.get(hash) {
+            Some(key) => *key,
+            None => return,
+        };
+
+        let mut finished = None;
+        self.update(key, |record| {
+            let now = unix_now();
+            record.outcome = CollationOutcome::Unknown;
+            record.reason = Some(reason);
+            record.updated_at = now;
+            record.finished_at = Some(now);
+            finished = Some((record.relayer, record.nonce));
+        });
+        if let Some(pending) = finished {
+            self.pending.remove(&pending);
+        }
+    }
+
+    /// Records a collation transaction which is dropped
+    pub fn record_dropped(&mut self, relayer: &Address, nonce: &U256, reason: String) {
+        let key = match self.pending.remove(&(*relayer, *nonce)) {
+            Some(key) => key,
+            None => return,
+        };
+
+        self.update(key, |record| {
+            let now = unix_now();
+            record.outcome = CollationOutcome::Dropped;
+            record.reason = Some(reason);
+            record.updated_at = now;
+            record.finished_at = Some(now);
+        });
+    }
+
+    /// Returns count of records
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Returns the record which contains a transaction with given hash
+    pub fn get(&self, transaction_hash: &H256) -> Option<CollationRecord> {
+        let key = self.by_transaction.get(transaction_hash)?;
+        match self.load(key) {
+            Ok(record) => record,
+            Err(err) => {
+                warn!(target: "relayer",
+                    "Failed to read collation record {:?} from history, error: {}", key, err);
+                None
+            }
+        }
+    }
+
+    /// Returns a page of matched records, from the newest to the oldest. Only records in the
+    /// page are read from history file unless request hash is in the filter.
+    pub fn query(&self, filter: &CollationFilter, page: &CollationPage) -> CollationRecordList {
+        let limit = usize::min(page.limit, MAX_PAGE_LIMIT);
+        let mut total = 0;
+        let mut records = Vec::new();
+
+        for key in self.order.iter().rev() {
+            match self.index.get(key) {
+                Some(index) if index.matches(filter) => {}
+                _ => continue,
+            }
+
+            let in_page = total >= page.offset && records.len() < limit;
+            let record = match in_page || filter.request_hash.is_some() {
+                true => match self.load(key) {
+                    Ok(Some(record)) => Some(record),
+                    Ok(None) => continue,
+                    Err(err) => {
+                        warn!(target: "relayer",
+                            "Failed to read collation record {:?} from history, error: {}",
+                            key, err);
+                        continue;
+                    }
+                },
+                false => None,
+            };
+
+            if let Some(hash) = filter.request_hash {
+                match record {
+                    Some(ref record) if record.request_hashes.contains(&hash) => {}
+                    _ => continue,
+                }
+            }
+
+            if let (true, Some(record)) = (in_page, record) {
+                records.push(record);
+            }
+            total += 1;
+        }
+
+        CollationRecordList {
+            total,
+            offset: page.offset,
+            records,
+        }
+    }
+}
@@ -23,6 +23,7 @@ extern crate error_chain;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
 
 extern crate futures;
 extern crate parking_lot;
@@ -33,6 +34,7 @@ extern crate ethereum_types;
 extern crate ethkey;
 
 extern crate collation;
+extern crate contract_abi;
 extern crate ethereum;
 extern crate pool;
 extern crate pricer;
@@ -41,6 +43,7 @@ extern crate types;
 
 mod error;
 mod event;
+mod history;
 mod machine;
 mod service;
 mod transaction_queue;
@@ -50,10 +53,11 @@ mod tests;
 
 pub use self::error::{Error, ErrorKind};
 use self::event::RelayerEvent;
-use self::machine::{RelayerInfo, RelayerMachine, RelayerParams, RelayerState};
-// use self::transaction_queue::TransactionQueue;
+pub use self::history::CollationHistory;
+use self::machine::{RelayerMachine, RelayerParams};
+use self::transaction_queue::{PendingTransaction, TransactionQueue};
 
-pub use self::machine::RelayerMode;
+pub use self::machine::{adjust_gas_amount, RelayerInfo, RelayerMode, RelayerState, RelayerStats};
 pub use self::service::{
     Params as MachineServiceParams, Service as MachineService, Status as MachineStatus,
 };
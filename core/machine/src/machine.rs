@@ -15,24 +15,214 @@
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 use ethereum_types::{Address, H256, U256};
 use ethkey::KeyPair;
-use futures::{sync::mpsc, Async, Future, Poll, Stream};
+use futures::future::Loop;
+use futures::{stream::FuturesUnordered, sync::mpsc, Async, Future, Poll, Stream};
 use parking_lot::Mutex;
 use std::cmp;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::timer::Delay;
 
 use ethcore_transaction::SignedTransaction;
 
 use collation::{ClosedCollation, OpenCollation, RequestConverter, RequestDispatcher};
+use contract_abi::ERC20AbiDecoder;
 use ethereum::{monitor::Error as EthereumMonitorError, service::Error as EthereumServiceError};
-use pricer::Error as PriceServiceError;
+use pricer::{token_amount_in_wei, Error as PriceServiceError};
 use traits::{
     EthereumMonitor, EthereumMonitorResponse, EthereumMonitorTask, EthereumService, PoolRequestTag,
     PoolService, PriceService,
 };
-use types::{AccountState, BlockId, Currency, GasEstimation, SignedRequest};
+use types::{
+    AccountState, BlockId, Currency, EthRpcTransactionReceipt, GasEstimation, SignedRequest,
+};
+
+use super::{
+    CollationHistory, Error, ErrorKind, PendingTransaction, RelayerEvent, TransactionQueue,
+};
+
+// a pending transaction is checked again after this many blocks if gas price bumping is disabled
+const DEFAULT_PENDING_CHECK_BLOCK_COUNT: u64 = 20;
+
+// interval between attempts to fetch receipt of an executed transaction, doubled on each attempt
+const MIN_RECEIPT_RETRY_SECS: u64 = 1;
+const MAX_RECEIPT_RETRY_SECS: u64 = 60;
+
+/// Adds a safety margin to an estimated gas amount of a collation transaction
+pub fn adjust_gas_amount(gas_amount: U256, max_gas_limit: U256) -> U256 {
+    // gas amount for a transaction must lower than max block gas limit
+
+    let adjusted = U256::min(
+        gas_amount + (gas_amount / U256::from(10)) + U256::from(50000),
+        max_gas_limit,
+    );
+    info!(target: "relayer",
+        "Orignal estimated gas amount: {}, adjusted estimated gas amount: {}",
+        gas_amount, adjusted
+    );
+    adjusted
+}
+
+/// Raises gas price of a transaction by given percent for replacing it
+pub fn bump_gas_price(gas_price: U256, percent: u32) -> U256 {
+    // a replacement transaction must pay strictly more than the original one
+    let bumped = gas_price + gas_price * U256::from(percent) / U256::from(100);
+    U256::max(bumped, gas_price + U256::one())
+}
+
+/// Returns the block number at which a transaction broadcasted at `block_number` is checked
+pub fn pending_check_block_number(
+    block_number: U256,
+    gas_price_bump_block_count: Option<u64>,
+) -> U256 {
+    block_number
+        + U256::from(gas_price_bump_block_count.unwrap_or(DEFAULT_PENDING_CHECK_BLOCK_COUNT))
+}
+
+/// Matches each request with a `Transfer` event in receipt logs, a request without matched
+/// event is failed.
+pub fn request_outcomes(
+    tx_hash: &H256,
+    requests: &[SignedRequest],
+    receipt: &EthRpcTransactionReceipt,
+) -> Vec<(H256, Option<String>)> {
+    let reverted = match receipt.status_code {
+        Some(status) => {
+            let status: u64 = status.into();
+            status == 0
+        }
+        None => false,
+    };
+
+    if reverted {
+        let reason = format!("transaction {:?} is reverted", tx_hash);
+        return requests
+            .iter()
+            .map(|req| (*req.hash(), Some(reason.clone())))
+            .collect();
+    }
+
+    let transfer_topic = ERC20AbiDecoder::transfer_event_topic();
+    let mut transfers: Vec<Option<(Address, Address, Address, U256)>> = receipt
+        .logs
+        .iter()
+        .filter_map(|log| {
+            let topics: Vec<H256> = log.topics.iter().cloned().map(Into::into).collect();
+            if topics.first() != Some(&transfer_topic) {
+                return None;
+            }
 
-use super::{Error, ErrorKind, RelayerEvent};
+            let (from, to, value) = ERC20AbiDecoder::transfer_event(&topics, &log.data.0).ok()?;
+            Some(Some((log.address.clone().into(), from, to, value)))
+        })
+        .collect();
+
+    requests
+        .iter()
+        .map(|req| {
+            let unverified = req.unverified();
+            let expected = (
+                *unverified.token(),
+                *req.sender(),
+                *unverified.receiver(),
+                *unverified.value(),
+            );
+
+            // each Transfer event can only be matched once
+            let matched = transfers
+                .iter_mut()
+                .find(|transfer| transfer.as_ref() == Some(&expected))
+                .map(|transfer| transfer.take());
+
+            match matched {
+                Some(_) => (*req.hash(), None),
+                None => (
+                    *req.hash(),
+                    Some(format!(
+                        "token transfer is not found in transaction {:?}",
+                        tx_hash
+                    )),
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Returns `true` if a draining relayer is idle and all of its collations and transactions are
+/// finished
+pub fn is_drained(
+    draining: bool,
+    state: RelayerState,
+    in_flight_collations: usize,
+    pending_transactions: usize,
+) -> bool {
+    draining
+        && state == RelayerState::Ready
+        && in_flight_collations == 0
+        && pending_transactions == 0
+}
+
+/// Returns `None` if the collation is profitable, otherwise returns the requests whose fee
+/// covers their share of transaction cost with margin. No request is kept if the transaction
+/// cost overflows.
+pub fn shrink_unprofitable(
+    collation: &ClosedCollation,
+    token_prices: &HashMap<Address, U256>,
+    margin_percent: u32,
+) -> Option<Vec<SignedRequest>> {
+    // cost with margin saturates, only fees which overflow can cover it
+    let with_margin = |cost: U256| {
+        cost.checked_mul(U256::from(100 + margin_percent as u64))
+            .map_or_else(U256::max_value, |cost| cost / U256::from(100))
+    };
+
+    let tx = collation.transaction();
+    let cost = match tx.gas.checked_mul(tx.gas_price) {
+        Some(cost) => cost,
+        None => {
+            warn!(target: "relayer",
+                "transaction cost overflows, gas: {}, gas price: {}", tx.gas, tx.gas_price);
+            return Some(Vec::new());
+        }
+    };
+    // fee which overflows in wei covers any cost
+    let fees: Vec<U256> = collation
+        .requests()
+        .iter()
+        .map(|req| {
+            let price = token_prices
+                .get(req.unverified().token())
+                .cloned()
+                .unwrap_or_else(U256::zero);
+            token_amount_in_wei(req.unverified().fee(), &price).unwrap_or_else(U256::max_value)
+        })
+        .collect();
+    let total_fee = fees.iter().fold(U256::zero(), |sum, fee| {
+        sum.checked_add(*fee).unwrap_or_else(U256::max_value)
+    });
+
+    info!(target: "relayer",
+        "transaction cost: {} wei, total fee of {} request(s): {} wei, minimum margin: {}%",
+        cost, fees.len(), total_fee, margin_percent
+    );
+
+    if total_fee >= with_margin(cost) {
+        return None;
+    }
+
+    let share = with_margin(cost / U256::from(cmp::max(fees.len(), 1)));
+    Some(
+        collation
+            .requests()
+            .iter()
+            .zip(fees.iter())
+            .filter(|(_, fee)| **fee >= share)
+            .map(|(req, _)| req.clone())
+            .collect(),
+    )
+}
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum RelayerMode {
@@ -49,9 +239,14 @@ where
     pub chain_id: Option<u64>,
     pub adjust_block_gas_limit_fn: Option<fn(U256) -> U256>,
     pub confirmation_count: u32,
+    pub gas_price_bump_block_count: Option<u64>,
+    pub gas_price_bump_percent: u32,
+    pub max_pending_transactions: usize,
+    pub min_profit_margin_percent: Option<u32>,
+    pub unprofitable_hold_back: Duration,
 }
 
-#[derive(PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Serialize, Deserialize)]
 pub enum RelayerState {
     #[serde(rename = "ready")]
     Ready,
@@ -83,6 +278,7 @@ impl ToString for RelayerState {
 
 struct Preparation {
     account_state: AccountState,
+    block_number: U256,
     block_gas_limit: U256,
     gas_price: U256,
 }
@@ -91,6 +287,7 @@ impl Default for Preparation {
     fn default() -> Preparation {
         Preparation {
             account_state: AccountState::empty(),
+            block_number: U256::zero(),
             block_gas_limit: U256::from(8_000_000 * 2 / 3),
             gas_price: U256::zero(),
         }
@@ -99,7 +296,82 @@ impl Default for Preparation {
 
 type InfoFetcher = Box<Future<Item = Preparation, Error = Error> + Send>;
 type TxBroadcaster = Box<Future<Item = H256, Error = Error> + Send>;
-type GasEstimator = Box<Future<Item = ClosedCollation, Error = Error> + Send>;
+type GasEstimator =
+    Box<Future<Item = (ClosedCollation, HashMap<Address, U256>), Error = Error> + Send>;
+type TokenPriceFetcher = Box<Future<Item = HashMap<Address, U256>, Error = Error> + Send>;
+type PendingChecker = Box<Future<Item = PendingCheck, Error = Error> + Send>;
+type OutcomeResolver = Box<Future<Item = TransactionOutcome, Error = UnresolvedOutcome> + Send>;
+
+// executed transaction whose receipt can not be fetched
+struct UnresolvedOutcome {
+    transaction_hash: H256,
+    request_hashes: Vec<H256>,
+    error: Error,
+}
+
+// outcome of an executed transaction
+struct TransactionOutcome {
+    transaction_hash: H256,
+    block_number: Option<U256>,
+    gas_used: Option<U256>,
+    // outcome of each request, the reason is none if the request is executed successfully
+    requests: Vec<(H256, Option<String>)>,
+    // fee of executed requests per token
+    fees: HashMap<Address, U256>,
+}
+
+impl TransactionOutcome {
+    fn new(
+        transaction_hash: H256,
+        requests: &[SignedRequest],
+        receipt: &EthRpcTransactionReceipt,
+        outcomes: Vec<(H256, Option<String>)>,
+    ) -> TransactionOutcome {
+        TransactionOutcome {
+            transaction_hash,
+            block_number: receipt.block_number.map(Into::into),
+            gas_used: receipt.gas_used.map(Into::into),
+            fees: executed_fees(requests, &outcomes),
+            requests: outcomes,
+        }
+    }
+}
+
+/// Returns fee of requests which are executed successfully per token
+pub fn executed_fees(
+    requests: &[SignedRequest],
+    outcomes: &[(H256, Option<String>)],
+) -> HashMap<Address, U256> {
+    let executed: HashSet<H256> = outcomes
+        .iter()
+        .filter(|(_, reason)| reason.is_none())
+        .map(|(hash, _)| *hash)
+        .collect();
+    requests
+        .iter()
+        .filter(|req| executed.contains(req.hash()))
+        .fold(HashMap::new(), |mut fees, req| {
+            {
+                let fee = fees
+                    .entry(*req.unverified().token())
+                    .or_insert_with(U256::zero);
+                *fee = *fee + *req.unverified().fee();
+            }
+            fees
+        })
+}
+
+enum PendingCheck {
+    // transaction is mined or still pending, check it again later
+    Pending(U256),
+    // transaction is broadcasted again with a new hash
+    Rebroadcasted {
+        collation: ClosedCollation,
+        hash: H256,
+    },
+    // transaction is dropped and can not be broadcasted again
+    Dropped { nonce: U256, error: String },
+}
 
 enum StateWorker {
     Ready,
@@ -123,6 +395,99 @@ pub struct RelayerInfo {
 
     // current state of a relayer machine
     state: RelayerState,
+
+    #[serde(rename = "pendingTransactionCount")]
+    // count of broadcasted but not yet confirmed transactions
+    pending_transaction_count: usize,
+
+    // whether the relayer is waiting for its in-flight collation before being removed
+    draining: bool,
+
+    // accumulated statistics since the relayer is created
+    stats: RelayerStats,
+}
+
+impl RelayerInfo {
+    #[inline]
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    #[inline]
+    pub fn state(&self) -> RelayerState {
+        self.state
+    }
+
+    #[inline]
+    pub fn pending_transaction_count(&self) -> usize {
+        self.pending_transaction_count
+    }
+
+    #[inline]
+    pub fn is_draining(&self) -> bool {
+        self.draining
+    }
+
+    #[inline]
+    pub fn stats(&self) -> &RelayerStats {
+        &self.stats
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct RelayerStats {
+    #[serde(rename = "stateMillis")]
+    // total time spent in each state
+    state_millis: HashMap<RelayerState, u64>,
+
+    #[serde(rename = "collationCount")]
+    // count of broadcasted collations
+    collation_count: u64,
+
+    #[serde(rename = "gasUsed")]
+    // total gas used by executed transactions
+    gas_used: U256,
+
+    // total fee of executed requests per token
+    fees: HashMap<Address, U256>,
+}
+
+impl RelayerStats {
+    #[inline]
+    pub fn state_millis(&self) -> &HashMap<RelayerState, u64> {
+        &self.state_millis
+    }
+
+    #[inline]
+    pub fn collation_count(&self) -> u64 {
+        self.collation_count
+    }
+
+    #[inline]
+    pub fn gas_used(&self) -> U256 {
+        self.gas_used
+    }
+
+    #[inline]
+    pub fn fees(&self) -> &HashMap<Address, U256> {
+        &self.fees
+    }
+
+    fn add_state_duration(&mut self, state: RelayerState, duration: Duration) {
+        let millis = duration.as_secs() * 1000 + u64::from(duration.subsec_millis());
+        *self.state_millis.entry(state).or_insert(0) += millis;
+    }
+
+    fn add_outcome(&mut self, outcome: &TransactionOutcome) {
+        if let Some(gas_used) = outcome.gas_used {
+            self.gas_used = self.gas_used + gas_used;
+        }
+
+        for (token, fee) in outcome.fees.iter() {
+            let total = self.fees.entry(*token).or_insert_with(U256::zero);
+            *total = *total + *fee;
+        }
+    }
 }
 
 impl cmp::Ord for RelayerInfo {
@@ -153,9 +518,26 @@ where
     mode: RelayerMode,
     state: RelayerState,
     state_worker: StateWorker,
+    // when the relayer entered current state
+    state_since: Instant,
+    // no new collation is created once draining
+    draining: bool,
+    stats: RelayerStats,
 
     current_collation: Option<ClosedCollation>,
 
+    // broadcasted transactions which are not confirmed yet
+    transaction_queue: Arc<Mutex<TransactionQueue>>,
+    max_pending_transactions: usize,
+    // history of broadcasted collations shared by all relayers
+    history: Arc<Mutex<CollationHistory>>,
+    pending_checkers: FuturesUnordered<PendingChecker>,
+    outcome_resolvers: FuturesUnordered<OutcomeResolver>,
+
+    // latest known block number
+    block_number: U256,
+    block_gas_limit: U256,
+
     keypair: KeyPair,
     relayer_address: Address,
 
@@ -163,6 +545,12 @@ where
     chain_id: Option<u64>,
 
     confirmation_count: u32,
+    gas_price_bump_block_count: Option<u64>,
+    gas_price_bump_percent: u32,
+    min_profit_margin_percent: Option<u32>,
+    // unprofitable requests are not relayed again until their hold-back period ends
+    unprofitable_hold_back: Duration,
+    held_back_requests: HashMap<H256, Instant>,
     adjust_block_gas_limit_fn: fn(U256) -> U256,
     adjust_gas_amount_fn: fn(U256, U256) -> U256,
 
@@ -186,20 +574,6 @@ where
         original - U256::from(100000)
     }
 
-    pub fn adjust_gas_amount(gas_amount: U256, max_gas_limit: U256) -> U256 {
-        // gas amount for a transaction must lower than max block gas limit
-
-        let adjusted = U256::min(
-            gas_amount + (gas_amount / U256::from(10)) + U256::from(50000),
-            max_gas_limit,
-        );
-        info!(target: "relayer",
-            "Orignal estimated gas amount: {}, adjusted estimated gas amount: {}",
-            gas_amount, adjusted
-        );
-        adjusted
-    }
-
     fn state_transfer(&mut self, new_state: RelayerState, new_state_worker: StateWorker) {
         info!(target: "relayer",
             "relayer {} state transfer from {:?} to {:?}",
@@ -208,10 +582,18 @@ where
             new_state.to_string()
         );
 
-        self.state = new_state;
+        self.enter_state(new_state);
         self.state_worker = new_state_worker;
     }
 
+    fn enter_state(&mut self, new_state: RelayerState) {
+        let now = Instant::now();
+        self.stats
+            .add_state_duration(self.state, now.duration_since(self.state_since));
+        self.state = new_state;
+        self.state_since = now;
+    }
+
     pub fn new(
         mode: RelayerMode,
         params: RelayerParams<C>,
@@ -219,6 +601,8 @@ where
         ethereum_monitor: Arc<Mutex<M>>,
         pool: Arc<Mutex<P>>,
         gas_pricer: Arc<Mutex<G>>,
+        transaction_queue: Arc<Mutex<TransactionQueue>>,
+        history: Arc<Mutex<CollationHistory>>,
     ) -> RelayerMachine<E, M, P, G, C> {
         let keypair = params.keypair;
         let address = keypair.address();
@@ -240,15 +624,32 @@ where
             mode,
             state: RelayerState::Ready,
             state_worker,
+            state_since: Instant::now(),
+            draining: false,
+            stats: RelayerStats::default(),
 
             current_collation: None,
 
+            transaction_queue,
+            max_pending_transactions: usize::max(params.max_pending_transactions, 1),
+            history,
+            pending_checkers: FuturesUnordered::new(),
+            outcome_resolvers: FuturesUnordered::new(),
+
+            block_number: U256::zero(),
+            block_gas_limit: U256::zero(),
+
             keypair,
             relayer_address: address,
 
             confirmation_count: params.confirmation_count,
+            gas_price_bump_block_count: params.gas_price_bump_block_count,
+            gas_price_bump_percent: params.gas_price_bump_percent,
+            min_profit_margin_percent: params.min_profit_margin_percent,
+            unprofitable_hold_back: params.unprofitable_hold_back,
+            held_back_requests: HashMap::new(),
             adjust_block_gas_limit_fn,
-            adjust_gas_amount_fn: Self::adjust_gas_amount,
+            adjust_gas_amount_fn: adjust_gas_amount,
 
             dispatcher: params.dispatcher,
             chain_id: params.chain_id,
@@ -268,12 +669,13 @@ where
     ) -> InfoFetcher {
         enum Info {
             AccountState(AccountState),
+            BlockNumber(U256),
             GasLimit(U256),
             GasPrice(U256),
         }
         type InfoFuture = Box<Future<Item = Info, Error = Error> + Send>;
 
-        let (state, gas_limit) = {
+        let (state, block_number, gas_limit) = {
             let ethereum = ethereum.lock();
             let state: InfoFuture = Box::new(
                 ethereum
@@ -281,13 +683,19 @@ where
                     .and_then(|account_state| Ok(Info::AccountState(account_state)))
                     .from_err(),
             );
+            let block_number: InfoFuture = Box::new(
+                ethereum
+                    .get_block_number()
+                    .and_then(|block_number| Ok(Info::BlockNumber(block_number)))
+                    .from_err(),
+            );
             let gas_limit: InfoFuture = Box::new(
                 ethereum
                     .block_gas_limit(BlockId::Latest)
                     .and_then(|gas_limit| Ok(Info::GasLimit(gas_limit)))
                     .from_err(),
             );
-            (state, gas_limit)
+            (state, block_number, gas_limit)
         };
         let gas_price: InfoFuture = Box::new(
             gas_pricer
@@ -298,7 +706,7 @@ where
         );
 
         Box::new(
-            futures::future::join_all(vec![state, gas_limit, gas_price]).map(|results| {
+            futures::future::join_all(vec![state, block_number, gas_limit, gas_price]).map(|results| {
                 results
                     .into_iter()
                     .fold(Preparation::default(), |mut preparation, value| {
@@ -306,6 +714,9 @@ where
                             Info::AccountState(state) => {
                                 preparation.account_state = state;
                             }
+                            Info::BlockNumber(block_number) => {
+                                preparation.block_number = block_number;
+                            }
                             Info::GasLimit(gas_limit) => {
                                 preparation.block_gas_limit = gas_limit;
                             }
@@ -340,7 +751,7 @@ where
                 .mark_by_hashes(&current_collation.request_hashes(), PoolRequestTag::Ready);
         }
 
-        self.state = RelayerState::Ready;
+        self.enter_state(RelayerState::Ready);
         self.state_worker = StateWorker::Ready;
     }
 
@@ -354,6 +765,38 @@ where
         self.mode = mode;
     }
 
+    /// Stops creating new collations, the relayer can be removed once it is drained
+    pub fn drain(&mut self) {
+        info!(target: "relayer",
+            "relayer {} is draining, {} pending transaction(s)",
+            self.relayer_address,
+            self.pending_transaction_count()
+        );
+        self.draining = true;
+    }
+
+    /// Keeps a draining relayer working
+    pub fn cancel_drain(&mut self) {
+        self.draining = false;
+    }
+
+    #[inline]
+    pub fn is_draining(&self) -> bool {
+        self.draining
+    }
+
+    /// Returns `true` if the relayer is draining and has no in-flight collation
+    pub fn is_drained(&self) -> bool {
+        let in_flight_collations =
+            self.current_collation.iter().count() + self.outcome_resolvers.len();
+        is_drained(
+            self.draining,
+            self.state,
+            in_flight_collations,
+            self.pending_transaction_count(),
+        )
+    }
+
     #[inline]
     pub fn address(&self) -> Address {
         self.relayer_address.clone()
@@ -419,6 +862,10 @@ where
     fn poll_ready(&mut self) -> Poll<Option<RelayerState>, Error> {
         assert!(self.current_collation.is_none());
 
+        if self.draining {
+            return Ok(Async::NotReady);
+        }
+
         let event = Self::select_event(
             &vec![match self.event_receiver.poll().unwrap() {
                 Async::Ready(Some(event)) => event,
@@ -467,9 +914,12 @@ where
         assert_ne!(event, RelayerEvent::Null);
         let Preparation {
             account_state,
+            block_number,
             block_gas_limit,
             gas_price,
         } = info;
+        self.block_number = block_number;
+        self.block_gas_limit = block_gas_limit;
 
         info!(target: "relayer",
             "relayer {:?} balance: {}, nonce: {}",
@@ -479,69 +929,116 @@ where
         );
         info!(target: "relayer", "latest block gas limit: {:?}", block_gas_limit);
 
-        let mut open_collation = match event {
+        let requests = match event {
             RelayerEvent::Null => return Ok(Async::NotReady),
-            RelayerEvent::SingleRequest(request) => OpenCollation::with_single_request(request),
+            RelayerEvent::SingleRequest(request) => vec![request],
             RelayerEvent::Timeout | RelayerEvent::Thredshold => {
                 let gas_limit = (self.adjust_block_gas_limit_fn)(block_gas_limit);
 
-                let requests: Vec<_> = {
-                    let mut pool = self.pool.lock();
-                    let requests: Vec<_> = pool
-                        .ready_requests(
-                            Some(PoolRequestTag::Processing),
-                            traits::PoolPendingSettings {
-                                filter: None,
-                                gas_limit,
-                                relayer: Some(self.relayer_address),
-                            },
-                        )
-                        .iter()
-                        .map(|req| req.as_ref().clone())
-                        .collect();
-
-                    requests
-                };
-
-                if requests.is_empty() {
-                    info!(target: "relayer",
-                        "relayer {}: no ready token transfer request in pool",
-                        self.relayer_address
-                    );
+                self.pool
+                    .lock()
+                    .ready_requests(
+                        Some(PoolRequestTag::Processing),
+                        traits::PoolPendingSettings {
+                            filter: None,
+                            gas_limit,
+                            relayer: Some(self.relayer_address),
+                        },
+                    )
+                    .iter()
+                    .map(|req| req.as_ref().clone())
+                    .collect()
+            }
+        };
 
-                    // transfer machine state back to ready
-                    self.state_transfer(RelayerState::Ready, StateWorker::Ready);
+        let requests = self.skip_held_back_requests(requests);
+        if requests.is_empty() {
+            info!(target: "relayer",
+                "relayer {}: no ready token transfer request in pool",
+                self.relayer_address
+            );
 
-                    return Ok(Async::Ready(Some(self.state)));
-                }
+            // transfer machine state back to ready
+            self.state_transfer(RelayerState::Ready, StateWorker::Ready);
 
-                info!(target: "relayer", "relayer {} try to relay {} request(s)",
-                    self.relayer_address, requests.len());
-                OpenCollation::with_requests(requests)
-            }
-        };
+            return Ok(Async::Ready(Some(self.state)));
+        }
 
-        let value = U256::zero();
-        open_collation.update_unestimated(
-            &self.dispatcher,
-            &account_state.nonce(),
-            &gas_price,
-            &value,
+        info!(target: "relayer", "relayer {} try to relay {} request(s)",
+            self.relayer_address, requests.len());
+        let open_collation = OpenCollation::with_requests(requests);
+
+        // there may be pending transactions which are not counted in account nonce
+        let nonce = self
+            .transaction_queue
+            .lock()
+            .next_nonce(&self.relayer_address, account_state.nonce());
+
+        let estimator = self.new_gas_estimator(open_collation, nonce, gas_price)?;
+        self.state_transfer(
+            RelayerState::GasEstimating,
+            StateWorker::GasEstimation(estimator),
         );
+        Ok(Async::Ready(Some(self.state)))
+    }
+
+    /// Returns requests which are not held back for being unprofitable, held back requests are
+    /// put back to the pool
+    fn skip_held_back_requests(&mut self, requests: Vec<SignedRequest>) -> Vec<SignedRequest> {
+        let now = Instant::now();
+        self.held_back_requests.retain(|_, until| *until > now);
+        if self.held_back_requests.is_empty() {
+            return requests;
+        }
+
+        let (held_back, requests): (Vec<_>, Vec<_>) = requests
+            .into_iter()
+            .partition(|req| self.held_back_requests.contains_key(req.hash()));
+        if !held_back.is_empty() {
+            let hashes: Vec<H256> = held_back.iter().map(|req| *req.hash()).collect();
+            self.pool
+                .lock()
+                .mark_by_hashes(&hashes, PoolRequestTag::Ready);
+        }
+
+        requests
+    }
+
+    fn hold_back_requests(&mut self, hashes: &[H256]) {
+        let until = Instant::now() + self.unprofitable_hold_back;
+        for hash in hashes {
+            self.held_back_requests.insert(*hash, until);
+        }
+        self.pool
+            .lock()
+            .mark_by_hashes(hashes, PoolRequestTag::Ready);
+    }
+
+    fn new_gas_estimator(
+        &mut self,
+        mut open_collation: OpenCollation,
+        nonce: U256,
+        gas_price: U256,
+    ) -> Result<GasEstimator, Error> {
+        let value = U256::zero();
+        open_collation.update_unestimated(&self.dispatcher, &nonce, &gas_price, &value);
         let unestimated_tx = match open_collation.unestimated() {
             Some(tx) => tx.clone(),
             None => return Err(Error::from(ErrorKind::EmptyTokenTransferRequestTransaction)),
         };
 
-        {
+        let token_price_fetcher = {
             let fake_closed_collation = open_collation
                 .fake_close(self.keypair.secret().clone(), self.chain_id)
                 .expect("keypair is valid; qed");
 
+            let fetcher = self.new_token_price_fetcher(fake_closed_collation.requests());
             self.current_collation = Some(fake_closed_collation);
-        }
+            fetcher
+        };
 
-        let new_state_worker = StateWorker::GasEstimation(Box::new(
+        let block_gas_limit = self.block_gas_limit;
+        Ok(Box::new(
             self.ethereum
                 .lock()
                 .estimate_gas(GasEstimation::Transaction(
@@ -561,11 +1058,40 @@ where
                         )?)
                     }
                 })
-                .from_err::<Error>(),
-        ));
+                .from_err::<Error>()
+                .join(token_price_fetcher),
+        ))
+    }
 
-        self.state_transfer(RelayerState::GasEstimating, new_state_worker);
-        Ok(Async::Ready(Some(self.state)))
+    fn new_token_price_fetcher(&self, requests: &[SignedRequest]) -> TokenPriceFetcher {
+        // token prices are only used to check profitability
+        if self.min_profit_margin_percent.is_none() {
+            return Box::new(futures::future::ok(HashMap::new()));
+        }
+
+        let tokens: HashSet<Address> = requests
+            .iter()
+            .map(|req| *req.unverified().token())
+            .collect();
+        let prices: Vec<_> = {
+            let gas_pricer = self.gas_pricer.lock();
+            tokens
+                .into_iter()
+                .map(|token| {
+                    // fee of a token without known price is not counted
+                    gas_pricer.token_price(token).then(move |price| match price {
+                        Ok(price) => Ok::<_, Error>((token, price)),
+                        Err(err) => {
+                            warn!(target: "relayer",
+                                "Failed to get price of token {:?}, error: {}", token, err);
+                            Ok((token, U256::zero()))
+                        }
+                    })
+                })
+                .collect()
+        };
+
+        Box::new(futures::future::join_all(prices).map(|prices| prices.into_iter().collect()))
     }
 
     fn poll_estimating(&mut self) -> Poll<Option<RelayerState>, Error> {
@@ -574,10 +1100,10 @@ where
             None => false,
         });
 
-        let closed_collation =
+        let (closed_collation, token_prices) =
             if let StateWorker::GasEstimation(ref mut estimate_future) = self.state_worker {
                 match estimate_future.poll() {
-                    Ok(Async::Ready(closed_collation)) => closed_collation,
+                    Ok(Async::Ready(result)) => result,
                     Ok(Async::NotReady) => return Ok(Async::NotReady),
                     Err(err) => return Err(err),
                 }
@@ -588,6 +1114,14 @@ where
                 )));
             };
 
+        if let Some(margin_percent) = self.min_profit_margin_percent {
+            if let Some(requests) =
+                shrink_unprofitable(&closed_collation, &token_prices, margin_percent)
+            {
+                return self.on_unprofitable(closed_collation, requests);
+            }
+        }
+
         let signed_tx = closed_collation.transaction().clone();
         {
             let (tx, _, _) = signed_tx.clone().deconstruct();
@@ -643,6 +1177,54 @@ where
         Ok(Async::Ready(Some(self.state.clone())))
     }
 
+    fn on_unprofitable(
+        &mut self,
+        closed_collation: ClosedCollation,
+        requests: Vec<SignedRequest>,
+    ) -> Poll<Option<RelayerState>, Error> {
+        let kept: HashSet<H256> = requests.iter().map(|req| *req.hash()).collect();
+        let dropped: Vec<H256> = closed_collation
+            .request_hashes()
+            .into_iter()
+            .filter(|hash| !kept.contains(hash))
+            .collect();
+
+        // hold back all requests if dropping requests does not help
+        if requests.is_empty() || dropped.is_empty() {
+            info!(target: "relayer",
+                "relayer {} holds back {} unprofitable request(s) for {} second(s)",
+                self.relayer_address,
+                closed_collation.request_count(),
+                self.unprofitable_hold_back.as_secs()
+            );
+
+            self.current_collation = None;
+            self.hold_back_requests(&closed_collation.request_hashes());
+            self.state_transfer(RelayerState::Ready, StateWorker::Ready);
+            return Ok(Async::Ready(Some(self.state)));
+        }
+
+        info!(target: "relayer",
+            "relayer {} drops {} unprofitable request(s), re-estimate with {} request(s)",
+            self.relayer_address,
+            dropped.len(),
+            requests.len()
+        );
+        self.hold_back_requests(&dropped);
+
+        let (nonce, gas_price) = {
+            let tx = closed_collation.transaction();
+            (tx.nonce, tx.gas_price)
+        };
+        let estimator =
+            self.new_gas_estimator(OpenCollation::with_requests(requests), nonce, gas_price)?;
+        self.state_transfer(
+            RelayerState::GasEstimating,
+            StateWorker::GasEstimation(estimator),
+        );
+        Ok(Async::Ready(Some(self.state)))
+    }
+
     fn poll_broadcasting(&mut self) -> Poll<Option<RelayerState>, Error> {
         assert!(self.current_collation.is_some());
 
@@ -661,53 +1243,528 @@ where
             )));
         };
 
-        self.state_transfer(
-            RelayerState::TxExecuting,
-            StateWorker::WaitingTransactionExecuted,
+        let closed_collation = ::std::mem::replace(&mut self.current_collation, None)
+            .expect("current_collation is some; qed");
+        let check_block_number = self.pending_check_block_number();
+        info!(target: "relayer",
+            "relayer {} broadcasted transaction {:?} with nonce {}",
+            self.relayer_address,
+            tx_hash,
+            closed_collation.transaction().nonce
         );
 
-        return match self.ethereum_monitor.lock().subscribe(
+        self.pool
+            .lock()
+            .mark_broadcasted(
+                &closed_collation.request_hashes(),
+                &self.relayer_address,
+                &tx_hash,
+            );
+        self.stats.collation_count += 1;
+        self.history
+            .lock()
+            .record_broadcast(&self.relayer_address, &closed_collation, tx_hash);
+        self.transaction_queue.lock().push(
+            &self.relayer_address,
+            PendingTransaction::new(closed_collation, tx_hash, check_block_number),
+        );
+        self.watch_transaction(tx_hash)?;
+        self.ethereum_monitor.lock().subscribe(
+            self.monitor_wather_id,
+            EthereumMonitorTask::BlockNumberReached(check_block_number),
+        )?;
+
+        if self.has_transaction_slot() {
+            self.state_transfer(RelayerState::Ready, StateWorker::Ready);
+        } else {
+            self.state_transfer(
+                RelayerState::TxExecuting,
+                StateWorker::WaitingTransactionExecuted,
+            );
+        }
+
+        Ok(Async::Ready(Some(self.state)))
+    }
+
+    fn poll_executing(&mut self) -> Poll<Option<RelayerState>, Error> {
+        assert!(self.current_collation.is_none());
+
+        // wait until one of pending transactions is confirmed
+        if !self.has_transaction_slot() {
+            return Ok(Async::NotReady);
+        }
+
+        self.state_transfer(RelayerState::Ready, StateWorker::Ready);
+        Ok(Async::Ready(Some(self.state)))
+    }
+
+    #[inline]
+    fn pending_check_block_number(&self) -> U256 {
+        pending_check_block_number(self.block_number, self.gas_price_bump_block_count)
+    }
+
+    #[inline]
+    fn has_transaction_slot(&self) -> bool {
+        self.pending_transaction_count() < self.max_pending_transactions
+    }
+
+    #[inline]
+    pub fn pending_transaction_count(&self) -> usize {
+        self.transaction_queue.lock().len(&self.relayer_address)
+    }
+
+    fn watch_transaction(&mut self, tx_hash: H256) -> Result<(), Error> {
+        self.ethereum_monitor.lock().subscribe(
             self.monitor_wather_id,
             EthereumMonitorTask::TransactionExecuted {
                 hash: tx_hash,
                 confirmation_count: self.confirmation_count,
             },
-        ) {
-            Ok(_) => Ok(Async::Ready(Some(self.state))),
-            Err(err) => Err(Error::from(err)),
+        )?;
+
+        Ok(())
+    }
+
+    fn unwatch_transactions(&mut self, pending: &PendingTransaction) {
+        let mut monitor = self.ethereum_monitor.lock();
+        for hash in pending.hashes() {
+            let _ = monitor.unsubscribe(
+                self.monitor_wather_id,
+                &EthereumMonitorTask::TransactionExecuted {
+                    hash: *hash,
+                    confirmation_count: self.confirmation_count,
+                },
+            );
+        }
+    }
+
+    fn schedule_pending_check(&mut self, nonce: &U256) -> Result<(), Error> {
+        let check_block_number = self.pending_check_block_number();
+        let hashes = match self
+            .transaction_queue
+            .lock()
+            .get_mut(&self.relayer_address, nonce)
+        {
+            Some(pending) => {
+                pending.set_checking(false);
+                pending.set_check_block_number(check_block_number);
+                pending.hashes().clone()
+            }
+            None => return Ok(()),
         };
+
+        // the monitor drops a task silently if it fails, so watch these transactions again
+        for hash in hashes {
+            self.watch_transaction(hash)?;
+        }
+
+        self.ethereum_monitor.lock().subscribe(
+            self.monitor_wather_id,
+            EthereumMonitorTask::BlockNumberReached(check_block_number),
+        )?;
+
+        Ok(())
     }
 
-    fn poll_executing(&mut self) -> Poll<Option<RelayerState>, Error> {
-        assert!(self.current_collation.is_some());
+    fn new_pending_checker(&self, pending: &PendingTransaction) -> PendingChecker {
+        let nonce = pending.nonce();
+        let collation = pending.collation().clone();
+        let resigned_collation = match self.gas_price_bump_block_count {
+            Some(_) => Some(collation.resign_with_gas_price(
+                self.keypair.secret().clone(),
+                self.chain_id,
+                &bump_gas_price(
+                    collation.transaction().gas_price,
+                    self.gas_price_bump_percent,
+                ),
+            )),
+            None => None,
+        };
+        let ethereum = self.ethereum.clone();
+        let lookups: Vec<_> = {
+            let ethereum = self.ethereum.lock();
+            pending
+                .hashes()
+                .iter()
+                .map(|hash| ethereum.get_transaction_by_hash(*hash))
+                .collect()
+        };
+
+        Box::new(
+            futures::future::join_all(lookups)
+                .from_err::<Error>()
+                .and_then(
+                    move |txs| -> Box<Future<Item = PendingCheck, Error = Error> + Send> {
+                        // any of transactions with the same nonce may be mined, not only the
+                        // latest one
+                        let mined = txs
+                            .iter()
+                            .any(|tx| tx.as_ref().map_or(false, |tx| tx.block_number.is_some()));
+                        let known = txs.iter().any(Option::is_some);
+
+                        let (collation, dropped) = match (mined, known) {
+                            // transaction is already mined, wait for enough confirmations
+                            (true, _) => {
+                                return Box::new(futures::future::ok(PendingCheck::Pending(nonce)));
+                            }
+                            // transaction is still pending, replace it with a higher gas price
+                            (false, true) => match resigned_collation {
+                                Some(resigned_collation) => (resigned_collation, false),
+                                None => {
+                                    return Box::new(futures::future::ok(PendingCheck::Pending(
+                                        nonce,
+                                    )));
+                                }
+                            },
+                            // transaction is dropped by Ethereum node, broadcast it again
+                            (false, false) => (resigned_collation.unwrap_or(collation), true),
+                        };
+
+                        let signed_tx = collation.transaction().clone();
+                        Box::new(ethereum.lock().send_transaction(signed_tx).then(
+                            move |result| {
+                                Ok::<_, Error>(match result {
+                                    Ok(hash) => PendingCheck::Rebroadcasted { collation, hash },
+                                    Err(ref err) if dropped => PendingCheck::Dropped {
+                                        nonce,
+                                        error: err.to_string(),
+                                    },
+                                    Err(_) => PendingCheck::Pending(nonce),
+                                })
+                            },
+                        ))
+                    },
+                )
+                .or_else(move |err| {
+                    warn!(target: "relayer",
+                        "failed to check pending transaction with nonce {}, error: {}",
+                        nonce, err
+                    );
+                    Ok::<_, Error>(PendingCheck::Pending(nonce))
+                }),
+        )
+    }
 
-        let tx_hash = match self.monitor_receiver.poll().unwrap() {
-            Async::Ready(Some(EthereumMonitorResponse::Transaction(tx_hash))) => tx_hash,
-            _ => return Ok(Async::NotReady),
+    fn on_transaction_executed(&mut self, tx_hash: H256) {
+        let pending = match self
+            .transaction_queue
+            .lock()
+            .remove_by_hash(&self.relayer_address, &tx_hash)
+        {
+            Some(pending) => pending,
+            None => return,
         };
+        self.unwatch_transactions(&pending);
 
-        assert_eq!(
+        info!(target: "relayer",
+            "relayer {} transaction {:?} with nonce {} is executed, resolve outcome of {} request(s)",
+            self.relayer_address,
             tx_hash,
-            match self.current_collation {
-                Some(ref closed_collation) => closed_collation.transaction().hash(),
-                None => H256::zero(),
+            pending.nonce(),
+            pending.collation().request_count()
+        );
+
+        let resolver = self.new_outcome_resolver(tx_hash, pending.collation().requests().clone());
+        self.outcome_resolvers.push(resolver);
+    }
+
+    fn new_outcome_resolver(&self, tx_hash: H256, requests: Vec<SignedRequest>) -> OutcomeResolver {
+        let ethereum = self.ethereum.clone();
+
+        // the transaction is confirmed, so its receipt must be found sooner or later, outcome of
+        // requests is unknown until then
+        let receipt = futures::future::loop_fn(0u32, move |attempt| {
+            let delay: Box<Future<Item = (), Error = Error> + Send> = match attempt {
+                0 => Box::new(futures::future::ok(())),
+                _ => Box::new(
+                    Delay::new(Instant::now() + Self::receipt_retry_interval(attempt)).from_err(),
+                ),
+            };
+
+            let ethereum = ethereum.clone();
+            delay.and_then(move |_| {
+                ethereum
+                    .lock()
+                    .get_transaction_receipt(tx_hash)
+                    .then(move |receipt| match receipt {
+                        Ok(Some(receipt)) => Ok(Loop::Break(receipt)),
+                        Ok(None) => {
+                            warn!(target: "relayer",
+                                "receipt of transaction {:?} is not found, attempt: {}",
+                                tx_hash, attempt + 1
+                            );
+                            Ok(Loop::Continue(attempt + 1))
+                        }
+                        Err(err) => {
+                            warn!(target: "relayer",
+                                "failed to fetch receipt of transaction {:?}, attempt: {}, error: {}",
+                                tx_hash, attempt + 1, err
+                            );
+                            Ok(Loop::Continue(attempt + 1))
+                        }
+                    })
+            })
+        });
+
+        Box::new(receipt.then(move |receipt| match receipt {
+            Ok(receipt) => Ok(TransactionOutcome::new(
+                tx_hash,
+                &requests,
+                &receipt,
+                request_outcomes(&tx_hash, &requests, &receipt),
+            )),
+            Err(error) => Err(UnresolvedOutcome {
+                transaction_hash: tx_hash,
+                request_hashes: requests.iter().map(|req| *req.hash()).collect(),
+                error,
+            }),
+        }))
+    }
+
+    #[inline]
+    fn receipt_retry_interval(attempt: u32) -> Duration {
+        Duration::from_secs(cmp::min(
+            MIN_RECEIPT_RETRY_SECS << cmp::min(attempt, 16),
+            MAX_RECEIPT_RETRY_SECS,
+        ))
+    }
+
+    fn on_outcomes_resolved(&mut self, outcome: TransactionOutcome) {
+        self.stats.add_outcome(&outcome);
+        self.history.lock().record_executed(
+            &outcome.transaction_hash,
+            outcome.block_number,
+            outcome.gas_used,
+            &outcome.requests,
+        );
+
+        let mut pool = self.pool.lock();
+        if let Some(block_number) = outcome.block_number {
+            let hashes: Vec<H256> = outcome.requests.iter().map(|(hash, _)| *hash).collect();
+            pool.mark_mined(&hashes, block_number);
+        }
+
+        let (executed, failed): (Vec<_>, Vec<_>) = outcome
+            .requests
+            .into_iter()
+            .partition(|(_, reason)| reason.is_none());
+
+        info!(target: "relayer",
+            "relayer {} mark {} request(s) as {:?} and {} request(s) as {:?}",
+            self.relayer_address,
+            executed.len(),
+            PoolRequestTag::Executed,
+            failed.len(),
+            PoolRequestTag::Invalid
+        );
+
+        let executed: Vec<H256> = executed.into_iter().map(|(hash, _)| hash).collect();
+        pool.mark_by_hashes(&executed, PoolRequestTag::Executed);
+
+        for (hash, reason) in failed {
+            let reason = reason.expect("failed request has a reason; qed");
+            warn!(target: "relayer", "request {:?} is failed: {}", hash, reason);
+            pool.mark_invalid(&hash, reason);
+        }
+    }
+
+    fn on_outcomes_unresolved(&mut self, unresolved: UnresolvedOutcome) {
+        warn!(target: "relayer",
+            "relayer {} failed to resolve outcome of transaction {:?}, error: {}",
+            self.relayer_address, unresolved.transaction_hash, unresolved.error
+        );
+        self.history.lock().record_unresolved(
+            &unresolved.transaction_hash,
+            format!("failed to fetch receipt: {}", unresolved.error),
+        );
+
+        // requests which are executed are removed from pool once their nonces are outdated
+        self.pool
+            .lock()
+            .mark_by_hashes(&unresolved.request_hashes, PoolRequestTag::Ready);
+    }
+
+    fn on_block_number_reached(&mut self, block_number: U256) {
+        self.block_number = U256::max(self.block_number, block_number);
+
+        let due: Vec<PendingTransaction> = {
+            let mut queue = self.transaction_queue.lock();
+            let address = self.relayer_address;
+            let nonces = queue.due(&address, &block_number);
+            nonces
+                .into_iter()
+                .filter_map(|nonce| {
+                    let pending = queue.get_mut(&address, &nonce)?;
+                    pending.set_checking(true);
+                    Some(pending.clone())
+                })
+                .collect()
+        };
+
+        for pending in due {
+            let _ = self.ethereum_monitor.lock().unsubscribe(
+                self.monitor_wather_id,
+                &EthereumMonitorTask::BlockNumberReached(pending.check_block_number()),
+            );
+
+            let checker = self.new_pending_checker(&pending);
+            self.pending_checkers.push(checker);
+        }
+    }
+
+    fn on_transaction_reorged(&mut self, tx_hash: H256) {
+        let pending = {
+            let mut queue = self.transaction_queue.lock();
+            let nonce = match queue.find_by_hash(&self.relayer_address, &tx_hash) {
+                Some(pending) => pending.nonce(),
+                None => return,
+            };
+            let pending = match queue.get_mut(&self.relayer_address, &nonce) {
+                Some(pending) => pending,
+                None => return,
+            };
+
+            // the transaction is being checked, it will be watched again after checking
+            if pending.is_checking() {
+                return;
             }
+            pending.set_checking(true);
+            pending.clone()
+        };
+
+        warn!(target: "relayer",
+            "relayer {} transaction {:?} with nonce {} is reorged, check it again",
+            self.relayer_address,
+            tx_hash,
+            pending.nonce()
         );
 
-        {
-            let current_collation = ::std::mem::replace(&mut self.current_collation, None);
-            let current_collation = current_collation.expect("current_collation is some; qed");
-            let hashes = current_collation.request_hashes();
-            info!(target: "relayer",
-                  "relayer {} mark {} requests as {:?}",
-                  self.relayer_address, hashes.len(), PoolRequestTag::Executed );
-            self.pool
-                .lock()
-                .mark_by_hashes(&hashes, PoolRequestTag::Executed);
+        let _ = self.ethereum_monitor.lock().unsubscribe(
+            self.monitor_wather_id,
+            &EthereumMonitorTask::BlockNumberReached(pending.check_block_number()),
+        );
+
+        // the transaction may be back in transaction pool or dropped, checker will rebroadcast
+        // it if necessary
+        let checker = self.new_pending_checker(&pending);
+        self.pending_checkers.push(checker);
+    }
+
+    fn on_pending_checked(&mut self, check: PendingCheck) -> Result<(), Error> {
+        match check {
+            PendingCheck::Pending(nonce) => self.schedule_pending_check(&nonce),
+            PendingCheck::Rebroadcasted { collation, hash } => {
+                let nonce = collation.transaction().nonce;
+                info!(target: "relayer",
+                    "relayer {} rebroadcasted transaction with nonce {} as {:?}, gas price: {}",
+                    self.relayer_address,
+                    nonce,
+                    hash,
+                    collation.transaction().gas_price
+                );
+
+                self.pool
+                    .lock()
+                    .mark_broadcasted(
+                        &collation.request_hashes(),
+                        &self.relayer_address,
+                        &hash,
+                    );
+                self.history
+                    .lock()
+                    .record_rebroadcast(&self.relayer_address, &collation, hash);
+                self.transaction_queue
+                    .lock()
+                    .replace(&self.relayer_address, collation, hash);
+                self.schedule_pending_check(&nonce)
+            }
+            PendingCheck::Dropped { nonce, error } => {
+                // transactions with higher nonces can not be mined without this one, keep
+                // broadcasting it until it is mined
+                if self
+                    .transaction_queue
+                    .lock()
+                    .has_higher_nonce(&self.relayer_address, &nonce)
+                {
+                    warn!(target: "relayer",
+                        "relayer {} failed to broadcast transaction with nonce {} again, error: {}, retry later",
+                        self.relayer_address,
+                        nonce,
+                        error
+                    );
+                    return self.schedule_pending_check(&nonce);
+                }
+
+                // the nonce is used again by the next collation
+                let pending = self
+                    .transaction_queue
+                    .lock()
+                    .remove_by_nonce(&self.relayer_address, &nonce);
+
+                if let Some(pending) = pending {
+                    let hashes = pending.collation().request_hashes();
+                    warn!(target: "relayer",
+                        "relayer {} transaction with nonce {} is dropped, error: {}, reset {} request(s) to {:?}",
+                        self.relayer_address,
+                        nonce,
+                        error,
+                        hashes.len(),
+                        PoolRequestTag::Ready
+                    );
+
+                    self.unwatch_transactions(&pending);
+                    self.pool
+                        .lock()
+                        .mark_by_hashes(&hashes, PoolRequestTag::Ready);
+                    self.history
+                        .lock()
+                        .record_dropped(&self.relayer_address, &nonce, error);
+                }
+
+                Ok(())
+            }
         }
+    }
 
-        self.state_transfer(RelayerState::Ready, StateWorker::Ready);
-        Ok(Async::Ready(Some(self.state)))
+    fn poll_pending_transactions(&mut self) -> Result<(), Error> {
+        loop {
+            match self.monitor_receiver.poll().unwrap() {
+                Async::Ready(Some(EthereumMonitorResponse::Transaction(tx_hash))) => {
+                    self.on_transaction_executed(tx_hash);
+                }
+                Async::Ready(Some(EthereumMonitorResponse::BlockNumber(block_number))) => {
+                    self.on_block_number_reached(block_number);
+                }
+                Async::Ready(Some(EthereumMonitorResponse::TransactionReorged(tx_hash))) => {
+                    self.on_transaction_reorged(tx_hash);
+                }
+                Async::Ready(Some(_)) => {}
+                _ => break,
+            }
+        }
+
+        loop {
+            match self.outcome_resolvers.poll() {
+                Ok(Async::Ready(Some(outcome))) => self.on_outcomes_resolved(outcome),
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => break,
+                Err(unresolved) => self.on_outcomes_unresolved(unresolved),
+            }
+        }
+
+        loop {
+            match self.pending_checkers.poll() {
+                Ok(Async::Ready(Some(check))) => self.on_pending_checked(check)?,
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => break,
+                Err(err) => {
+                    warn!(target: "relayer",
+                        "relayer {} failed to check pending transaction, error: {}",
+                        self.relayer_address, err
+                    );
+                }
+            }
+        }
+
+        Ok(())
     }
 
     #[inline]
@@ -716,6 +1773,14 @@ where
             address: self.address(),
             dispatcher_address: self.dispatcher_address(),
             state: self.state(),
+            pending_transaction_count: self.pending_transaction_count(),
+            draining: self.draining,
+            stats: {
+                // time spent in current state is counted as well
+                let mut stats = self.stats.clone();
+                stats.add_state_duration(self.state, self.state_since.elapsed());
+                stats
+            },
         }
     }
 }
@@ -732,6 +1797,8 @@ where
     type Error = Error;
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        self.poll_pending_transactions()?;
+
         match self.state {
             RelayerState::Ready => self.poll_ready(),
             RelayerState::Preparing => self.poll_preparing(),
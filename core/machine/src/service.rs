@@ -27,11 +27,13 @@ use ethereum::monitor::Error as EthereumMonitorError;
 use ethereum::service::Error as EthereumServiceError;
 use pricer::Error as PriceServiceError;
 use traits::{EthereumMonitor, EthereumService, PoolRequestTag, PoolService, PriceService};
-use types::SignedRequest;
+use types::{
+    CollationFilter, CollationPage, CollationRecord, CollationRecordList, SignedRequest,
+};
 
 use super::{
-    Error, ErrorKind, RelayerEvent, RelayerInfo, RelayerMachine, RelayerMode, RelayerParams,
-    RelayerState,
+    CollationHistory, Error, ErrorKind, RelayerEvent, RelayerInfo, RelayerMachine, RelayerMode,
+    RelayerParams, RelayerState, TransactionQueue,
 };
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -46,6 +48,18 @@ pub struct Status {
     relayer_infos: Vec<RelayerInfo>,
 }
 
+impl Status {
+    #[inline]
+    pub fn is_working(&self) -> bool {
+        self.is_working
+    }
+
+    #[inline]
+    pub fn relayer_infos(&self) -> &[RelayerInfo] {
+        &self.relayer_infos
+    }
+}
+
 pub struct Params<C>
 where
     C: RequestConverter,
@@ -56,6 +70,23 @@ where
 
     pub interval: Duration,
     pub confirmation_count: u32,
+
+    // re-sign a pending transaction with a higher gas price after this many blocks
+    pub gas_price_bump_block_count: Option<u64>,
+    pub gas_price_bump_percent: u32,
+
+    // maximum number of broadcasted but unconfirmed transactions per relayer
+    pub max_pending_transactions: usize,
+
+    // minimum margin of request fees over transaction cost, no profitability check if none
+    pub min_profit_margin_percent: Option<u32>,
+
+    // unprofitable requests are not relayed again within this period
+    pub unprofitable_hold_back: Duration,
+
+    // maximum number of collation records kept in memory, records are paged from history file
+    // if it is set
+    pub max_history_records: usize,
 }
 
 pub struct Service<E, M, P, G, C>
@@ -67,11 +98,16 @@ where
     C: RequestConverter,
 {
     running: bool,
+    mode: RelayerMode,
+    // parameters shared by all relayer machines
+    params: Params<C>,
 
     ethereum: Arc<Mutex<E>>,
     ethereum_monitor: Arc<Mutex<M>>,
     pool: Arc<Mutex<P>>,
     gas_pricer: Arc<Mutex<G>>,
+    transaction_queue: Arc<Mutex<TransactionQueue>>,
+    history: Arc<Mutex<CollationHistory>>,
 
     relayer_machines: Mutex<Vec<RelayerMachine<E, M, P, G, C>>>,
     ticker: Interval,
@@ -99,6 +135,9 @@ where
             .sort_unstable_by(|a, b| a.address().cmp(&b.address()));
         params.relayer_keypairs.dedup();
 
+        let transaction_queue = Arc::new(Mutex::new(TransactionQueue::new()));
+        let history = Arc::new(Mutex::new(CollationHistory::new(params.max_history_records)));
+
         // create new relayer machines from keypairs
         let relayer_machines: Mutex<Vec<_>> = Mutex::new(
             params
@@ -107,17 +146,13 @@ where
                 .map(|keypair| {
                     let machine = RelayerMachine::new(
                         mode,
-                        RelayerParams {
-                            keypair: keypair.clone(),
-                            dispatcher: params.dispatcher.clone(),
-                            chain_id: params.chain_id.clone(),
-                            adjust_block_gas_limit_fn: None,
-                            confirmation_count: params.confirmation_count,
-                        },
+                        Self::new_relayer_params(&params, keypair.clone()),
                         ethereum.clone(),
                         ethereum_monitor.clone(),
                         pool.clone(),
                         gas_pricer.clone(),
+                        transaction_queue.clone(),
+                        history.clone(),
                     );
                     info!(target: "relayer",
                           "relayer machine {:?} created", keypair.address());
@@ -143,23 +178,90 @@ where
             relayer_machines.lock().len()
         );
 
+        let ticker = Interval::new_interval(params.interval);
         Service {
             running: false,
+            mode,
+            params,
             ethereum,
             ethereum_monitor,
             pool,
             gas_pricer,
+            transaction_queue,
+            history,
             relayer_machines,
-            ticker: Interval::new_interval(params.interval),
+            ticker,
+        }
+    }
+
+    /// Replaces the in-memory collation history, e.g. with one restored from file
+    pub fn set_history(&mut self, history: CollationHistory) {
+        *self.history.lock() = history;
+    }
+
+    fn new_relayer_params(params: &Params<C>, keypair: KeyPair) -> RelayerParams<C> {
+        RelayerParams {
+            keypair,
+            dispatcher: params.dispatcher.clone(),
+            chain_id: params.chain_id.clone(),
+            adjust_block_gas_limit_fn: None,
+            confirmation_count: params.confirmation_count,
+            gas_price_bump_block_count: params.gas_price_bump_block_count,
+            gas_price_bump_percent: params.gas_price_bump_percent,
+            max_pending_transactions: params.max_pending_transactions,
+            min_profit_margin_percent: params.min_profit_margin_percent,
+            unprofitable_hold_back: params.unprofitable_hold_back,
+        }
+    }
+
+    /// Removes relayers which finished their in-flight collations after draining
+    fn remove_drained_relayers(&mut self) {
+        let relayers = {
+            let mut machines = self.relayer_machines.lock();
+            let count = machines.len();
+            machines.retain(|machine| {
+                let drained = machine.is_drained();
+                if drained {
+                    info!(target: "relayer",
+                        "relayer {} is drained and removed", machine.address());
+                }
+                !drained
+            });
+
+            if count == machines.len() {
+                return;
+            }
+            machines.iter().map(RelayerMachine::address).collect()
+        };
+
+        self.pool.lock().set_relayers(relayers);
+        if traits::MachineService::relayer_count(self) == 0 {
+            warn!(target: "relayer", "All relayer is removed, we can not relay any token transfer request");
         }
     }
 
+    /// Removes executed and invalid requests from pool, reasons of invalid requests are kept in
+    /// their receipts. Returns the number of removed executed and invalid requests.
+    fn remove_finished_requests(&self) -> (usize, usize) {
+        let mut pool = self.pool.lock();
+        let executed = pool.remove_by_tag(PoolRequestTag::Executed).len();
+        let invalid = pool.remove_by_tag(PoolRequestTag::Invalid).len();
+        (executed, invalid)
+    }
+
     fn poll_ticker(&mut self) -> Poll<Option<()>, Error> {
         match self.ticker.poll() {
             Ok(Async::Ready(Some(_))) => {
                 trace!(target: "relayer",
                         "Relayer service: timeouts, try to create token transfer request transaction");
 
+                // transactions may be confirmed while relayers are busy
+                let (executed, invalid) = self.remove_finished_requests();
+                if executed + invalid > 0 {
+                    info!(target: "relayer", "{} executed and {} invalid request(s) removed from pool",
+                        executed, invalid);
+                }
+
                 let ready_count = self.pool.lock().count_by_tag(PoolRequestTag::Ready);
                 if ready_count > 0 {
                     // notify all idle relayer machine
@@ -168,7 +270,9 @@ where
                             .lock()
                             .iter_mut()
                             .fold(0, |selected, relayer| {
-                                if relayer.state() == RelayerState::Ready {
+                                if relayer.state() == RelayerState::Ready
+                                    && !relayer.is_draining()
+                                {
                                     info!(target: "relayer",
                                         "Relayer service: relayer {:?} is selected to relay token transfer request",
                                         relayer.address()
@@ -199,9 +303,10 @@ where
         for relayer in self.relayer_machines.lock().iter_mut() {
             match relayer.poll() {
                 Ok(Async::Ready(Some(RelayerState::Ready))) => {
-                    let requests = self.pool.lock().remove_by_tag(PoolRequestTag::Executed);
-                    info!(target: "relayer", "{} executed request(s) removed from pool by relayer {}",
-                        requests.len(), relayer.address());
+                    let (executed, invalid) = self.remove_finished_requests();
+                    info!(target: "relayer",
+                        "{} executed and {} invalid request(s) removed from pool by relayer {}",
+                        executed, invalid, relayer.address());
                 }
                 Ok(Async::Ready(Some(RelayerState::Preparing))) => {}
                 Ok(Async::Ready(Some(RelayerState::GasEstimating))) => {}
@@ -220,6 +325,7 @@ where
             }
         }
 
+        self.remove_drained_relayers();
         Ok(Async::NotReady)
     }
 }
@@ -238,6 +344,7 @@ where
     type SignedRequest = SignedRequest;
     type RelayerMode = RelayerMode;
     type RelayerInfo = RelayerInfo;
+    type KeyPair = KeyPair;
 
     #[inline]
     fn is_working(&self) -> bool {
@@ -323,6 +430,15 @@ where
     ) -> Result<Option<RelayerInfo>, Self::MachineError> {
         let address = params.keypair.address();
         if self.contains_relayer(&address) {
+            // a relayer which is being removed keeps working
+            if let Some(machine) = self
+                .relayer_machines
+                .lock()
+                .iter_mut()
+                .find(|machine| machine.address().eq(&address))
+            {
+                machine.cancel_drain();
+            }
             return Ok(self.relayer_info(&address));
         }
 
@@ -334,6 +450,8 @@ where
                 self.ethereum_monitor.clone(),
                 self.pool.clone(),
                 self.gas_pricer.clone(),
+                self.transaction_queue.clone(),
+                self.history.clone(),
             );
             let info = machine.info();
             let mut machines = self.relayer_machines.lock();
@@ -347,22 +465,30 @@ where
     }
 
     fn remove_relayer(&mut self, address: &Address) -> Result<(), Self::MachineError> {
-        if self.contains_relayer(address) {
-            let relayers = {
-                let mut machines = self.relayer_machines.lock();
-                machines.retain(|machine| !machine.address().eq(address));
-                machines.iter().map(RelayerMachine::address).collect()
-            };
-            self.pool.lock().set_relayers(relayers);
-        }
-
-        if self.relayer_count() == 0 {
-            warn!(target: "relayer", "All relayer is removed, we can not relay any token transfer request");
+        if let Some(machine) = self
+            .relayer_machines
+            .lock()
+            .iter_mut()
+            .find(|machine| machine.address().eq(address))
+        {
+            machine.drain();
         }
 
+        // an idle relayer is removed immediately
+        self.remove_drained_relayers();
         Ok(())
     }
 
+    #[inline]
+    fn relayer_params(&self, keypair: KeyPair) -> RelayerParams<C> {
+        Self::new_relayer_params(&self.params, keypair)
+    }
+
+    #[inline]
+    fn default_relayer_mode(&self) -> RelayerMode {
+        self.mode
+    }
+
     fn relayer_mode(&self, relayer_address: &Address) -> Option<RelayerMode> {
         self.relayer_machines
             .lock()
@@ -437,6 +563,20 @@ where
 
         dispatchers.into_iter().collect()
     }
+
+    #[inline]
+    fn collation(&self, transaction_hash: &H256) -> Option<CollationRecord> {
+        self.history.lock().get(transaction_hash)
+    }
+
+    #[inline]
+    fn collation_history(
+        &self,
+        filter: &CollationFilter,
+        page: &CollationPage,
+    ) -> CollationRecordList {
+        self.history.lock().query(filter, page)
+    }
 }
 
 impl<E, M, P, G, C> Stream for Service<E, M, P, G, C>
@@ -13,3 +13,601 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::{Address, H256, U256};
+use std::collections::HashMap;
+use ethkey::{Generator, Random};
+use serde_json;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::{env, process};
+
+use collation::{ClosedCollation, EmptyRequestConverter, OpenCollation, RequestDispatcher};
+use contract_abi::ERC20AbiDecoder;
+use types::{
+    CollationFilter, CollationOutcome, CollationPage, CollationRecordList,
+    EthRpcTransactionReceipt, SignedRequest,
+};
+
+use history::CollationHistory;
+use machine::{
+    bump_gas_price, executed_fees, is_drained, pending_check_block_number, request_outcomes,
+    shrink_unprofitable, RelayerState,
+};
+use transaction_queue::{PendingTransaction, TransactionQueue};
+
+fn closed_collation(nonce: u64, gas_price: u64) -> ClosedCollation {
+    let keypair = Random.generate().unwrap();
+    let request = types::Request {
+        nonce: U256::from(nonce),
+        ..types::Request::empty()
+    }.null_sign();
+    let dispatcher = RequestDispatcher::new(Address::from(1), EmptyRequestConverter {});
+
+    let mut collation = OpenCollation::with_single_request(request);
+    collation.update_unestimated(
+        &dispatcher,
+        &U256::from(nonce),
+        &U256::from(gas_price),
+        &U256::zero(),
+    );
+    collation
+        .close_with_gas(keypair.secret().clone(), None, &U256::from(100_000))
+        .unwrap()
+}
+
+// collation of requests with given (token, fee), its transaction costs 1,000,000 wei
+fn collation_with_fees(fees: &[(u64, u64)]) -> ClosedCollation {
+    let keypair = Random.generate().unwrap();
+    let requests = fees
+        .iter()
+        .enumerate()
+        .map(|(nonce, (token, fee))| {
+            types::Request {
+                token_address: Address::from(*token),
+                nonce: U256::from(nonce),
+                fee: U256::from(*fee),
+                ..types::Request::empty()
+            }.null_sign()
+        }).collect();
+    let dispatcher = RequestDispatcher::new(Address::from(1), EmptyRequestConverter {});
+
+    let mut collation = OpenCollation::with_requests(requests);
+    collation.update_unestimated(&dispatcher, &U256::zero(), &U256::from(10), &U256::zero());
+    collation
+        .close_with_gas(keypair.secret().clone(), None, &U256::from(100_000))
+        .unwrap()
+}
+
+#[test]
+fn test_bump_gas_price() {
+    assert_eq!(bump_gas_price(U256::from(1000), 10), U256::from(1100));
+    // the bumped amount is rounded down
+    assert_eq!(bump_gas_price(U256::from(1005), 10), U256::from(1105));
+    assert_eq!(bump_gas_price(U256::from(19), 10), U256::from(20));
+    // a replacement always pays more than the original one
+    assert_eq!(bump_gas_price(U256::from(5), 10), U256::from(6));
+    assert_eq!(bump_gas_price(U256::from(1000), 0), U256::from(1001));
+    assert_eq!(bump_gas_price(U256::zero(), 10), U256::one());
+}
+
+#[test]
+fn test_pending_check_block_number() {
+    assert_eq!(
+        pending_check_block_number(U256::from(100), Some(3)),
+        U256::from(103)
+    );
+    // a pending transaction is still checked if gas price bumping is disabled
+    assert_eq!(
+        pending_check_block_number(U256::from(100), None),
+        U256::from(120)
+    );
+}
+
+#[test]
+fn test_gas_price_bump_is_due_after_block_count() {
+    let relayer = Address::from(1);
+    let mut queue = TransactionQueue::new();
+    queue.push(
+        &relayer,
+        PendingTransaction::new(
+            closed_collation(0, 1000),
+            H256::from(1),
+            pending_check_block_number(U256::from(100), Some(3)),
+        ),
+    );
+
+    assert!(queue.due(&relayer, &U256::from(102)).is_empty());
+    assert_eq!(queue.due(&relayer, &U256::from(103)), vec![U256::zero()]);
+    assert_eq!(queue.due(&relayer, &U256::from(110)), vec![U256::zero()]);
+
+    // a transaction being checked is not due again
+    queue.get_mut(&relayer, &U256::zero()).unwrap().set_checking(true);
+    assert!(queue.due(&relayer, &U256::from(110)).is_empty());
+}
+
+#[test]
+fn test_transaction_queue_push() {
+    let relayer = Address::from(1);
+    let mut queue = TransactionQueue::new();
+    queue.push(
+        &relayer,
+        PendingTransaction::new(closed_collation(2, 1000), H256::from(2), U256::zero()),
+    );
+    queue.push(
+        &relayer,
+        PendingTransaction::new(closed_collation(1, 1000), H256::from(1), U256::zero()),
+    );
+    // a transaction which is already pushed is ignored
+    queue.push(
+        &relayer,
+        PendingTransaction::new(closed_collation(3, 1000), H256::from(1), U256::zero()),
+    );
+
+    assert_eq!(queue.len(&relayer), 2);
+    assert_eq!(queue.len(&Address::from(2)), 0);
+    assert_eq!(
+        queue.find_by_hash(&relayer, &H256::from(2)).map(PendingTransaction::nonce),
+        Some(U256::from(2))
+    );
+
+    // a transaction with the same nonce is replaced
+    queue.push(
+        &relayer,
+        PendingTransaction::new(closed_collation(2, 1000), H256::from(3), U256::zero()),
+    );
+    assert_eq!(queue.len(&relayer), 2);
+    assert!(queue.find_by_hash(&relayer, &H256::from(2)).is_none());
+    assert!(queue.remove_by_hash(&relayer, &H256::from(2)).is_none());
+    assert_eq!(
+        queue.find_by_hash(&relayer, &H256::from(3)).map(PendingTransaction::nonce),
+        Some(U256::from(2))
+    );
+}
+
+#[test]
+fn test_transaction_queue_replace() {
+    let relayer = Address::from(1);
+    let mut queue = TransactionQueue::new();
+    let collation = closed_collation(1, 1000);
+    queue.push(
+        &relayer,
+        PendingTransaction::new(collation.clone(), H256::from(1), U256::zero()),
+    );
+
+    let resigned = collation.resign_with_gas_price(
+        Random.generate().unwrap().secret().clone(),
+        None,
+        &U256::from(1100),
+    );
+    assert!(queue.replace(&relayer, resigned, H256::from(2)));
+    assert!(!queue.replace(&relayer, closed_collation(2, 1000), H256::from(3)));
+    assert!(!queue.replace(&Address::from(2), collation, H256::from(4)));
+
+    {
+        let pending = queue.get_mut(&relayer, &U256::one()).unwrap();
+        assert_eq!(pending.hashes(), &vec![H256::from(1), H256::from(2)]);
+        assert_eq!(pending.collation().transaction().gas_price, U256::from(1100));
+    }
+
+    // any of replaced transactions removes the pending transaction once it is executed
+    let removed = queue.remove_by_hash(&relayer, &H256::from(1)).unwrap();
+    assert_eq!(removed.nonce(), U256::one());
+    assert_eq!(queue.len(&relayer), 0);
+    assert!(queue.remove_by_hash(&relayer, &H256::from(2)).is_none());
+}
+
+#[test]
+fn test_transaction_queue_remove_by_nonce() {
+    let relayer = Address::from(1);
+    let mut queue = TransactionQueue::new();
+    for nonce in 1..4 {
+        queue.push(
+            &relayer,
+            PendingTransaction::new(
+                closed_collation(nonce, 1000),
+                H256::from(nonce),
+                U256::zero(),
+            ),
+        );
+    }
+
+    let removed = queue.remove_by_nonce(&relayer, &U256::from(2)).unwrap();
+    assert_eq!(removed.hashes(), &vec![H256::from(2)]);
+    assert!(queue.remove_by_nonce(&relayer, &U256::from(2)).is_none());
+    assert!(queue.remove_by_hash(&relayer, &H256::from(2)).is_none());
+    assert_eq!(queue.len(&relayer), 2);
+
+    assert!(queue.has_higher_nonce(&relayer, &U256::from(2)));
+    assert!(!queue.has_higher_nonce(&relayer, &U256::from(3)));
+}
+
+#[test]
+fn test_transaction_queue_next_nonce() {
+    let relayer = Address::from(1);
+    let mut queue = TransactionQueue::new();
+    assert_eq!(queue.next_nonce(&relayer, U256::from(5)), U256::from(5));
+
+    for nonce in 5..8 {
+        queue.push(
+            &relayer,
+            PendingTransaction::new(
+                closed_collation(nonce, 1000),
+                H256::from(nonce),
+                U256::zero(),
+            ),
+        );
+    }
+    assert_eq!(queue.next_nonce(&relayer, U256::from(5)), U256::from(8));
+    // pending transactions may not be counted in the nonce on chain yet
+    assert_eq!(queue.next_nonce(&relayer, U256::from(3)), U256::from(8));
+    assert_eq!(queue.next_nonce(&relayer, U256::from(9)), U256::from(9));
+
+    // nonce of a dropped transaction is used again
+    queue.remove_by_nonce(&relayer, &U256::from(7));
+    queue.remove_by_nonce(&relayer, &U256::from(6));
+    assert_eq!(queue.next_nonce(&relayer, U256::from(5)), U256::from(6));
+    // unless it is already used on chain
+    assert_eq!(queue.next_nonce(&relayer, U256::from(7)), U256::from(7));
+    assert_eq!(queue.next_nonce(&relayer, U256::from(8)), U256::from(8));
+
+    queue.push(
+        &relayer,
+        PendingTransaction::new(closed_collation(6, 1000), H256::from(16), U256::zero()),
+    );
+    assert_eq!(queue.next_nonce(&relayer, U256::from(5)), U256::from(7));
+    queue.push(
+        &relayer,
+        PendingTransaction::new(closed_collation(7, 1000), H256::from(17), U256::zero()),
+    );
+    assert_eq!(queue.next_nonce(&relayer, U256::from(5)), U256::from(8));
+
+    // the last dropped transaction does not leave a gap either
+    queue.remove_by_nonce(&relayer, &U256::from(7));
+    assert_eq!(queue.next_nonce(&relayer, U256::from(5)), U256::from(7));
+}
+
+fn transfer_request(token: u64, receiver: u64, value: u64) -> SignedRequest {
+    types::Request {
+        token_address: Address::from(token),
+        receiver: Address::from(receiver),
+        value: U256::from(value),
+        ..types::Request::empty()
+    }.null_sign()
+}
+
+fn transfer_receipt(transfers: &[(Address, Address, Address, u64)]) -> EthRpcTransactionReceipt {
+    let logs: Vec<_> = transfers
+        .iter()
+        .map(|(token, from, to, value)| {
+            format!(
+                r#"{{"address":"{:?}","topics":["{:?}","{:?}","{:?}"],"data":"0x{:064x}","type":"mined"}}"#,
+                token,
+                ERC20AbiDecoder::transfer_event_topic(),
+                H256::from(*from),
+                H256::from(*to),
+                value
+            )
+        }).collect();
+
+    EthRpcTransactionReceipt {
+        logs: serde_json::from_str(&format!("[{}]", logs.join(","))).unwrap(),
+        status_code: Some(1u64.into()),
+        ..EthRpcTransactionReceipt::default()
+    }
+}
+
+#[test]
+fn test_request_outcomes() {
+    let tx_hash = H256::from(1);
+    let executed = transfer_request(1, 2, 100);
+    let failed = transfer_request(1, 3, 100);
+    let sender = *executed.sender();
+
+    let receipt = transfer_receipt(&[
+        (Address::from(1), sender, Address::from(2), 100),
+        // value or token does not match
+        (Address::from(1), sender, Address::from(3), 99),
+        (Address::from(4), sender, Address::from(3), 100),
+    ]);
+    let outcomes = request_outcomes(&tx_hash, &[executed.clone(), failed.clone()], &receipt);
+    assert_eq!(outcomes[0], (*executed.hash(), None));
+    assert_eq!(outcomes[1].0, *failed.hash());
+    assert!(outcomes[1].1.is_some());
+}
+
+#[test]
+fn test_request_outcomes_match_each_transfer_once() {
+    let tx_hash = H256::from(1);
+    let (first, second) = (transfer_request(1, 2, 100), transfer_request(1, 2, 100));
+    let sender = *first.sender();
+
+    let receipt = transfer_receipt(&[(Address::from(1), sender, Address::from(2), 100)]);
+    let outcomes = request_outcomes(&tx_hash, &[first, second], &receipt);
+    assert!(outcomes[0].1.is_none());
+    assert!(outcomes[1].1.is_some());
+}
+
+#[test]
+fn test_request_outcomes_of_reverted_transaction() {
+    let tx_hash = H256::from(1);
+    let request = transfer_request(1, 2, 100);
+    let sender = *request.sender();
+
+    let mut receipt = transfer_receipt(&[(Address::from(1), sender, Address::from(2), 100)]);
+    receipt.status_code = Some(0u64.into());
+    let outcomes = request_outcomes(&tx_hash, &[request.clone()], &receipt);
+    assert_eq!(
+        outcomes,
+        vec![(
+            *request.hash(),
+            Some(format!("transaction {:?} is reverted", tx_hash))
+        )]
+    );
+}
+
+#[test]
+fn test_shrink_unprofitable() {
+    // 1 token unit is worth 1 wei
+    let mut token_prices = HashMap::new();
+    token_prices.insert(Address::from(1), U256::from(10).pow(U256::from(18)));
+
+    let profitable = collation_with_fees(&[(1, 600_000), (1, 600_000)]);
+    assert!(shrink_unprofitable(&profitable, &token_prices, 10).is_none());
+    // total fee does not cover the margin
+    assert!(shrink_unprofitable(&profitable, &token_prices, 30).is_some());
+
+    // requests paying less than their share of cost with margin are dropped
+    let collation = collation_with_fees(&[(1, 900_000), (1, 100_000)]);
+    let kept: Vec<H256> = shrink_unprofitable(&collation, &token_prices, 10)
+        .unwrap()
+        .iter()
+        .map(|req| *req.hash())
+        .collect();
+    assert_eq!(kept, vec![*collation.requests()[0].hash()]);
+
+    // fee which overflows in wei is profitable
+    let mut expensive = HashMap::new();
+    expensive.insert(Address::from(1), U256::max_value());
+    let collation = collation_with_fees(&[(1, 2 * 10u64.pow(18)), (1, 2 * 10u64.pow(18))]);
+    assert!(shrink_unprofitable(&collation, &expensive, 10).is_none());
+
+    // fee of a token without known price is not counted
+    let collation = collation_with_fees(&[(2, 2_000_000)]);
+    assert_eq!(
+        shrink_unprofitable(&collation, &token_prices, 10).map(|requests| requests.len()),
+        Some(0)
+    );
+}
+
+#[test]
+fn test_is_drained() {
+    assert!(is_drained(true, RelayerState::Ready, 0, 0));
+    // relayer keeps working until it is drained
+    assert!(!is_drained(false, RelayerState::Ready, 0, 0));
+    assert!(!is_drained(true, RelayerState::TxBroadcasting, 0, 0));
+    // collation is broadcasted but its outcome is not resolved yet
+    assert!(!is_drained(true, RelayerState::Ready, 1, 0));
+    assert!(!is_drained(true, RelayerState::Ready, 0, 1));
+}
+
+#[test]
+fn test_executed_fees() {
+    let tx_hash = H256::from(1);
+    let fee_request = |token: u64, receiver: u64, fee: u64| {
+        types::Request {
+            token_address: Address::from(token),
+            receiver: Address::from(receiver),
+            value: U256::from(100),
+            fee: U256::from(fee),
+            ..types::Request::empty()
+        }.null_sign()
+    };
+    let requests = vec![
+        fee_request(1, 2, 10),
+        fee_request(1, 3, 20),
+        fee_request(4, 2, 30),
+    ];
+    let sender = *requests[0].sender();
+
+    let mut receipt = transfer_receipt(&[
+        (Address::from(1), sender, Address::from(2), 100),
+        (Address::from(4), sender, Address::from(2), 100),
+    ]);
+    let fees = executed_fees(&requests, &request_outcomes(&tx_hash, &requests, &receipt));
+    assert_eq!(fees.len(), 2);
+    assert_eq!(fees[&Address::from(1)], U256::from(10));
+    assert_eq!(fees[&Address::from(4)], U256::from(30));
+
+    // no fee is earned from a reverted transaction
+    receipt.status_code = Some(0u64.into());
+    let fees = executed_fees(&requests, &request_outcomes(&tx_hash, &requests, &receipt));
+    assert!(fees.is_empty());
+}
+
+fn history_path(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("machine-{}-{}.jsonl", name, process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn line_count(path: &PathBuf) -> usize {
+    BufReader::new(fs::File::open(path).unwrap())
+        .lines()
+        .count()
+}
+
+#[test]
+fn test_history_replay() {
+    let path = history_path("replay");
+    let relayer = Address::from(1);
+    let (executed, pending) = (closed_collation(1, 10), closed_collation(2, 10));
+
+    {
+        let mut history = CollationHistory::open(&path, 10).unwrap();
+        history.record_broadcast(&relayer, &executed, H256::from(1));
+        history.record_broadcast(&relayer, &pending, H256::from(2));
+        history.record_executed(
+            &H256::from(1),
+            Some(U256::from(100)),
+            Some(U256::from(50_000)),
+            &[(executed.request_hashes()[0], None)],
+        );
+    }
+    assert_eq!(line_count(&path), 3);
+
+    // a line broken by crashing while writing is skipped
+    {
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"transactionHash\":").unwrap();
+    }
+
+    let history = CollationHistory::open(&path, 10).unwrap();
+    assert_eq!(history.len(), 2);
+    let record = history.get(&H256::from(1)).unwrap();
+    assert_eq!(record.outcome, CollationOutcome::Executed);
+    assert_eq!(record.block_number, Some(U256::from(100)));
+    // outcome of a pending transaction can not be resolved after restart
+    assert_eq!(
+        history.get(&H256::from(2)).unwrap().outcome,
+        CollationOutcome::Unknown
+    );
+    // only the latest version of each record is kept
+    assert_eq!(line_count(&path), 2);
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_history_rebroadcast_and_drop() {
+    let relayer = Address::from(1);
+    let mut history = CollationHistory::new(10);
+    history.record_broadcast(&relayer, &closed_collation(1, 10), H256::from(1));
+    history.record_rebroadcast(&relayer, &closed_collation(1, 20), H256::from(2));
+
+    // the record is found by hash of any of its transactions
+    let record = history.get(&H256::from(2)).unwrap();
+    assert_eq!(record.transaction_hash, H256::from(2));
+    assert_eq!(
+        record.transaction_hashes,
+        vec![H256::from(1), H256::from(2)]
+    );
+    assert_eq!(record.gas_price, U256::from(20));
+    assert_eq!(record.outcome, CollationOutcome::Pending);
+    assert_eq!(history.len(), 1);
+
+    history.record_dropped(&relayer, &U256::from(1), "nonce is used".to_owned());
+    let record = history.get(&H256::from(1)).unwrap();
+    assert_eq!(record.outcome, CollationOutcome::Dropped);
+    assert_eq!(record.reason, Some("nonce is used".to_owned()));
+    assert!(record.finished_at.is_some());
+
+    // a finished record is not changed by the next transaction with the same nonce
+    history.record_rebroadcast(&relayer, &closed_collation(1, 30), H256::from(3));
+    assert!(history.get(&H256::from(3)).is_none());
+}
+
+#[test]
+fn test_history_unresolved_outcome() {
+    let relayer = Address::from(1);
+    let mut history = CollationHistory::new(10);
+    history.record_broadcast(&relayer, &closed_collation(1, 10), H256::from(1));
+    history.record_unresolved(&H256::from(1), "failed to fetch receipt".to_owned());
+
+    let record = history.get(&H256::from(1)).unwrap();
+    assert_eq!(record.outcome, CollationOutcome::Unknown);
+    assert!(record.finished_at.is_some());
+}
+
+#[test]
+fn test_history_query_pages_from_file() {
+    let path = history_path("query");
+    let collations: Vec<_> = (0..5).map(|nonce| closed_collation(nonce, 10)).collect();
+
+    // only 2 records are cached, the others are read from history file
+    let mut history = CollationHistory::open(&path, 2).unwrap();
+    for (index, collation) in collations.iter().enumerate() {
+        let relayer = Address::from(index as u64 % 2 + 1);
+        history.record_broadcast(&relayer, collation, H256::from(index as u64 + 1));
+    }
+    history.record_executed(
+        &H256::from(1),
+        None,
+        None,
+        &[(collations[0].request_hashes()[0], None)],
+    );
+    assert_eq!(history.len(), 5);
+
+    let page = |offset, limit| CollationPage { offset, limit };
+    let hashes = |list: &CollationRecordList| -> Vec<H256> {
+        list.records
+            .iter()
+            .map(|record| record.transaction_hash)
+            .collect()
+    };
+
+    let list = history.query(&CollationFilter::default(), &page(1, 2));
+    assert_eq!(list.total, 5);
+    assert_eq!(hashes(&list), vec![H256::from(4), H256::from(3)]);
+
+    let list = history.query(&CollationFilter::default(), &page(4, 2));
+    assert_eq!(hashes(&list), vec![H256::from(1)]);
+    assert_eq!(list.records[0].outcome, CollationOutcome::Executed);
+
+    let filter = CollationFilter {
+        relayer: Some(Address::from(1)),
+        ..CollationFilter::default()
+    };
+    let list = history.query(&filter, &page(0, 10));
+    assert_eq!(list.total, 3);
+    assert_eq!(
+        hashes(&list),
+        vec![H256::from(5), H256::from(3), H256::from(1)]
+    );
+
+    let filter = CollationFilter {
+        outcome: Some(CollationOutcome::Pending),
+        request_hash: Some(collations[1].request_hashes()[0]),
+        ..CollationFilter::default()
+    };
+    let list = history.query(&filter, &page(0, 10));
+    assert_eq!(list.total, 1);
+    assert_eq!(hashes(&list), vec![H256::from(2)]);
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_history_compaction() {
+    let path = history_path("compaction");
+    let relayer = Address::from(1);
+    let collation = closed_collation(1, 10);
+
+    let mut history = CollationHistory::open(&path, 10).unwrap();
+    for index in 1..601 {
+        history.record_broadcast(&relayer, &collation, H256::from(index));
+        history.record_dropped(&relayer, &U256::from(1), "dropped".to_owned());
+    }
+
+    // outdated versions of records are removed while the history is open
+    assert!(line_count(&path) < 1200);
+    assert_eq!(history.len(), 600);
+
+    let history = CollationHistory::open(&path, 10).unwrap();
+    assert_eq!(history.len(), 600);
+    assert_eq!(line_count(&path), 600);
+    let list = history.query(
+        &CollationFilter {
+            outcome: Some(CollationOutcome::Dropped),
+            ..CollationFilter::default()
+        },
+        &CollationPage {
+            offset: 599,
+            limit: 10,
+        },
+    );
+    assert_eq!(list.total, 600);
+    assert_eq!(list.records[0].transaction_hash, H256::from(1));
+
+    let _ = fs::remove_file(&path);
+}
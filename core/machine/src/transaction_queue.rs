@@ -13,20 +13,94 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use ethereum_types::{Address, H256, U256};
 
-use ethcore_transaction::SignedTransaction;
+use collation::ClosedCollation;
+
+/// A broadcasted transaction which is not confirmed yet
+#[derive(Debug, Clone)]
+pub struct PendingTransaction {
+    // nonce of transaction
+    nonce: U256,
+
+    // latest (re)signed collation of this nonce
+    collation: ClosedCollation,
+
+    // hashes of all transactions broadcasted with this nonce, the latest one is the last
+    hashes: Vec<H256>,
+
+    // block number at which this transaction should be checked again
+    check_block_number: U256,
+
+    // whether this transaction is being checked
+    checking: bool,
+}
+
+impl PendingTransaction {
+    pub fn new(
+        collation: ClosedCollation,
+        hash: H256,
+        check_block_number: U256,
+    ) -> PendingTransaction {
+        PendingTransaction {
+            nonce: collation.transaction().nonce,
+            collation,
+            hashes: vec![hash],
+            check_block_number,
+            checking: false,
+        }
+    }
+
+    #[inline]
+    pub fn nonce(&self) -> U256 {
+        self.nonce
+    }
+
+    #[inline]
+    pub fn collation(&self) -> &ClosedCollation {
+        &self.collation
+    }
+
+    #[inline]
+    pub fn hashes(&self) -> &Vec<H256> {
+        &self.hashes
+    }
+
+    #[inline]
+    pub fn check_block_number(&self) -> U256 {
+        self.check_block_number
+    }
+
+    #[inline]
+    pub fn is_checking(&self) -> bool {
+        self.checking
+    }
+
+    #[inline]
+    pub fn set_checking(&mut self, checking: bool) {
+        self.checking = checking;
+    }
+
+    #[inline]
+    pub fn set_check_block_number(&mut self, check_block_number: U256) {
+        self.check_block_number = check_block_number;
+    }
+}
 
 pub struct TransactionQueue {
     inner: HashMap<Address, TransactionQueueInner>,
 }
 
 struct TransactionQueueInner {
-    queue: Vec<SignedTransaction>,
+    // pending transactions sorted by nonce
+    queue: Vec<PendingTransaction>,
     transactions_set: HashSet<H256>,
+    // nonce of the latest pushed transaction
     current_nonce: U256,
+    // nonces of dropped transactions which are not used again yet
+    released_nonces: BTreeSet<U256>,
 }
 
 impl TransactionQueue {
@@ -35,6 +109,107 @@ impl TransactionQueue {
             inner: HashMap::new(),
         }
     }
+
+    /// Returns count of pending transactions of a relayer
+    pub fn len(&self, address: &Address) -> usize {
+        self.inner
+            .get(address)
+            .map(|inner| inner.queue.len())
+            .unwrap_or(0)
+    }
+
+    /// Returns the nonce for the next transaction of a relayer
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - relayer address
+    /// * `chain_nonce` - nonce of relayer fetched from Ethereum
+    ///
+    pub fn next_nonce(&self, address: &Address, chain_nonce: U256) -> U256 {
+        let inner = match self.inner.get(address) {
+            Some(inner) => inner,
+            None => return chain_nonce,
+        };
+
+        // a nonce released by a dropped transaction is used again first, otherwise transactions
+        // with higher nonces could never be mined
+        if let Some(nonce) = inner.released_nonces.range(chain_nonce..).next() {
+            return *nonce;
+        }
+
+        match inner.queue.is_empty() {
+            true => chain_nonce,
+            false => U256::max(chain_nonce, inner.current_nonce + U256::one()),
+        }
+    }
+
+    /// Returns `true` if a relayer has a pending transaction with higher nonce than the given one
+    pub fn has_higher_nonce(&self, address: &Address, nonce: &U256) -> bool {
+        self.inner
+            .get(address)
+            .map(|inner| inner.queue.iter().any(|pending| pending.nonce > *nonce))
+            .unwrap_or(false)
+    }
+
+    pub fn push(&mut self, address: &Address, pending: PendingTransaction) {
+        self.inner
+            .entry(*address)
+            .or_insert_with(TransactionQueueInner::new)
+            .push(pending);
+    }
+
+    pub fn find_by_hash(&self, address: &Address, hash: &H256) -> Option<&PendingTransaction> {
+        self.inner
+            .get(address)?
+            .queue
+            .iter()
+            .find(|pending| pending.hashes.contains(hash))
+    }
+
+    pub fn get_mut(&mut self, address: &Address, nonce: &U256) -> Option<&mut PendingTransaction> {
+        self.inner
+            .get_mut(address)?
+            .queue
+            .iter_mut()
+            .find(|pending| pending.nonce == *nonce)
+    }
+
+    /// Replaces the collation of a pending transaction with a re-signed one
+    pub fn replace(&mut self, address: &Address, collation: ClosedCollation, hash: H256) -> bool {
+        match self.inner.get_mut(address) {
+            Some(inner) => inner.replace(collation, hash),
+            None => false,
+        }
+    }
+
+    /// Returns nonces of pending transactions which should be checked at given block number
+    pub fn due(&self, address: &Address, block_number: &U256) -> Vec<U256> {
+        match self.inner.get(address) {
+            Some(inner) => inner
+                .queue
+                .iter()
+                .filter(|pending| !pending.checking && pending.check_block_number <= *block_number)
+                .map(|pending| pending.nonce)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Removes a dropped transaction, its nonce is used again by the next transaction
+    pub fn remove_by_nonce(
+        &mut self,
+        address: &Address,
+        nonce: &U256,
+    ) -> Option<PendingTransaction> {
+        let inner = self.inner.get_mut(address)?;
+        let pending = inner.remove_by_nonce(nonce)?;
+        inner.released_nonces.insert(*nonce);
+        Some(pending)
+    }
+
+    pub fn remove_by_hash(&mut self, address: &Address, hash: &H256) -> Option<PendingTransaction> {
+        self.inner.get_mut(address)?.remove_by_hash(hash)
+    }
 }
 
 impl TransactionQueueInner {
@@ -43,49 +218,77 @@ impl TransactionQueueInner {
             queue: Vec::new(),
             transactions_set: HashSet::new(),
             current_nonce: 0.into(),
+            released_nonces: BTreeSet::new(),
         }
     }
 
-    pub fn current_nonce(&self) -> U256 {
-        self.current_nonce
-    }
-
-    pub fn push(&mut self, tx: SignedTransaction) {
-        if tx.as_unsigned().nonce < self.current_nonce {
+    pub fn push(&mut self, pending: PendingTransaction) {
+        if pending
+            .hashes
+            .iter()
+            .any(|hash| self.transactions_set.contains(hash))
+        {
             return;
         }
 
-        let hash = tx.hash();
-        if !self.transactions_set.contains(&hash) {
-            self.current_nonce = tx.as_unsigned().nonce;
-            self.transactions_set.insert(hash);
-            self.queue.push(tx);
+        // a transaction with the same nonce is replaced
+        self.remove_by_nonce(&pending.nonce);
+
+        if self.queue.is_empty() || pending.nonce > self.current_nonce {
+            self.current_nonce = pending.nonce;
         }
+
+        // released nonces lower than a new transaction are already used on chain
+        self.released_nonces = self.released_nonces.split_off(&pending.nonce);
+        self.released_nonces.remove(&pending.nonce);
+
+        self.transactions_set.extend(pending.hashes.iter().cloned());
+        let position = self
+            .queue
+            .iter()
+            .position(|p| p.nonce > pending.nonce)
+            .unwrap_or(self.queue.len());
+        self.queue.insert(position, pending);
     }
 
-    pub fn remove_by_nonce(&mut self, nonce: &U256) {
-        if *nonce > self.current_nonce {
-            return;
+    pub fn replace(&mut self, collation: ClosedCollation, hash: H256) -> bool {
+        let nonce = collation.transaction().nonce;
+        match self.queue.iter_mut().find(|pending| pending.nonce == nonce) {
+            Some(pending) => {
+                if !pending.hashes.contains(&hash) {
+                    pending.hashes.push(hash);
+                }
+                pending.collation = collation;
+                self.transactions_set.insert(hash);
+                true
+            }
+            None => false,
         }
+    }
 
-        let mut hash = H256::default();
-        for tx in &self.queue {
-            if tx.as_unsigned().nonce == *nonce {
-                hash = tx.hash();
-                break;
-            }
+    pub fn remove_by_nonce(&mut self, nonce: &U256) -> Option<PendingTransaction> {
+        let position = self
+            .queue
+            .iter()
+            .position(|pending| pending.nonce == *nonce)?;
+        let pending = self.queue.remove(position);
+        for hash in pending.hashes.iter() {
+            self.transactions_set.remove(hash);
         }
 
-        self.transactions_set.remove(&hash);
-        self.queue.retain(|tx| tx.as_unsigned().nonce != *nonce);
+        Some(pending)
     }
 
-    pub fn remove_by_hash(&mut self, hash: &H256) {
+    pub fn remove_by_hash(&mut self, hash: &H256) -> Option<PendingTransaction> {
         if !self.transactions_set.contains(hash) {
-            return;
+            return None;
         }
 
-        self.transactions_set.remove(hash);
-        self.queue.retain(|tx| tx.hash() != *hash);
+        let nonce = self
+            .queue
+            .iter()
+            .find(|pending| pending.hashes.contains(hash))?
+            .nonce;
+        self.remove_by_nonce(&nonce)
     }
 }
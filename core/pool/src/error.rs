@@ -13,9 +13,13 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::{Address, U256};
+
 error_chain! {
     foreign_links {
         Timer(tokio_timer::Error);
+        Io(std::io::Error);
+        Json(serde_json::Error);
     }
 
     errors {
@@ -35,6 +39,23 @@ error_chain! {
             display("Not supported token")
         }
 
+        /// fee of request does not cover its gas cost
+        FeeTooLow(hash: String, fee: U256, min_fee: U256) {
+            description("request fee is lower than required minimum fee"),
+            display("[{}] fee {} is lower than required minimum fee {}", hash, fee, min_fee)
+        }
+
+        /// gas cost of request is too large to be paid in token units
+        MinimumFeeOverflow(hash: String) {
+            description("minimum fee of request overflows"),
+            display("[{}] minimum fee overflows", hash)
+        }
+
+        TokenPriceUnavailable(token: Address) {
+            description("Token price is unavailable")
+            display("Price of token {:?} is unavailable", token)
+        }
+
         AlreadyImported(hash: String) {
             description("request is already in the pool"),
             display("[{}] already imported", hash)
@@ -44,6 +65,16 @@ error_chain! {
             description("the pool is full and request is too cheap to replace any request"),
             display("[{}] too cheap to enter the pool. Min score: {}", hash, min_score)
         }
+        /// memory usage limit of the pool is reached and request is not better than any request
+        MemoryLimitReached(hash: String) {
+            description("memory usage limit of the pool is reached"),
+            display("[{}] pool memory limit is reached, request is worse than all requests", hash)
+        }
+        /// request alone exceeds memory usage limit of the pool
+        TooLargeToEnter(hash: String, mem_usage: usize, max_mem_usage: usize) {
+            description("request exceeds memory usage limit of the pool"),
+            display("[{}] memory usage {} exceeds pool limit {}", hash, mem_usage, max_mem_usage)
+        }
         /// Request is too cheap to replace existing request that occupies the same slot.
         TooCheapToReplace(old_hash: String, hash: String) {
             description("request is too cheap to replace existing request in the pool"),
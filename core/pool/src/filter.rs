@@ -39,15 +39,19 @@ impl AddressFilter for DummyAddressFilter {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum ListAddressFilterMode {
+    #[serde(rename = "blacklist")]
     Blacklist,
+    #[serde(rename = "whitelist")]
     Whitelist,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Serialize)]
 pub struct ListAddressFilter {
+    #[serde(rename = "tokens")]
     list: HashSet<Address>,
+    #[serde(rename = "mode")]
     mode: ListAddressFilterMode,
 }
 
@@ -78,6 +82,22 @@ impl ListAddressFilter {
     pub fn tokens(&self) -> &HashSet<Address> {
         &self.list
     }
+
+    /// Allows a token in either mode
+    pub fn allow_token(&mut self, token: Address) {
+        match self.mode {
+            ListAddressFilterMode::Whitelist => self.add_token(token),
+            ListAddressFilterMode::Blacklist => self.remove_token(&token),
+        }
+    }
+
+    /// Denies a token in either mode
+    pub fn deny_token(&mut self, token: Address) {
+        match self.mode {
+            ListAddressFilterMode::Whitelist => self.remove_token(&token),
+            ListAddressFilterMode::Blacklist => self.add_token(token),
+        }
+    }
 }
 
 impl Default for ListAddressFilter {
@@ -17,14 +17,24 @@ use ethereum_types::{Address, H256, U256};
 use std::collections::{hash_map, BTreeSet, HashMap, HashSet};
 use std::slice;
 use std::sync::Arc;
+use std::time::Instant;
 
-use types::SignedRequest;
+use futures::sync::mpsc;
+
+use types::{RequestReceipt, RequestState, RequestStatus, SignedRequest};
 
 use super::{
-    AddResult, Error, ErrorKind, PoolParams, PoolRequest, PoolRequestTag, Readiness, ReadyChecker,
-    RequestQueue, RequestSelector, ScoredRequest, Status,
+    AddResult, Choice, Error, ErrorKind, Journal, PoolParams, PoolRequest, PoolRequestTag,
+    Readiness, ReadyChecker, Receipts, RequestQueue, RequestSelector, ScoredRequest, Status,
+    Watchers,
 };
 
+/// Reason recorded for requests evicted for staying in the pool too long
+const EXPIRED_REASON: &str = "expired";
+
+/// Reason recorded for requests evicted to keep memory usage of the pool under its limit
+const EVICTED_REASON: &str = "evicted";
+
 pub struct InnerPool<R, S>
 where
     R: PoolRequest,
@@ -34,10 +44,19 @@ where
     queues: HashMap<Address, RequestQueue<R, S>>,
     by_hash: HashMap<H256, R>,
     tags: HashMap<H256, PoolRequestTag>,
+    // reasons of requests which are marked as invalid
+    invalid_reasons: HashMap<H256, String>,
+    // time when requests are inserted into the pool
+    inserted_at: HashMap<H256, Instant>,
     best_requests: BTreeSet<ScoredRequest<S::Score, R>>,
     worst_requests: BTreeSet<ScoredRequest<S::Score, R>>,
     selector: Arc<S>,
     aggregate_gas: U256,
+    // approximate memory usage of requests in the pool
+    mem_usage: usize,
+    journal: Option<Journal>,
+    watchers: Watchers,
+    receipts: Receipts,
 }
 
 impl<R, S> InnerPool<R, S>
@@ -46,27 +65,78 @@ where
     S: RequestSelector<R>,
 {
     pub fn new(params: PoolParams, selector: Arc<S>) -> InnerPool<R, S> {
+        let receipts = Receipts::new(params.max_receipts);
         InnerPool {
             params,
             queues: Default::default(),
             by_hash: Default::default(),
             tags: Default::default(),
+            invalid_reasons: Default::default(),
+            inserted_at: Default::default(),
             best_requests: Default::default(),
             worst_requests: Default::default(),
             selector,
             aggregate_gas: U256::zero(),
+            mem_usage: 0,
+            journal: None,
+            watchers: Watchers::default(),
+            receipts,
         }
     }
 
+    #[inline]
+    pub fn set_journal(&mut self, journal: Journal) {
+        self.journal = Some(journal);
+    }
+
     pub fn contains_hash(&self, hash: &H256) -> bool {
         self.by_hash.contains_key(hash)
     }
 
+    /// Records a request which is not in the pool as removed in journal, so that it will not be
+    /// restored again
+    pub fn forget(&mut self, hash: &H256) {
+        if self.by_hash.contains_key(hash) {
+            return;
+        }
+
+        if let Some(ref mut journal) = self.journal {
+            journal.record_remove(&[*hash]);
+        }
+    }
+
     pub fn import(&mut self, request: R) -> Result<Arc<SignedRequest>, Error> {
         ensure!(
             !self.by_hash.contains_key(&request.hash()),
             ErrorKind::AlreadyImported(format!("{:?}", request.hash()))
         );
+        ensure!(
+            request.mem_usage() <= self.params.max_mem_usage,
+            ErrorKind::TooLargeToEnter(
+                format!("{:x}", request.hash()),
+                request.mem_usage(),
+                self.params.max_mem_usage,
+            )
+        );
+
+        // decide whether the request is admitted before evicting anything for it, a request it
+        // replaces or pushes out of its sender's queue frees memory as well
+        loop {
+            let removed = {
+                let result = match self.queues.get(request.sender()) {
+                    Some(queue) => {
+                        queue.check(&request, &self.selector, self.params.max_per_sender)
+                    }
+                    None => AddResult::Ok(request.clone()),
+                };
+                Self::admitted(result)?
+            };
+            let freed = removed.as_ref().map_or(0, |old| old.mem_usage());
+            if self.mem_usage + request.mem_usage() <= self.params.max_mem_usage + freed {
+                break;
+            }
+            self.remove_worst(&request, removed.as_ref())?;
+        }
 
         let (result, prev_state, current_state) = {
             let queue = self
@@ -81,15 +151,17 @@ where
 
         self.update_senders_worst_and_best(prev_state, current_state);
 
+        let old = Self::admitted(result)?;
+        self.finalize_insert(&request, old.as_ref());
+        Ok(request.clone_signed())
+    }
+
+    /// Returns the request removed from a sender's queue by adding a request, fails if the
+    /// request is rejected.
+    fn admitted(result: AddResult<R, S::Score>) -> Result<Option<R>, Error> {
         match result {
-            AddResult::Ok(req) => {
-                self.finalize_insert(&req, None);
-                Ok(req.clone_signed())
-            }
-            AddResult::PushedOut { new, old } | AddResult::Replaced { new, old } => {
-                self.finalize_insert(&new, Some(&old));
-                Ok(new.clone_signed())
-            }
+            AddResult::Ok(_) => Ok(None),
+            AddResult::PushedOut { old, .. } | AddResult::Replaced { old, .. } => Ok(Some(old)),
             AddResult::TooCheap { new, old } => {
                 let error = ErrorKind::TooCheapToReplace(
                     format!("{:x}", old.hash()),
@@ -107,6 +179,38 @@ where
         }
     }
 
+    /// Removes the worst request which is not being processed to make room for `request`, fails
+    /// if `request` is not better than it. The request that `request` is going to replace is kept.
+    fn remove_worst(&mut self, request: &R, replaced: Option<&R>) -> Result<(), Error> {
+        let worst = {
+            let tags = &self.tags;
+            let replaced = replaced.map(|old| *old.hash());
+            self.worst_requests
+                .iter()
+                .rev()
+                .find(|worst| {
+                    let hash = worst.request().hash();
+                    tags.get(hash) != Some(&PoolRequestTag::Processing) && Some(*hash) != replaced
+                })
+                .cloned()
+        };
+
+        let worst = match worst {
+            Some(worst) => match self.selector.should_replace(worst.request(), request) {
+                Choice::ReplaceOld => worst,
+                Choice::InsertNew | Choice::RejectNew => {
+                    bail!(ErrorKind::MemoryLimitReached(format!("{:x}", request.hash())))
+                }
+            },
+            None => bail!(ErrorKind::MemoryLimitReached(format!("{:x}", request.hash()))),
+        };
+
+        debug!(target: "pool", "Evicting request {:?} to make room for request {:?}",
+            worst.request().hash(), request.hash());
+        self.remove_with_reason(worst.request().hash(), Some(EVICTED_REASON.to_owned()));
+        Ok(())
+    }
+
     /// Updates best and worst request from a sender.
     fn update_senders_worst_and_best(
         &mut self,
@@ -169,18 +273,114 @@ where
 
     #[inline]
     pub fn mark_by_hash(&mut self, hash: &H256, tag: PoolRequestTag) {
-        self.tags.get_mut(hash).map(|v| *v = tag);
+        self.mark_by_hashes(&[*hash], tag);
     }
 
     #[inline]
     pub fn mark_by_hashes(&mut self, hashes: &[H256], tag: PoolRequestTag) {
         hashes.iter().for_each(|hash| {
-            self.tags.get_mut(hash).map(|v| *v = tag);
+            if let Some(v) = self.tags.get_mut(hash) {
+                *v = tag;
+            } else {
+                return;
+            }
+            if tag != PoolRequestTag::Invalid {
+                self.invalid_reasons.remove(hash);
+            }
+            let reason = self.invalid_reasons.get(hash).cloned();
+            self.receipts.update_state(hash, Self::request_state(tag), reason);
+            let status = self.request_status(hash, tag);
+            self.watchers.notify(hash, status);
         });
+
+        if let Some(ref mut journal) = self.journal {
+            journal.record_tag(hashes, tag);
+        }
+    }
+
+    pub fn mark_invalid(&mut self, hash: &H256, reason: String) {
+        if !self.tags.contains_key(hash) {
+            return;
+        }
+
+        self.invalid_reasons.insert(*hash, reason);
+        self.mark_by_hash(hash, PoolRequestTag::Invalid);
+    }
+
+    /// Returns the reason why a request is invalid, the reason of a removed invalid request is
+    /// kept in its receipt.
+    pub fn invalid_reason(&self, hash: &H256) -> Option<&String> {
+        self.invalid_reasons.get(hash).or_else(|| {
+            self.receipts.get(hash).and_then(|receipt| match receipt.state {
+                RequestState::Invalid => receipt.reason.as_ref(),
+                _ => None,
+            })
+        })
+    }
+
+    /// Records that requests are broadcasted by relayer in a collation transaction
+    pub fn mark_broadcasted(&mut self, hashes: &[H256], relayer: &Address, tx_hash: &H256) {
+        for hash in hashes {
+            if !self.tags.contains_key(hash) {
+                continue;
+            }
+            self.receipts.update_transaction(hash, relayer, tx_hash);
+            self.watchers.notify(
+                hash,
+                RequestStatus::Broadcasted {
+                    transaction_hash: *tx_hash,
+                },
+            );
+        }
+    }
+
+    /// Records the number of block which contains the collation transaction of requests
+    pub fn mark_mined(&mut self, hashes: &[H256], block_number: &U256) {
+        hashes.iter().for_each(|hash| self.receipts.update_block_number(hash, block_number));
+    }
+
+    #[inline]
+    pub fn receipt(&self, hash: &H256) -> Option<&RequestReceipt> {
+        self.receipts.get(hash)
+    }
+
+    /// Returns a stream of status changes of request, returns `None` if request is not in pool
+    pub fn watch(&mut self, hash: &H256) -> Option<mpsc::UnboundedReceiver<RequestStatus>> {
+        let tag = *self.tags.get(hash)?;
+        let status = self.request_status(hash, tag);
+        Some(self.watchers.add(hash, status))
+    }
+
+    fn request_state(tag: PoolRequestTag) -> RequestState {
+        match tag {
+            PoolRequestTag::Ready => RequestState::Ready,
+            PoolRequestTag::Processing => RequestState::Processing,
+            PoolRequestTag::Executed => RequestState::Executed,
+            PoolRequestTag::Invalid => RequestState::Invalid,
+        }
+    }
+
+    fn request_status(&self, hash: &H256, tag: PoolRequestTag) -> RequestStatus {
+        match tag {
+            PoolRequestTag::Ready => RequestStatus::Ready,
+            PoolRequestTag::Processing => RequestStatus::Processing,
+            PoolRequestTag::Executed => RequestStatus::Executed,
+            PoolRequestTag::Invalid => RequestStatus::Invalid {
+                reason: self.invalid_reasons.get(hash).cloned(),
+            },
+        }
     }
 
     pub fn remove(&mut self, hash: &H256) -> Option<Arc<SignedRequest>> {
-        if let Some(req) = self.finalize_remove(hash) {
+        self.remove_with_reason(hash, None)
+    }
+
+    fn remove_with_reason(
+        &mut self,
+        hash: &H256,
+        reason: Option<String>,
+    ) -> Option<Arc<SignedRequest>> {
+        if let Some(req) = self.finalize_remove(hash, reason) {
             self.remove_from_set(req.sender(), |set, selector| set.remove(&req, &selector));
             Some(req.clone_signed())
         } else {
@@ -219,7 +419,7 @@ where
             Some(removed) => {
                 let len = removed.len();
                 removed.iter().for_each(|req| {
-                    self.finalize_remove(req.hash());
+                    self.finalize_remove(req.hash(), None);
                 });
                 len
             }
@@ -250,8 +450,34 @@ where
         self.queues.retain(|_, queue| !queue.is_empty());
     }
 
+    /// Removes requests which stay in the pool longer than `max_age` of pool parameters,
+    /// requests being processed are kept. Returns count of removed requests.
+    pub fn remove_expired(&mut self) -> usize {
+        let max_age = match self.params.max_age {
+            Some(max_age) => max_age,
+            None => return 0,
+        };
+
+        let now = Instant::now();
+        let expired: Vec<H256> = {
+            let tags = &self.tags;
+            self.inserted_at
+                .iter()
+                .filter(|&(hash, inserted_at)| {
+                    tags.get(hash) != Some(&PoolRequestTag::Processing)
+                        && now.duration_since(*inserted_at) > max_age
+                })
+                .map(|(hash, _)| *hash)
+                .collect()
+        };
+
+        expired
+            .iter()
+            .filter_map(|hash| self.remove_with_reason(hash, Some(EXPIRED_REASON.to_owned())))
+            .count()
+    }
+
     /// Removes all stalled requests from given sender list (or from all senders).
-    #[allow(unused)]
     pub fn cull<Ready: ReadyChecker<R>>(
         &mut self,
         senders: Option<&[Address]>,
@@ -277,20 +503,36 @@ where
 
     fn finalize_insert(&mut self, request: &R, old: Option<&R>) {
         self.aggregate_gas += *request.gas_amount();
+        self.mem_usage += request.mem_usage();
         self.by_hash.insert(request.hash().clone(), request.clone());
         self.tags
             .insert(request.hash().clone(), PoolRequestTag::Ready);
+        self.inserted_at.insert(*request.hash(), Instant::now());
+
+        self.receipts.insert(request.hash(), request.relayer());
+
+        if let Some(ref mut journal) = self.journal {
+            journal.record_import(request.signed());
+        }
 
         if let Some(old) = old {
-            self.finalize_remove(&old.hash());
+            self.finalize_remove(&old.hash(), None);
         }
     }
 
-    fn finalize_remove(&mut self, hash: &H256) -> Option<R> {
+    fn finalize_remove(&mut self, hash: &H256, reason: Option<String>) -> Option<R> {
         self.tags.remove(hash);
+        self.invalid_reasons.remove(hash);
+        self.inserted_at.remove(hash);
+        self.watchers.remove(hash);
         match self.by_hash.remove(hash) {
             Some(old) => {
                 self.aggregate_gas -= *old.gas_amount();
+                self.mem_usage -= old.mem_usage();
+                self.receipts.finish(hash, reason);
+                if let Some(ref mut journal) = self.journal {
+                    journal.record_remove(&[*hash]);
+                }
                 Some(old)
             }
             None => None,
@@ -371,25 +613,49 @@ where
     }
 
     pub fn clear(&mut self) {
+        let hashes: Vec<_> = self.by_hash.keys().cloned().collect();
+        hashes.iter().for_each(|hash| self.receipts.finish(hash, None));
+
+        if let Some(ref mut journal) = self.journal {
+            journal.record_remove(&hashes);
+        }
+
         self.queues.clear();
         self.tags.clear();
+        self.invalid_reasons.clear();
+        self.inserted_at.clear();
+        self.mem_usage = 0;
+        self.watchers.clear();
         self.by_hash.clear();
         self.best_requests.clear();
         self.worst_requests.clear();
         self.aggregate_gas = U256::zero();
     }
 
-    pub fn status(&self) -> HashMap<Address, Status> {
-        // TODO
-        unimplemented!();
+    /// Counts requests tagged `Ready` per token, requests which are not ready by `ready` yet
+    /// are counted as future.
+    pub fn status<Ready: ReadyChecker<R>>(&self, mut ready: Ready) -> HashMap<Address, Status> {
+        let mut status = HashMap::new();
+        for req in self.queues.values().flat_map(|queue| queue.iter()) {
+            let token_status = status.entry(*req.token()).or_insert_with(Status::zero);
+            if self.tags.get(req.hash()) != Some(&PoolRequestTag::Ready) {
+                continue;
+            }
 
-        // let ready = |_req: &R| Readiness::Ready;
-        // self.unordered_pending(ready).fold()
+            match ready.is_ready(req) {
+                Readiness::Ready => token_status.ready += 1,
+                Readiness::Future => token_status.future += 1,
+                // stale requests are culled from pool
+                Readiness::Stale => {}
+            }
+        }
+        status
     }
 
-    pub fn token_status(&self, _address: &Address) -> Status {
-        // TODO
-        unimplemented!();
+    pub fn token_status<Ready: ReadyChecker<R>>(&self, address: &Address, ready: Ready) -> Status {
+        self.status(ready)
+            .remove(address)
+            .unwrap_or_else(Status::zero)
     }
 }
 
@@ -470,18 +736,19 @@ where
                     .expect("Just taken from iterator; qed")
             };
 
+            // retrieve next one from that sender, nonces are counted per token so a request
+            // which is not ready does not block requests of other tokens.
+            let next = self
+                .pool
+                .queues
+                .get(best.request().sender())
+                .and_then(|s| s.find_next(&best.request(), &self.pool.selector));
+            if let Some((score, tx)) = next {
+                self.best_requests.insert(ScoredRequest::new(score, tx));
+            }
+
             match self.ready.is_ready(&best.request()) {
                 Readiness::Ready => {
-                    // retrieve next one from that sender.
-                    let next = self
-                        .pool
-                        .queues
-                        .get(best.request().sender())
-                        .and_then(|s| s.find_next(&best.request(), &self.pool.selector));
-                    if let Some((score, tx)) = next {
-                        self.best_requests.insert(ScoredRequest::new(score, tx));
-                    }
-
                     return Some(best.request().clone());
                 }
                 state => trace!(target: "pool",
@@ -0,0 +1,242 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::H256;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use traits::PoolRequestTag;
+use types::{RelayerRpcRequest, SignedRequest};
+
+use super::Error;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+enum JournalTag {
+    #[serde(rename = "invalid")]
+    Invalid,
+    #[serde(rename = "ready")]
+    Ready,
+    #[serde(rename = "processing")]
+    Processing,
+    #[serde(rename = "executed")]
+    Executed,
+}
+
+impl From<PoolRequestTag> for JournalTag {
+    fn from(tag: PoolRequestTag) -> JournalTag {
+        match tag {
+            PoolRequestTag::Invalid => JournalTag::Invalid,
+            PoolRequestTag::Ready => JournalTag::Ready,
+            PoolRequestTag::Processing => JournalTag::Processing,
+            PoolRequestTag::Executed => JournalTag::Executed,
+        }
+    }
+}
+
+impl Into<PoolRequestTag> for JournalTag {
+    fn into(self) -> PoolRequestTag {
+        match self {
+            JournalTag::Invalid => PoolRequestTag::Invalid,
+            JournalTag::Ready => PoolRequestTag::Ready,
+            JournalTag::Processing => PoolRequestTag::Processing,
+            JournalTag::Executed => PoolRequestTag::Executed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum JournalEntry {
+    #[serde(rename = "import")]
+    Import { request: RelayerRpcRequest },
+
+    #[serde(rename = "tag")]
+    Tag { hashes: Vec<H256>, tag: JournalTag },
+
+    #[serde(rename = "remove")]
+    Remove { hashes: Vec<H256> },
+}
+
+/// A request restored from journal
+#[derive(Debug, Clone)]
+pub struct JournalRecord {
+    pub request: SignedRequest,
+    pub tag: PoolRequestTag,
+}
+
+/// Append-only journal of pool changes, one JSON entry per line
+pub struct Journal {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl Journal {
+    /// Opens a journal file, returns the journal and all requests still alive in it.
+    /// The journal file is compacted so that it only contains the alive requests.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<(Journal, Vec<JournalRecord>), Error> {
+        let path = path.as_ref().to_path_buf();
+        let records = if path.exists() {
+            Self::replay(&path)?
+        } else {
+            Vec::new()
+        };
+
+        // compact journal, write alive requests into a temporary file and then replace the
+        // original one
+        {
+            let tmp_path = path.with_extension("tmp");
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            for record in records.iter() {
+                Self::write_entry(
+                    &mut writer,
+                    &JournalEntry::Import {
+                        request: RelayerRpcRequest::from(&record.request),
+                    },
+                )?;
+                Self::write_entry(
+                    &mut writer,
+                    &JournalEntry::Tag {
+                        hashes: vec![*record.request.hash()],
+                        tag: JournalTag::from(record.tag),
+                    },
+                )?;
+            }
+            writer.flush()?;
+            fs::rename(&tmp_path, &path)?;
+        }
+
+        let file = OpenOptions::new().append(true).create(true).open(&path)?;
+        info!(target: "pool",
+            "Open request journal {:?}, {} request(s) found", path, records.len());
+
+        Ok((
+            Journal {
+                path,
+                writer: BufWriter::new(file),
+            },
+            records,
+        ))
+    }
+
+    fn replay(path: &PathBuf) -> Result<Vec<JournalRecord>, Error> {
+        let mut order: Vec<H256> = Vec::new();
+        let mut records: HashMap<H256, JournalRecord> = HashMap::new();
+
+        for (line_number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // a broken line is usually caused by crashing while writing, just skip it
+            let entry: JournalEntry = match serde_json::from_str(&line) {
+                Ok(entry) => entry,
+                Err(err) => {
+                    warn!(target: "pool",
+                        "Skip broken entry at line {} of request journal {:?}: {}",
+                        line_number + 1, path, err);
+                    continue;
+                }
+            };
+
+            match entry {
+                JournalEntry::Import { request } => match request.into_signed_request() {
+                    Ok(request) => {
+                        let hash = *request.hash();
+                        if !records.contains_key(&hash) {
+                            order.push(hash);
+                        }
+                        records.insert(
+                            hash,
+                            JournalRecord {
+                                request,
+                                tag: PoolRequestTag::Ready,
+                            },
+                        );
+                    }
+                    Err(err) => {
+                        warn!(target: "pool",
+                            "Skip invalid request at line {} of request journal {:?}: {:?}",
+                            line_number + 1, path, err);
+                    }
+                },
+                JournalEntry::Tag { hashes, tag } => {
+                    for hash in hashes.iter() {
+                        if let Some(record) = records.get_mut(hash) {
+                            record.tag = tag.into();
+                        }
+                    }
+                }
+                JournalEntry::Remove { hashes } => {
+                    for hash in hashes.iter() {
+                        records.remove(hash);
+                    }
+                }
+            }
+        }
+
+        Ok(order
+            .into_iter()
+            .filter_map(|hash| records.remove(&hash))
+            .collect())
+    }
+
+    fn write_entry<W: Write>(writer: &mut W, entry: &JournalEntry) -> Result<(), Error> {
+        serde_json::to_writer(&mut *writer, entry)?;
+        writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn append(&mut self, entry: JournalEntry) {
+        let result = Self::write_entry(&mut self.writer, &entry).and_then(|_| {
+            self.writer.flush()?;
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            warn!(target: "pool",
+                "Failed to write request journal {:?}, error: {}", self.path, err);
+        }
+    }
+
+    pub fn record_import(&mut self, request: &SignedRequest) {
+        self.append(JournalEntry::Import {
+            request: RelayerRpcRequest::from(request),
+        });
+    }
+
+    pub fn record_tag(&mut self, hashes: &[H256], tag: PoolRequestTag) {
+        if hashes.is_empty() {
+            return;
+        }
+
+        self.append(JournalEntry::Tag {
+            hashes: hashes.to_vec(),
+            tag: JournalTag::from(tag),
+        });
+    }
+
+    pub fn record_remove(&mut self, hashes: &[H256]) {
+        if hashes.is_empty() {
+            return;
+        }
+
+        self.append(JournalEntry::Remove {
+            hashes: hashes.to_vec(),
+        });
+    }
+}
@@ -22,6 +22,11 @@ extern crate log;
 #[macro_use]
 extern crate lazy_static;
 
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
 extern crate ethereum_types;
 extern crate futures;
 extern crate parking_lot;
@@ -29,12 +34,16 @@ extern crate rustc_hex;
 extern crate smallvec;
 extern crate tokio_timer;
 
+extern crate pricer;
 extern crate traits;
 extern crate types;
 
+#[cfg(test)]
+extern crate ethkey;
+
 use ethereum_types::{Address, H256, U256};
 use std::sync::Arc;
-use std::{cmp, fmt};
+use std::{cmp, fmt, mem};
 
 use traits::PoolRequestTag;
 use types::{DelegateMode, SignedRequest, UnverifiedRequest};
@@ -42,13 +51,16 @@ use types::{DelegateMode, SignedRequest, UnverifiedRequest};
 mod error;
 mod filter;
 mod inner;
+mod journal;
 mod params;
 mod queue;
 mod ready;
+mod receipt;
 mod selector;
 mod service;
 mod status;
 mod verifier;
+mod watcher;
 
 #[cfg(test)]
 mod tests;
@@ -59,14 +71,18 @@ pub use self::error::{Error, ErrorKind};
 pub use self::filter::{
     AddressFilter, DummyAddressFilter, ListAddressFilter, ListAddressFilterMode,
 };
+pub use self::journal::{Journal as PoolJournal, JournalRecord as PoolJournalRecord};
 pub use self::params::Params as PoolParams;
-pub use self::ready::{Readiness, ReadyChecker};
+pub use self::ready::{NonceCache, NonceReadyChecker, Readiness, ReadyChecker};
 pub use self::selector::{NonceAndFeeSelector, RequestSelector, TokenSelector};
 pub use self::status::Status;
-pub use self::verifier::{RequestVerifier, Verifier};
+pub use self::verifier::{minimum_fee, minus_intrinsic_gas_amount, RequestVerifier, Verifier};
 
 use self::inner::InnerPool;
+use self::journal::{Journal, JournalRecord};
 use self::queue::{AddResult, RequestQueue};
+use self::receipt::Receipts;
+use self::watcher::Watchers;
 
 pub trait PoolRequest: fmt::Debug + Clone {
     fn clone_signed(&self) -> Arc<SignedRequest>;
@@ -92,6 +108,8 @@ pub trait PoolRequest: fmt::Debug + Clone {
     fn update_gas_amount(&mut self, gas_amount: U256);
 
     fn delegate_mode(&self) -> DelegateMode;
+
+    fn mem_usage(&self) -> usize;
 }
 
 #[derive(Debug, Clone)]
@@ -188,6 +206,11 @@ impl PoolRequest for VerifiedRequest {
     fn update_gas_amount(&mut self, gas_amount: U256) {
         self.gas_amount = gas_amount;
     }
+
+    #[inline]
+    fn mem_usage(&self) -> usize {
+        mem::size_of::<SignedRequest>() + self.unverified().as_unsigned().data.capacity()
+    }
 }
 
 #[derive(Debug)]
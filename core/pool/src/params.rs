@@ -13,10 +13,16 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use std::time::Duration;
+
 pub struct Params {
     pub max_count: usize,
     pub max_per_sender: usize,
     pub max_mem_usage: usize,
+    // count of receipts kept after requests leave the pool
+    pub max_receipts: usize,
+    // requests staying in the pool longer than it are evicted, never evicted if none
+    pub max_age: Option<Duration>,
 }
 
 impl Default for Params {
@@ -25,6 +31,8 @@ impl Default for Params {
             max_count: 10240,
             max_per_sender: 16,
             max_mem_usage: 8 * 1024 * 1024,
+            max_receipts: 10240,
+            max_age: None,
         }
     }
 }
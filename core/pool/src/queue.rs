@@ -16,7 +16,6 @@
 use ethereum_types::Address;
 use smallvec::SmallVec;
 use std::collections::HashSet;
-use std::mem;
 
 use super::{Change, Choice, PoolRequest, Readiness, ReadyChecker, RequestSelector};
 
@@ -85,68 +84,72 @@ where
         })
     }
 
-    fn push_cheapest(
-        &mut self,
-        request: R,
-        selector: &S,
-        max_count: usize,
-    ) -> AddResult<R, S::Score> {
-        let index = self.requests.len();
-        if index == max_count && !selector.should_ignore_sender_limit(&request) {
-            let min_score = self.scores[index - 1].clone();
-            AddResult::TooCheapToEnter(request, min_score)
-        } else {
-            self.requests.push(request.clone());
-            self.scores.push(Default::default());
-
-            selector.update_scores(&self.requests, &mut self.scores, Change::InsertedAt(index));
-            AddResult::Ok(request)
+    /// Returns what `add` would do with `request` without changing the queue.
+    pub fn check(&self, request: &R, selector: &S, max_count: usize) -> AddResult<R, S::Score> {
+        let index = self.position(request, selector);
+        let len = self.requests.len();
+        if index == len {
+            return if len == max_count && !selector.should_ignore_sender_limit(request) {
+                AddResult::TooCheapToEnter(request.clone(), self.scores[len - 1].clone())
+            } else {
+                AddResult::Ok(request.clone())
+            };
         }
-    }
-
-    pub fn add(&mut self, request: R, selector: &S, max_count: usize) -> AddResult<R, S::Score> {
-        let index = match self
-            .requests
-            .binary_search_by(|old| selector.compare(old, &request))
-        {
-            Ok(index) => index,
-            Err(index) => index,
-        };
 
-        if index == self.requests.len() {
-            return self.push_cheapest(request, selector, max_count);
+        let old = self.requests[index].clone();
+        match selector.choose(&old, request) {
+            Choice::InsertNew if len >= max_count => AddResult::PushedOut {
+                old: self.requests[len - 1].clone(),
+                new: request.clone(),
+            },
+            Choice::InsertNew => AddResult::Ok(request.clone()),
+            Choice::RejectNew => AddResult::TooCheap {
+                old,
+                new: request.clone(),
+            },
+            Choice::ReplaceOld => AddResult::Replaced {
+                old,
+                new: request.clone(),
+            },
         }
+    }
 
-        match selector.choose(&self.requests[index], &request) {
-            Choice::InsertNew => {
-                self.requests.insert(index, request.clone());
+    pub fn add(&mut self, request: R, selector: &S, max_count: usize) -> AddResult<R, S::Score> {
+        let index = self.position(&request, selector);
+        let result = self.check(&request, selector, max_count);
+        match result {
+            AddResult::Ok(_) | AddResult::PushedOut { .. } => {
+                self.requests.insert(index, request);
                 self.scores.insert(index, Default::default());
                 selector.update_scores(&self.requests, &mut self.scores, Change::InsertedAt(index));
 
-                if self.requests.len() > max_count {
-                    let old = self.requests.pop().expect("len is non-zero; qed");
+                if let AddResult::PushedOut { .. } = result {
+                    self.requests.pop();
                     self.scores.pop();
                     selector.update_scores(
                         &self.requests,
                         &mut self.scores,
                         Change::RemovedAt(self.requests.len()),
                     );
-
-                    AddResult::PushedOut { old, new: request }
-                } else {
-                    AddResult::Ok(request)
                 }
             }
-            Choice::RejectNew => AddResult::TooCheap {
-                old: self.requests[index].clone(),
-                new: request,
-            },
-            Choice::ReplaceOld => {
-                let old = mem::replace(&mut self.requests[index], request.clone());
+            AddResult::Replaced { .. } => {
+                self.requests[index] = request;
                 selector.update_scores(&self.requests, &mut self.scores, Change::ReplacedAt(index));
-
-                AddResult::Replaced { old, new: request }
             }
+            AddResult::TooCheap { .. } | AddResult::TooCheapToEnter(..) => {}
+        }
+
+        result
+    }
+
+    fn position(&self, request: &R, selector: &S) -> usize {
+        match self
+            .requests
+            .binary_search_by(|old| selector.compare(old, request))
+        {
+            Ok(index) => index,
+            Err(index) => index,
         }
     }
 
@@ -179,36 +182,23 @@ where
             return result;
         }
 
-        let mut first_non_stalled = 0;
-        for req in &self.requests {
-            match ready.is_ready(req) {
+        // requests are ordered by token priority first, a stalled request is not necessarily
+        // at the front of the queue.
+        let mut index = 0;
+        while index < self.requests.len() {
+            match ready.is_ready(&self.requests[index]) {
                 Readiness::Stale => {
-                    first_non_stalled += 1;
+                    self.scores.remove(index);
+                    result.push(self.requests.remove(index));
                 }
-                Readiness::Ready | Readiness::Future => break,
+                Readiness::Ready | Readiness::Future => index += 1,
             }
         }
 
-        if first_non_stalled == 0 {
+        if result.is_empty() {
             return result;
         }
 
-        // reverse the vectors to easily remove first elements.
-        self.requests.reverse();
-        self.scores.reverse();
-
-        for _ in 0..first_non_stalled {
-            self.scores.pop();
-            result.push(
-                self.requests
-                    .pop()
-                    .expect("first_non_stalled is never greater than requests.len(); qed"),
-            );
-        }
-
-        self.requests.reverse();
-        self.scores.reverse();
-
         // update scoring
         selector.update_scores(
             &self.requests,
@@ -216,8 +206,6 @@ where
             Change::Culled(result.len()),
         );
 
-        // reverse the result to maintain correct order.
-        result.reverse();
         result
     }
 
@@ -13,6 +13,12 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::{Address, U256};
+use std::cmp;
+use std::collections::HashMap;
+
+use super::PoolRequest;
+
 #[derive(Debug)]
 pub enum Readiness {
     Stale,
@@ -32,3 +38,74 @@ where
         (*self)(request)
     }
 }
+
+/// On-chain nonces of senders, keyed by `(sender, token)`, loaded at a particular block.
+#[derive(Debug, Default)]
+pub struct NonceCache {
+    block_number: Option<U256>,
+    nonces: HashMap<(Address, Address), U256>,
+}
+
+impl NonceCache {
+    pub fn new() -> NonceCache {
+        NonceCache::default()
+    }
+
+    #[inline]
+    pub fn block_number(&self) -> Option<&U256> {
+        self.block_number.as_ref()
+    }
+
+    #[inline]
+    pub fn contains(&self, sender: &Address, token: &Address) -> bool {
+        self.nonces.contains_key(&(*sender, *token))
+    }
+
+    /// Stores nonces loaded at `block_number`, nonces loaded at an older block are discarded.
+    pub fn update(&mut self, block_number: U256, nonces: Vec<((Address, Address), U256)>) {
+        if self.block_number != Some(block_number) {
+            self.block_number = Some(block_number);
+            self.nonces.clear();
+        }
+        self.nonces.extend(nonces);
+    }
+
+    /// Returns a `ReadyChecker` based on the cached nonces.
+    pub fn checker(&self) -> NonceReadyChecker {
+        NonceReadyChecker::new(self.nonces.clone())
+    }
+}
+
+/// Checks readiness of requests by comparing their nonces with on-chain nonces.
+///
+/// Requests of a sender and a token have to be checked in nonce order, a request which
+/// is ready advances the expected nonce of its sender and token.
+#[derive(Debug)]
+pub struct NonceReadyChecker {
+    nonces: HashMap<(Address, Address), U256>,
+}
+
+impl NonceReadyChecker {
+    pub fn new(nonces: HashMap<(Address, Address), U256>) -> NonceReadyChecker {
+        NonceReadyChecker { nonces }
+    }
+}
+
+impl<R: PoolRequest> ReadyChecker<R> for NonceReadyChecker {
+    fn is_ready(&mut self, request: &R) -> Readiness {
+        let nonce = match self.nonces.get_mut(&(*request.sender(), *request.token())) {
+            Some(nonce) => nonce,
+            // on-chain nonce is not loaded yet
+            None => return Readiness::Future,
+        };
+
+        match request.nonce().cmp(nonce) {
+            cmp::Ordering::Less => Readiness::Stale,
+            cmp::Ordering::Greater => Readiness::Future,
+            cmp::Ordering::Equal => {
+                *nonce = *nonce + U256::from(1);
+                Readiness::Ready
+            }
+        }
+    }
+}
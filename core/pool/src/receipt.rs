@@ -0,0 +1,106 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::{Address, H256, U256};
+use std::collections::{HashMap, VecDeque};
+
+use types::{RequestReceipt, RequestState};
+
+/// Receipts of requests, receipts of requests which left the pool are kept up to `capacity`
+pub struct Receipts {
+    receipts: HashMap<H256, RequestReceipt>,
+    // hashes of requests which left the pool, oldest first
+    finished: VecDeque<H256>,
+    capacity: usize,
+}
+
+impl Receipts {
+    pub fn new(capacity: usize) -> Receipts {
+        Receipts {
+            receipts: Default::default(),
+            finished: Default::default(),
+            capacity,
+        }
+    }
+
+    pub fn insert(&mut self, hash: &H256, relayer: &Address) {
+        // a dropped request may be imported again
+        if self.receipts.contains_key(hash) {
+            self.finished.retain(|finished| finished != hash);
+        }
+
+        self.receipts.insert(
+            *hash,
+            RequestReceipt {
+                hash: *hash,
+                state: RequestState::Ready,
+                reason: None,
+                relayer: *relayer,
+                transaction_hash: None,
+                block_number: None,
+                confirmations: U256::zero(),
+            },
+        );
+    }
+
+    pub fn update_state(&mut self, hash: &H256, state: RequestState, reason: Option<String>) {
+        if let Some(receipt) = self.receipts.get_mut(hash) {
+            receipt.state = state;
+            receipt.reason = reason;
+        }
+    }
+
+    pub fn update_transaction(&mut self, hash: &H256, relayer: &Address, tx_hash: &H256) {
+        if let Some(receipt) = self.receipts.get_mut(hash) {
+            receipt.relayer = *relayer;
+            receipt.transaction_hash = Some(*tx_hash);
+            // the transaction may be rebroadcasted with a new hash
+            receipt.block_number = None;
+        }
+    }
+
+    pub fn update_block_number(&mut self, hash: &H256, block_number: &U256) {
+        if let Some(receipt) = self.receipts.get_mut(hash) {
+            receipt.block_number = Some(*block_number);
+        }
+    }
+
+    /// Keeps receipt of a request which left the pool, a request which is not executed nor
+    /// invalid is marked as dropped.
+    pub fn finish(&mut self, hash: &H256, reason: Option<String>) {
+        match self.receipts.get_mut(hash) {
+            Some(receipt) => match receipt.state {
+                RequestState::Ready | RequestState::Processing => {
+                    receipt.state = RequestState::Dropped;
+                    receipt.reason = reason;
+                }
+                _ => {}
+            },
+            None => return,
+        }
+
+        self.finished.push_back(*hash);
+        while self.finished.len() > self.capacity {
+            if let Some(oldest) = self.finished.pop_front() {
+                self.receipts.remove(&oldest);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, hash: &H256) -> Option<&RequestReceipt> {
+        self.receipts.get(hash)
+    }
+}
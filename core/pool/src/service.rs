@@ -14,9 +14,10 @@
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 use ethereum_types::{Address, H256, U256};
+use futures::sync::mpsc;
 use futures::{Async, Future, Poll, Stream};
 use parking_lot::{Mutex, RwLock};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{
     atomic::{AtomicUsize, Ordering},
     Arc,
@@ -24,16 +25,16 @@ use std::sync::{
 use std::time::Duration;
 use tokio_timer::Interval;
 
-use types::{DelegateMode, SignedRequest};
+use types::{Currency, DelegateMode, RequestReceipt, RequestStatus, SignedRequest};
 
 use super::{
-    AddressFilter, Error, ErrorKind, InnerPool, PoolParams, PoolRequest, PoolRequestTag, Readiness,
-    RequestSelector, Status, Verifier,
+    AddressFilter, Error, ErrorKind, InnerPool, Journal, JournalRecord, NonceCache, PoolParams,
+    PoolRequest, PoolRequestTag, Readiness, ReadyChecker, RequestSelector, Status, Verifier,
 };
 
 pub struct Service<E, N, F, R, S, V>
 where
-    E: traits::EthereumService,
+    E: traits::EthereumService + 'static,
     N: traits::NetworkService,
     F: AddressFilter + Send,
     R: PoolRequest + Send + Sync + 'static,
@@ -66,11 +67,23 @@ where
 
     /// ticker for routine jobs
     ticker: Interval,
+
+    /// requests restored from journal, waiting for being verified again
+    restored_requests: Vec<SignedRequest>,
+
+    /// future of verifying restored requests
+    restoring: Mutex<Option<Box<Future<Item = (), Error = ()> + Send>>>,
+
+    /// on-chain nonces of senders in the pool
+    nonces: Arc<Mutex<NonceCache>>,
+
+    /// future of loading on-chain nonces of senders
+    loading_nonces: Mutex<Option<Box<Future<Item = (), Error = ()> + Send>>>,
 }
 
 impl<E, N, F, R, S, V> Service<E, N, F, R, S, V>
 where
-    E: traits::EthereumService,
+    E: traits::EthereumService + 'static,
     N: traits::NetworkService,
     F: AddressFilter + Send,
     R: PoolRequest + Send + Sync + 'static,
@@ -111,13 +124,131 @@ where
             dispatcher: Default::default(),
 
             ticker: Interval::new_interval(interval),
+
+            restored_requests: Vec::new(),
+            restoring: Mutex::new(None),
+
+            nonces: Arc::new(Mutex::new(NonceCache::new())),
+            loading_nonces: Mutex::new(None),
+        }
+    }
+
+    /// Attaches a journal to the pool, all further changes are recorded into the journal.
+    /// Restored requests which are not executed or invalid are verified and imported again.
+    pub fn set_journal(&mut self, journal: Journal, records: Vec<JournalRecord>) {
+        self.inner.write().set_journal(journal);
+        self.restored_requests = records
+            .into_iter()
+            .filter(|record| match record.tag {
+                PoolRequestTag::Ready | PoolRequestTag::Processing => true,
+                PoolRequestTag::Executed | PoolRequestTag::Invalid => false,
+            })
+            .map(|record| record.request)
+            .collect();
+    }
+
+    fn restore_requests(&mut self) {
+        let requests = ::std::mem::replace(&mut self.restored_requests, Vec::new());
+        info!(target: "pool", "Verifying {} request(s) restored from journal", requests.len());
+
+        let futures: Vec<_> = requests
+            .into_iter()
+            .map(|request| {
+                let hash = *request.hash();
+                let inner = self.inner.clone();
+                traits::PoolService::import(self, request).then(move |result| {
+                    if let Err(ref err) = result {
+                        warn!(target: "pool",
+                            "Failed to restore request {:?} from journal, error: {}", hash, err);
+                        // the request would be restored and verified again on every restart
+                        inner.write().forget(&hash);
+                    }
+                    Ok::<bool, ()>(result.is_ok())
+                })
+            })
+            .collect();
+
+        *self.restoring.lock() = Some(Box::new(futures::future::join_all(futures).map(
+            |results| {
+                let restored = results.into_iter().filter(|ok| *ok).count();
+                info!(target: "pool", "{} request(s) are restored from journal", restored);
+            },
+        )));
+    }
+
+    /// Loads on-chain nonces of senders in the pool, nonces are reloaded once a new block is
+    /// mined.
+    fn load_nonces(&mut self) {
+        let pairs: Vec<(Address, Address)> = self
+            .inner
+            .read()
+            .senders_with_token()
+            .flat_map(|(sender, tokens)| {
+                let sender = *sender;
+                tokens.into_iter().map(move |token| (sender, token))
+            })
+            .collect();
+
+        if pairs.is_empty() {
+            return;
+        }
+
+        let ethereum = self.ethereum.clone();
+        let nonces = self.nonces.clone();
+        let block_number = self.ethereum.lock().get_block_number();
+        let loading = block_number
+            .and_then(move |block_number| {
+                let pairs: Vec<_> = {
+                    let nonces = nonces.lock();
+                    match nonces.block_number() == Some(&block_number) {
+                        true => pairs
+                            .into_iter()
+                            .filter(|(sender, token)| !nonces.contains(sender, token))
+                            .collect(),
+                        false => pairs,
+                    }
+                };
+
+                let futures: Vec<_> = {
+                    let ethereum = ethereum.lock();
+                    pairs
+                        .into_iter()
+                        .map(|(sender, token)| {
+                            ethereum
+                                .nonce_of(sender, Currency::Token(token))
+                                .map(move |nonce| ((sender, token), nonce))
+                        })
+                        .collect()
+                };
+
+                futures::future::join_all(futures)
+                    .map(move |loaded| nonces.lock().update(block_number, loaded))
+            })
+            .map_err(|err| {
+                warn!(target: "pool", "Failed to load nonces of senders, error: {}", err);
+            });
+
+        *self.loading_nonces.lock() = Some(Box::new(loading));
+    }
+
+    fn poll_loading_nonces(&mut self) {
+        let mut loading_nonces = self.loading_nonces.lock();
+        let loaded = match *loading_nonces {
+            Some(ref mut loading) => match loading.poll() {
+                Ok(Async::NotReady) => false,
+                _ => true,
+            },
+            None => false,
+        };
+        if loaded {
+            *loading_nonces = None;
         }
     }
 }
 
 impl<E, N, F, R, S, V> traits::PoolService for Service<E, N, F, R, S, V>
 where
-    E: traits::EthereumService,
+    E: traits::EthereumService + 'static,
     N: traits::NetworkService,
     F: AddressFilter + Send,
     R: PoolRequest + Send + Sync + 'static,
@@ -195,18 +326,36 @@ where
         } = pending_settings;
 
         let mut pool = self.inner.write();
-        // let _senders_with_token = pool.senders_with_token();
 
-        let ready = |_request: &R| {
-            // TODO check if token transfer request is executable
-            Readiness::Ready
+        // remove requests which are outdated by on-chain nonces, requests being processed are
+        // left to the machine.
+        let culled = {
+            let tags = pool.tags().clone();
+            let mut checker = self.nonces.lock().checker();
+            pool.cull(None, |request: &R| match tags.get(request.hash()) {
+                Some(PoolRequestTag::Ready) => checker.is_ready(request),
+                _ => Readiness::Future,
+            })
         };
+        if culled > 0 {
+            debug!(target: "pool", "{} stale request(s) are removed from pool", culled);
+        }
+
+        let ready = self.nonces.lock().checker();
 
         let mut total_gas_amount = U256::from(0);
+        // senders and tokens of which a request is skipped, following requests would have
+        // nonce gaps
+        let mut skipped = HashSet::new();
         let requests: Vec<_> = {
             let tags = pool.tags();
             pool.pending(ready)
                 .filter(|req| {
+                    let key = (*req.sender(), *req.token());
+                    if skipped.contains(&key) {
+                        return false;
+                    }
+
                     let hash = req.hash();
                     match tags.get(hash) {
                         Some(PoolRequestTag::Ready) => {
@@ -236,6 +385,7 @@ where
 
                         return true;
                     }
+                    skipped.insert(key);
                     false
                 })
                 .map(|req| req.clone_signed())
@@ -263,6 +413,19 @@ where
             .collect()
     }
 
+    fn future_requests(&self) -> Vec<Arc<SignedRequest>> {
+        let mut checker = self.nonces.lock().checker();
+        let future = |request: &R| match checker.is_ready(request) {
+            Readiness::Future => Readiness::Ready,
+            Readiness::Ready | Readiness::Stale => Readiness::Stale,
+        };
+        self.inner
+            .read()
+            .unordered_pending(future)
+            .map(|req| req.clone_signed())
+            .collect()
+    }
+
     #[inline]
     fn mark_by_hash(&mut self, hash: &H256, tag: PoolRequestTag) {
         self.inner.write().mark_by_hash(hash, tag);
@@ -273,6 +436,36 @@ where
         self.inner.write().mark_by_hashes(hashes, tag);
     }
 
+    #[inline]
+    fn mark_invalid(&mut self, hash: &H256, reason: String) {
+        self.inner.write().mark_invalid(hash, reason);
+    }
+
+    #[inline]
+    fn invalid_reason(&self, hash: &H256) -> Option<String> {
+        self.inner.read().invalid_reason(hash).cloned()
+    }
+
+    #[inline]
+    fn mark_broadcasted(&mut self, hashes: &[H256], relayer: &Address, tx_hash: &H256) {
+        self.inner.write().mark_broadcasted(hashes, relayer, tx_hash);
+    }
+
+    #[inline]
+    fn mark_mined(&mut self, hashes: &[H256], block_number: U256) {
+        self.inner.write().mark_mined(hashes, &block_number);
+    }
+
+    #[inline]
+    fn request_receipt(&self, hash: &H256) -> Option<RequestReceipt> {
+        self.inner.read().receipt(hash).cloned()
+    }
+
+    #[inline]
+    fn watch_request(&mut self, hash: &H256) -> Option<mpsc::UnboundedReceiver<RequestStatus>> {
+        self.inner.write().watch(hash)
+    }
+
     #[inline]
     fn remove_by_hash(&mut self, hash: &H256) -> Option<Arc<SignedRequest>> {
         self.inner.write().remove(hash)
@@ -361,14 +554,18 @@ where
 
     fn token_status(&self, token_address: &Address) -> Result<Status, Error> {
         match self.token_filter.lock().is_allowed(&token_address) {
-            true => Ok(self.inner.write().token_status(token_address)),
+            true => {
+                let ready = self.nonces.lock().checker();
+                Ok(self.inner.write().token_status(token_address, ready))
+            }
             false => Err(Error::from(ErrorKind::NotSupportedToken)),
         }
     }
 
     #[inline]
     fn status(&self) -> HashMap<Address, Status> {
-        self.inner.write().status()
+        let ready = self.nonces.lock().checker();
+        self.inner.write().status(ready)
     }
 
     #[inline]
@@ -376,6 +573,11 @@ where
         self.relayers = relayers;
     }
 
+    #[inline]
+    fn relayers(&self) -> Vec<Address> {
+        self.relayers.clone()
+    }
+
     #[inline]
     fn set_dispatcher(&mut self, dispatcher: Address) {
         self.dispatcher = dispatcher;
@@ -384,23 +586,51 @@ where
 
 impl<E, N, F, R, S, V> Stream for Service<E, N, F, R, S, V>
 where
-    E: traits::EthereumService,
+    E: traits::EthereumService + 'static,
     N: traits::NetworkService,
     F: AddressFilter + Send,
-    R: PoolRequest + Send + Sync,
-    S: RequestSelector<R> + Send,
+    R: PoolRequest + Send + Sync + 'static,
+    S: RequestSelector<R> + Send + Sync + 'static,
     V: Verifier<Request = R, Error = Error> + Send,
 {
     type Item = ();
     type Error = Error;
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if !self.restored_requests.is_empty() {
+            self.restore_requests();
+        }
+
+        {
+            let mut restoring = self.restoring.lock();
+            let restored = match *restoring {
+                Some(ref mut restoring) => match restoring.poll() {
+                    Ok(Async::NotReady) => false,
+                    _ => true,
+                },
+                None => false,
+            };
+            if restored {
+                *restoring = None;
+            }
+        }
+
+        self.poll_loading_nonces();
+
         loop {
             match self.ticker.poll() {
                 Ok(Async::Ready(_)) => {
                     // trace!(target: "pool", "pool timeouts");
-                    // TODO add pool routine jobs here
-                    // self.inner.lock().remove_stalled
+                    let expired = self.inner.write().remove_expired();
+                    if expired > 0 {
+                        info!(target: "pool",
+                            "{} expired request(s) are removed from pool", expired);
+                    }
+
+                    if self.loading_nonces.lock().is_none() {
+                        self.load_nonces();
+                        self.poll_loading_nonces();
+                    }
                 }
                 Ok(Async::NotReady) => {
                     return Ok(Async::NotReady);
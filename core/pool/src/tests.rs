@@ -14,3 +14,453 @@
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 use super::*;
+use ethkey::{Generator, Random};
+use futures::Stream;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::{env, process, thread};
+use std::time::Duration;
+
+use journal::Journal;
+use types::{RequestState, RequestStatus};
+
+fn signed_request(nonce: u64) -> SignedRequest {
+    let keypair = Random.generate().unwrap();
+    types::Request {
+        nonce: nonce.into(),
+        ..types::Request::empty()
+    }.sign(keypair.secret())
+}
+
+fn token_request(token: u64, nonce: u64) -> VerifiedRequest {
+    let signed = types::Request {
+        token_address: Address::from(token),
+        nonce: nonce.into(),
+        ..types::Request::empty()
+    }.null_sign();
+    VerifiedRequest::from_signed(signed, nonce as usize)
+}
+
+fn fee_request(keypair: &ethkey::KeyPair, nonce: u64, fee: u64) -> VerifiedRequest {
+    let signed = types::Request {
+        nonce: nonce.into(),
+        fee: fee.into(),
+        ..types::Request::empty()
+    }.sign(keypair.secret());
+    VerifiedRequest::from_signed(signed, nonce as usize)
+}
+
+fn journal_path(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("pool-{}-{}.journal", name, process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn test_watchers_drop_after_terminal_status() {
+    let hash = H256::from(1);
+    let mut watchers = Watchers::default();
+    let receiver = watchers.add(&hash, RequestStatus::Ready);
+
+    watchers.notify(&hash, RequestStatus::Processing);
+    watchers.notify(&hash, RequestStatus::Executed);
+    watchers.notify(&hash, RequestStatus::Ready);
+
+    let statuses: Vec<_> = receiver.wait().map(Result::unwrap).collect();
+    assert_eq!(
+        statuses,
+        vec![
+            RequestStatus::Ready,
+            RequestStatus::Processing,
+            RequestStatus::Executed,
+        ]
+    );
+}
+
+#[test]
+fn test_receipts_keep_finished_requests() {
+    let relayer = Address::from(1);
+    let mut receipts = Receipts::new(1);
+
+    receipts.insert(&H256::from(1), &relayer);
+    receipts.update_state(&H256::from(1), RequestState::Executed, None);
+    receipts.finish(&H256::from(1), None);
+    assert_eq!(
+        receipts.get(&H256::from(1)).map(|receipt| receipt.state),
+        Some(RequestState::Executed)
+    );
+
+    receipts.insert(&H256::from(2), &relayer);
+    receipts.finish(&H256::from(2), None);
+    assert_eq!(
+        receipts.get(&H256::from(2)).map(|receipt| receipt.state),
+        Some(RequestState::Dropped)
+    );
+    assert!(receipts.get(&H256::from(1)).is_none());
+}
+
+#[test]
+fn test_nonce_ready_checker() {
+    let token = Address::from(1);
+    let sender = *token_request(1, 0).sender();
+    let mut nonces = HashMap::new();
+    nonces.insert((sender, token), U256::from(3));
+    let mut checker = NonceReadyChecker::new(nonces);
+
+    let readiness: Vec<_> = [2, 3, 4, 6]
+        .iter()
+        .map(|nonce| format!("{:?}", checker.is_ready(&token_request(1, *nonce))))
+        .collect();
+    assert_eq!(readiness, vec!["Stale", "Ready", "Ready", "Future"]);
+
+    // nonces of other tokens are not loaded
+    match checker.is_ready(&token_request(2, 0)) {
+        Readiness::Future => {}
+        readiness => panic!("unexpected readiness {:?}", readiness),
+    }
+}
+
+#[test]
+fn test_remove_expired_requests() {
+    let params = PoolParams {
+        max_age: Some(Duration::from_millis(0)),
+        ..PoolParams::default()
+    };
+    let mut pool = InnerPool::new(params, Arc::new(TokenSelector::new()));
+
+    let signed = types::Request {
+        nonce: 1.into(),
+        ..types::Request::empty()
+    }.null_sign();
+    let hash = *signed.hash();
+    pool.import(VerifiedRequest::from_signed(signed, 0)).unwrap();
+
+    thread::sleep(Duration::from_millis(10));
+    assert_eq!(pool.remove_expired(), 1);
+    assert!(!pool.contains_hash(&hash));
+
+    let receipt = pool.receipt(&hash).cloned().unwrap();
+    assert_eq!(receipt.state, RequestState::Dropped);
+    assert_eq!(receipt.reason, Some("expired".to_owned()));
+}
+
+#[test]
+fn test_keep_reason_of_removed_invalid_request() {
+    let mut pool = InnerPool::new(PoolParams::default(), Arc::new(TokenSelector::new()));
+
+    let request = token_request(0, 1);
+    let hash = *request.hash();
+    pool.import(request).unwrap();
+    pool.mark_invalid(&hash, "reverted".to_owned());
+
+    assert!(pool.remove(&hash).is_some());
+
+    assert_eq!(pool.invalid_reason(&hash), Some(&"reverted".to_owned()));
+    let receipt = pool.receipt(&hash).cloned().unwrap();
+    assert_eq!(receipt.state, RequestState::Invalid);
+}
+
+#[test]
+fn test_evict_worst_request_when_memory_limit_is_reached() {
+    let params = PoolParams {
+        max_mem_usage: token_request(0, 0).mem_usage(),
+        ..PoolParams::default()
+    };
+    let mut pool = InnerPool::new(params, Arc::new(TokenSelector::new()));
+
+    let evicted = token_request(0, 2);
+    pool.import(evicted.clone()).unwrap();
+
+    // earlier request of the same sender is preferred
+    pool.import(token_request(0, 1)).unwrap();
+    assert_eq!(pool.len(), 1);
+    assert_eq!(
+        pool.receipt(evicted.hash()).and_then(|receipt| receipt.reason.clone()),
+        Some("evicted".to_owned())
+    );
+
+    match pool.import(token_request(0, 3)) {
+        Err(Error(ErrorKind::MemoryLimitReached(_), _)) => {}
+        result => panic!("unexpected import result {:?}", result),
+    }
+    assert_eq!(pool.len(), 1);
+}
+
+#[test]
+fn test_keep_requests_when_import_is_rejected() {
+    let sender = Random.generate().unwrap();
+    let cheap = fee_request(&Random.generate().unwrap(), 0, 1);
+    let params = PoolParams {
+        max_per_sender: 1,
+        max_mem_usage: 2 * cheap.mem_usage(),
+        ..PoolParams::default()
+    };
+    let mut pool = InnerPool::new(params, Arc::new(TokenSelector::new()));
+    pool.import(cheap.clone()).unwrap();
+    pool.import(fee_request(&sender, 1, 10)).unwrap();
+
+    // the cheap request could be evicted for the new one, but the sender has no room for it
+    match pool.import(fee_request(&sender, 2, 10)) {
+        Err(Error(ErrorKind::TooCheapToEnter(_, _), _)) => {}
+        result => panic!("unexpected import result {:?}", result),
+    }
+    assert_eq!(pool.len(), 2);
+    assert!(pool.contains_hash(cheap.hash()));
+}
+
+#[test]
+fn test_replace_request_without_eviction() {
+    let sender = Random.generate().unwrap();
+    let cheap = fee_request(&Random.generate().unwrap(), 0, 1);
+    let params = PoolParams {
+        max_mem_usage: 2 * cheap.mem_usage(),
+        ..PoolParams::default()
+    };
+    let mut pool = InnerPool::new(params, Arc::new(TokenSelector::new()));
+    pool.import(cheap.clone()).unwrap();
+    let replaced = fee_request(&sender, 1, 5);
+    pool.import(replaced.clone()).unwrap();
+
+    let replacement = fee_request(&sender, 1, 10);
+    pool.import(replacement.clone()).unwrap();
+    assert_eq!(pool.len(), 2);
+    assert!(pool.contains_hash(cheap.hash()));
+    assert!(pool.contains_hash(replacement.hash()));
+    assert!(!pool.contains_hash(replaced.hash()));
+    assert_eq!(
+        pool.receipt(cheap.hash()).and_then(|receipt| receipt.reason.clone()),
+        None
+    );
+}
+
+#[test]
+fn test_journal_replay() {
+    let path = journal_path("replay");
+    let (executed, removed, ready) = (signed_request(1), signed_request(2), signed_request(3));
+
+    {
+        let (mut journal, records) = Journal::open(&path).unwrap();
+        assert!(records.is_empty());
+
+        journal.record_import(&executed);
+        journal.record_import(&removed);
+        journal.record_import(&ready);
+        journal.record_tag(&[*executed.hash()], PoolRequestTag::Processing);
+        journal.record_tag(&[*executed.hash()], PoolRequestTag::Executed);
+        journal.record_remove(&[*removed.hash()]);
+    }
+
+    // a line broken by crashing while writing is skipped
+    {
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"type\":\"imp").unwrap();
+    }
+
+    let (_, records) = Journal::open(&path).unwrap();
+    let records: Vec<_> = records
+        .into_iter()
+        .map(|record| (*record.request.hash(), record.tag))
+        .collect();
+    assert_eq!(
+        records,
+        vec![
+            (*executed.hash(), PoolRequestTag::Executed),
+            (*ready.hash(), PoolRequestTag::Ready),
+        ]
+    );
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_journal_compaction() {
+    let path = journal_path("compaction");
+    let (removed, processing) = (signed_request(1), signed_request(2));
+
+    {
+        let (mut journal, _) = Journal::open(&path).unwrap();
+        journal.record_import(&removed);
+        journal.record_import(&processing);
+        journal.record_tag(&[*processing.hash()], PoolRequestTag::Processing);
+        journal.record_tag(&[*processing.hash()], PoolRequestTag::Processing);
+        journal.record_remove(&[*removed.hash()]);
+    }
+
+    let line_count = |path: &PathBuf| {
+        BufReader::new(fs::File::open(path).unwrap())
+            .lines()
+            .count()
+    };
+    assert_eq!(line_count(&path), 5);
+
+    // only an import and a tag entry of each alive request are kept
+    let (_, records) = Journal::open(&path).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(line_count(&path), 2);
+
+    let (_, records) = Journal::open(&path).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].request.hash(), processing.hash());
+    assert_eq!(records[0].tag, PoolRequestTag::Processing);
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_allow_and_deny_tokens() {
+    let (token1, token2) = (Address::from(1), Address::from(2));
+
+    for mode in [ListAddressFilterMode::Whitelist, ListAddressFilterMode::Blacklist].iter() {
+        let mut filter = ListAddressFilter::new(*mode);
+        filter.allow_token(token1);
+        filter.deny_token(token2);
+        assert!(filter.is_allowed(&token1), "{:?}", mode);
+        assert!(filter.is_denied(&token2), "{:?}", mode);
+
+        filter.deny_token(token1);
+        filter.allow_token(token2);
+        assert!(filter.is_denied(&token1), "{:?}", mode);
+        assert!(filter.is_allowed(&token2), "{:?}", mode);
+    }
+
+    // denied tokens are listed in blacklist mode, allowed tokens are listed in whitelist mode
+    let mut filter = ListAddressFilter::new(ListAddressFilterMode::Blacklist);
+    filter.deny_token(token1);
+    assert_eq!(filter.tokens().iter().collect::<Vec<_>>(), vec![&token1]);
+    filter.set_mode(ListAddressFilterMode::Whitelist);
+    filter.deny_token(token1);
+    assert!(filter.tokens().is_empty());
+}
+
+#[test]
+fn test_remove_requests_of_denied_tokens() {
+    // requests of a sender are ordered by token priority, tokens of the same priority would
+    // compete for the same place in the queue
+    let mut priorities = HashMap::new();
+    priorities.insert(Address::from(2), 1);
+    let selector = TokenSelector::with_priorities(priorities);
+    let mut pool = InnerPool::new(PoolParams::default(), Arc::new(selector));
+    pool.import(token_request(1, 0)).unwrap();
+    pool.import(token_request(2, 1)).unwrap();
+
+    let mut filter = ListAddressFilter::new(ListAddressFilterMode::Whitelist);
+    filter.allow_token(Address::from(1));
+    let removed = pool.remove_by_filter(|req: &VerifiedRequest| filter.is_denied(req.token()));
+
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].unverified().token(), &Address::from(2));
+    assert_eq!(pool.len(), 1);
+}
+
+#[test]
+fn test_status_counts_ready_and_future_requests() {
+    let token = Address::from(1);
+
+    let mut pool = InnerPool::new(PoolParams::default(), Arc::new(TokenSelector::new()));
+    for nonce in [3, 4, 6].iter() {
+        pool.import(token_request(1, *nonce)).unwrap();
+    }
+    let processing = token_request(1, 5);
+    pool.import(processing.clone()).unwrap();
+    pool.mark_by_hash(processing.hash(), PoolRequestTag::Processing);
+
+    let sender = *token_request(1, 0).sender();
+    let mut nonces = HashMap::new();
+    nonces.insert((sender, token), U256::from(3));
+    let status = pool.token_status(&token, NonceReadyChecker::new(nonces));
+    assert_eq!((status.ready, status.future), (2, 1));
+
+    // requests are future until on-chain nonces are loaded
+    let status = pool.token_status(&token, NonceReadyChecker::new(HashMap::new()));
+    assert_eq!((status.ready, status.future), (0, 3));
+}
+
+#[test]
+fn test_minimum_fee() {
+    let gas_amount = U256::from(21000);
+    let gas_price = U256::from(1_000_000_000u64);
+    let one_ether = U256::from(1_000_000_000_000_000_000u64);
+
+    // 1 token costs 1 ether, fee in token equals gas cost in wei
+    assert_eq!(
+        minimum_fee(gas_amount, gas_price, one_ether, 0),
+        Some(U256::from(21_000_000_000_000u64))
+    );
+    assert_eq!(
+        minimum_fee(gas_amount, gas_price, one_ether, 10),
+        Some(U256::from(23_100_000_000_000u64))
+    );
+    // 1 token costs 2 ether, fee in token is half of gas cost in wei
+    assert_eq!(
+        minimum_fee(gas_amount, gas_price, one_ether * U256::from(2), 0),
+        Some(U256::from(10_500_000_000_000u64))
+    );
+
+    assert_eq!(minimum_fee(gas_amount, gas_price, U256::zero(), 0), None);
+    assert_eq!(
+        minimum_fee(U256::max_value(), gas_price, one_ether, 0),
+        None
+    );
+    // gas cost fits but the margin overflows
+    assert_eq!(
+        minimum_fee(
+            U256::max_value() / U256::from(100),
+            U256::one(),
+            one_ether,
+            10
+        ),
+        None
+    );
+}
+
+#[test]
+fn test_cull_stale_requests_of_mixed_tokens() {
+    let mut priorities = HashMap::new();
+    priorities.insert(Address::from(2), 1);
+    let selector = TokenSelector::with_priorities(priorities);
+
+    // stale request of token 2 sits behind the requests of token 1
+    let mut queue = RequestQueue::new();
+    for &(token, nonce) in [(1, 1), (1, 2), (1, 3), (2, 0), (2, 5)].iter() {
+        match queue.add(token_request(token, nonce), &selector, 16) {
+            AddResult::Ok(_) => {}
+            _ => panic!("request ({}, {}) is not added", token, nonce),
+        }
+    }
+
+    let sender = *token_request(1, 0).sender();
+    let mut nonces = HashMap::new();
+    nonces.insert((sender, Address::from(1)), U256::from(2));
+    nonces.insert((sender, Address::from(2)), U256::from(1));
+    let mut checker = NonceReadyChecker::new(nonces.clone());
+
+    let mut culled: Vec<_> = queue
+        .cull(&mut checker, &selector)
+        .into_iter()
+        .map(|req| (*req.token(), req.nonce().low_u64()))
+        .collect();
+    culled.sort();
+    assert_eq!(culled, vec![(Address::from(1), 1), (Address::from(2), 0)]);
+
+    let mut remaining: Vec<_> = queue
+        .iter()
+        .map(|req| (*req.token(), req.nonce().low_u64()))
+        .collect();
+    remaining.sort();
+    assert_eq!(
+        remaining,
+        vec![
+            (Address::from(1), 2),
+            (Address::from(1), 3),
+            (Address::from(2), 5),
+        ]
+    );
+
+    // nothing is left to cull, a checker advances nonces of ready requests so culls of the
+    // pool use a new one
+    let mut checker = NonceReadyChecker::new(nonces);
+    assert!(queue.cull(&mut checker, &selector).is_empty());
+    assert_eq!(queue.len(), 3);
+}
@@ -26,10 +26,27 @@ lazy_static! {
     static ref INTRINSIC_GAS_AMOUNT: U256 = U256::from(21000);
 }
 
-fn minus_intrinsic_gas_amount(origin_gas_amount: U256) -> U256 {
+/// Returns gas amount of a request without the intrinsic gas of a transaction, the intrinsic gas
+/// is paid once by a collation transaction which contains many requests
+pub fn minus_intrinsic_gas_amount(origin_gas_amount: U256) -> U256 {
     origin_gas_amount - *INTRINSIC_GAS_AMOUNT
 }
 
+/// Returns minimum fee in token units of a request which covers its gas cost with margin,
+/// returns `None` if price of the token is unknown or the fee overflows
+pub fn minimum_fee(
+    gas_amount: U256,
+    gas_price: U256,
+    token_price: U256,
+    margin_percent: u32,
+) -> Option<U256> {
+    let cost = gas_amount
+        .checked_mul(gas_price)?
+        .checked_mul(U256::from(100 + margin_percent as u64))?
+        / U256::from(100);
+    pricer::wei_in_token_amount(&cost, &token_price)
+}
+
 pub trait Verifier {
     type Request: PoolRequest;
     type Error: ::std::error::Error + Send + 'static;
@@ -45,25 +62,67 @@ pub trait Verifier {
         S: 'static + RequestSelector<Self::Request>;
 }
 
-pub struct RequestVerifier<E>
+pub struct RequestVerifier<E, G>
 where
     E: traits::EthereumService,
+    G: traits::PriceService,
 {
     ethereum: Arc<Mutex<E>>,
+    pricer: Arc<Mutex<G>>,
+    // minimum margin of request fee over its gas cost, no fee check if none
+    min_fee_margin_percent: Option<u32>,
 }
 
-impl<E> RequestVerifier<E>
+impl<E, G> RequestVerifier<E, G>
 where
     E: traits::EthereumService,
+    G: traits::PriceService,
 {
-    pub fn new(ethereum: Arc<Mutex<E>>) -> RequestVerifier<E> {
-        RequestVerifier { ethereum }
+    pub fn new(
+        ethereum: Arc<Mutex<E>>,
+        pricer: Arc<Mutex<G>>,
+        min_fee_margin_percent: Option<u32>,
+    ) -> RequestVerifier<E, G> {
+        RequestVerifier {
+            ethereum,
+            pricer,
+            min_fee_margin_percent,
+        }
+    }
+
+    /// Returns gas price and price of token for checking fee, returns `None` if fee check is
+    /// disabled
+    fn fee_prices(
+        &self,
+        token: Address,
+    ) -> Box<Future<Item = Option<(U256, U256)>, Error = Error> + Send> {
+        if self.min_fee_margin_percent.is_none() {
+            return Box::new(futures::future::ok(None));
+        }
+
+        let pricer = self.pricer.lock();
+        Box::new(
+            pricer
+                .gas_price()
+                .join(pricer.token_price(token))
+                .then(move |result| match result {
+                    Ok(prices) => Ok(Some(prices)),
+                    Err(err) => {
+                        warn!(target: "pool",
+                            "failed to fetch prices for checking fee of token {:?}, error: {}",
+                            token, err
+                        );
+                        Err(Error::from(ErrorKind::TokenPriceUnavailable(token)))
+                    }
+                }),
+        )
     }
 }
 
-impl<E> Verifier for RequestVerifier<E>
+impl<E, G> Verifier for RequestVerifier<E, G>
 where
     E: traits::EthereumService,
+    G: traits::PriceService,
 {
     type Request = VerifiedRequest;
     type Error = Error;
@@ -78,6 +137,10 @@ where
     where
         S: 'static + RequestSelector<Self::Request>,
     {
+        let hash = *signed_request.hash();
+        let fee_prices = self.fee_prices(*signed_request.unverified().token());
+        let margin_percent = self.min_fee_margin_percent.unwrap_or(0);
+
         Box::new(
             self.ethereum
                 .lock()
@@ -85,34 +148,65 @@ where
                     relayer_address,
                     signed_request: signed_request.clone(),
                 })
-            .then(move |result| match result {
-                Ok(origin_gas_amount) => {
+                .then(move |result| match result {
+                    Ok(origin_gas_amount) => Ok(origin_gas_amount),
+                    Err(err) => {
+                        info!("{}: failed to estiamate gas, error: {:?}", hash, err);
+                        Err(Error::from(
+                            ErrorKind::TokenTransferRequestGasEstimationFailed,
+                        ))
+                    }
+                })
+                .join(fee_prices)
+                .and_then(move |(origin_gas_amount, fee_prices)| {
                     let calibrated_gas_amount = minus_intrinsic_gas_amount(origin_gas_amount);
 
                     info!(target: "pool",
                         "{}: origin estimated gas amount: {}, calibrated estimated gas amount: {}",
-                        signed_request.hash(),
+                        hash,
                         origin_gas_amount,
                         calibrated_gas_amount,
                     );
+
+                    if let Some((gas_price, token_price)) = fee_prices {
+                        let token = *signed_request.unverified().token();
+                        let fee = *signed_request.unverified().fee();
+                        let min_fee = match minimum_fee(
+                            calibrated_gas_amount,
+                            gas_price,
+                            token_price,
+                            margin_percent,
+                        ) {
+                            Some(min_fee) => min_fee,
+                            None if token_price.is_zero() => {
+                                return Err(Error::from(ErrorKind::TokenPriceUnavailable(token)));
+                            }
+                            None => {
+                                return Err(Error::from(ErrorKind::MinimumFeeOverflow(format!(
+                                    "{:?}",
+                                    hash
+                                ))));
+                            }
+                        };
+
+                        if fee < min_fee {
+                            info!(target: "pool",
+                                "{}: fee {} is lower than minimum fee {}", hash, fee, min_fee);
+                            return Err(Error::from(ErrorKind::FeeTooLow(
+                                format!("{:?}", hash),
+                                fee,
+                                min_fee,
+                            )));
+                        }
+                    }
+
                     pool.write()
                         .import(VerifiedRequest::from_signed_with_gas_amount(
                             signed_request,
                             insertion_id,
                             calibrated_gas_amount,
                         ))
-                }
-                Err(err) => {
-                    info!(
-                        "{}: failed to estiamate gas, error: {:?}",
-                        signed_request.hash(),
-                        err
-                    );
-                    Err(Error::from(
-                        ErrorKind::TokenTransferRequestGasEstimationFailed,
-                    ))
-                }
-            }),
+                }),
         )
     }
 }
@@ -0,0 +1,72 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::H256;
+use futures::sync::mpsc;
+use std::collections::HashMap;
+
+use types::RequestStatus;
+
+/// Subscribers of request status changes
+#[derive(Default)]
+pub struct Watchers {
+    senders: HashMap<H256, Vec<mpsc::UnboundedSender<RequestStatus>>>,
+}
+
+impl Watchers {
+    /// Adds a watcher of request, `status` is sent to the watcher immediately
+    pub fn add(
+        &mut self,
+        hash: &H256,
+        status: RequestStatus,
+    ) -> mpsc::UnboundedReceiver<RequestStatus> {
+        let (sender, receiver) = mpsc::unbounded();
+        if sender.unbounded_send(status.clone()).is_ok() && !status.is_terminal() {
+            self.senders
+                .entry(*hash)
+                .or_insert_with(Vec::new)
+                .push(sender);
+        }
+        receiver
+    }
+
+    /// Notifies watchers of request, watchers are dropped after terminal status
+    pub fn notify(&mut self, hash: &H256, status: RequestStatus) {
+        let terminal = status.is_terminal();
+        let remains = match self.senders.get_mut(hash) {
+            Some(senders) => {
+                senders.retain(|sender| sender.unbounded_send(status.clone()).is_ok());
+                !senders.is_empty()
+            }
+            None => return,
+        };
+
+        if terminal || !remains {
+            self.senders.remove(hash);
+        }
+    }
+
+    /// Drops watchers of request, their streams are ended
+    #[inline]
+    pub fn remove(&mut self, hash: &H256) {
+        self.senders.remove(hash);
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.senders.clear();
+    }
+}
+
@@ -0,0 +1,86 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::U256;
+use std::time::Duration;
+
+/// Parameters of calibrate mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrateParams {
+    // interval between two gas price samples
+    pub interval: Duration,
+
+    // percentile of transaction gas prices in latest block
+    pub percentile: u32,
+
+    // weight of a new sample in percent when smoothing gas price
+    pub smoothing_percent: u32,
+
+    pub min_gas_price: U256,
+    pub max_gas_price: U256,
+}
+
+/// Returns the value at given percentile of gas prices
+pub fn percentile(mut gas_prices: Vec<U256>, percentile: u32) -> Option<U256> {
+    if gas_prices.is_empty() {
+        return None;
+    }
+
+    gas_prices.sort_unstable();
+    let percentile = u32::min(percentile, 100) as usize;
+    let index = (gas_prices.len() - 1) * percentile / 100;
+    Some(gas_prices[index])
+}
+
+/// Combines gas price suggested by Ethereum node and the percentile of block gas prices
+pub fn sample(node_gas_price: U256, block_gas_prices: Vec<U256>, block_percentile: u32) -> U256 {
+    match percentile(block_gas_prices, block_percentile) {
+        Some(block_gas_price) => (node_gas_price + block_gas_price) / U256::from(2),
+        None => node_gas_price,
+    }
+}
+
+/// Smooths gas price with exponential moving average
+pub fn smooth(current: U256, sample: U256, smoothing_percent: u32) -> U256 {
+    let weight = U256::from(u32::min(smoothing_percent, 100));
+    let hundred = U256::from(100);
+    (current * (hundred - weight) + sample * weight) / hundred
+}
+
+/// Caps gas price with floor and ceiling
+pub fn cap(gas_price: U256, min_gas_price: U256, max_gas_price: U256) -> U256 {
+    U256::min(U256::max(gas_price, min_gas_price), max_gas_price)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let gas_prices: Vec<U256> = vec![5, 1, 4, 2, 3].into_iter().map(U256::from).collect();
+        assert_eq!(percentile(gas_prices.clone(), 0), Some(U256::from(1)));
+        assert_eq!(percentile(gas_prices.clone(), 50), Some(U256::from(3)));
+        assert_eq!(percentile(gas_prices, 100), Some(U256::from(5)));
+        assert_eq!(percentile(vec![], 50), None);
+    }
+
+    #[test]
+    fn test_smooth_and_cap() {
+        assert_eq!(smooth(U256::from(100), U256::from(200), 25), U256::from(125));
+        assert_eq!(cap(U256::from(1), U256::from(10), U256::from(20)), U256::from(10));
+        assert_eq!(cap(U256::from(30), U256::from(10), U256::from(20)), U256::from(20));
+    }
+}
@@ -13,4 +13,61 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
-error_chain!{}
+use ethereum_types::Address;
+
+error_chain! {
+    foreign_links {
+        Io(std::io::Error);
+        Json(serde_json::Error);
+        Toml(toml::de::Error);
+        Hyper(hyper::Error);
+        ContractAbi(contract_abi::Error);
+    }
+
+    errors {
+        Ethereum(error: String) {
+            description("Ethereum service error")
+            display("Ethereum service error: {}", error)
+        }
+
+        UnknownTokenPrice(token: Address) {
+            description("No price source for token")
+            display("No price source for token {:?}", token)
+        }
+
+        StaleTokenPrice(token: Address) {
+            description("Token price is stale")
+            display("Price of token {:?} is stale", token)
+        }
+
+        InvalidPriceFile(error: String) {
+            description("Invalid token price file")
+            display("Invalid token price file: {}", error)
+        }
+
+        InvalidPriceUrl(url: String) {
+            description("Invalid token price URL")
+            display("Invalid token price URL {}", url)
+        }
+
+        InvalidJsonPath(json_path: String) {
+            description("Invalid JSONPath")
+            display("Invalid JSONPath {}", json_path)
+        }
+
+        InvalidPrice(price: String) {
+            description("Invalid token price")
+            display("Invalid token price {}", price)
+        }
+
+        InvalidExchangePair(pair: Address, token: Address) {
+            description("Exchange pair is not a pair of token and WETH")
+            display("Exchange pair {:?} is not a pair of token {:?} and WETH", pair, token)
+        }
+
+        EmptyReserve(pair: Address) {
+            description("Exchange pair has no reserve")
+            display("Exchange pair {:?} has no reserve", pair)
+        }
+    }
+}
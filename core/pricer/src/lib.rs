@@ -19,22 +19,45 @@ extern crate lazy_static;
 #[macro_use]
 extern crate error_chain;
 
+#[macro_use]
+extern crate log;
+
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 
 extern crate ethereum_types;
 extern crate futures;
+extern crate hyper;
+extern crate hyper_rustls;
+extern crate parking_lot;
+extern crate serde_json;
+extern crate tokio;
+extern crate toml;
 
+extern crate contract_abi;
 extern crate traits;
+extern crate types;
 
+mod calibrate;
 mod error;
+mod source;
 
 use ethereum_types::{Address, U256};
 use futures::{Async, Future, Poll, Stream};
+use parking_lot::Mutex;
 use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::timer::Interval;
+
+use traits::EthereumService;
+use types::BlockId;
 
-pub use self::error::Error;
+pub use self::calibrate::CalibrateParams;
+pub use self::error::{Error, ErrorKind};
+pub use self::source::{
+    TokenPriceFeed, TokenPriceFeedParams, TokenPriceSource, TokenPriceSourceParams,
+};
 
 lazy_static! {
     static ref WEI: U256 = U256::from(1);
@@ -45,62 +68,132 @@ lazy_static! {
     static ref FIXED_GAS_PRICE: U256 = U256::from(1) * (*GWEI);
 }
 
+/// Converts an amount of token into wei, token price is the amount of wei per 10^18 token units,
+/// returns `None` if the amount of wei overflows
+pub fn token_amount_in_wei(amount: &U256, token_price: &U256) -> Option<U256> {
+    match amount.checked_mul(*token_price) {
+        Some(value) => Some(value / *ETHER),
+        None => (*amount / *ETHER).checked_mul(*token_price),
+    }
+}
+
+/// Converts an amount of wei into token, returns `None` if token price is zero or the amount of
+/// token overflows
+pub fn wei_in_token_amount(wei: &U256, token_price: &U256) -> Option<U256> {
+    if token_price.is_zero() {
+        return None;
+    }
+
+    match wei.checked_mul(*ETHER) {
+        Some(value) => Some(value / *token_price),
+        None => (*wei / *token_price).checked_mul(*ETHER),
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PriceServiceMode {
     Fixed {
         gas_price: U256,
         token_prices: HashMap<Address, U256>,
     },
-    Calibrate,
+    Calibrate(CalibrateParams),
 }
 
-pub struct PriceService {
+type GasPriceSampler = Box<Future<Item = U256, Error = ()> + Send>;
+
+pub struct PriceService<E>
+where
+    E: EthereumService,
+{
     mode: PriceServiceMode,
+    ethereum: Arc<Mutex<E>>,
     gas_price: U256,
     token_prices: HashMap<Address, U256>,
+    token_feeds: HashMap<Address, TokenPriceFeed>,
+
+    // only used in calibrate mode
+    ticker: Option<Interval>,
+    sampler: Mutex<Option<GasPriceSampler>>,
+    calibrated: bool,
 }
 
-impl PriceService {
-    pub fn new(mode: PriceServiceMode) -> PriceService {
-        match mode.clone() {
+impl<E> PriceService<E>
+where
+    E: EthereumService + 'static,
+{
+    pub fn new(
+        mode: PriceServiceMode,
+        token_feeds: Vec<TokenPriceFeedParams>,
+        ethereum: Arc<Mutex<E>>,
+    ) -> Result<PriceService<E>, Error> {
+        let token_feeds = token_feeds
+            .into_iter()
+            .map(|params| Ok((params.token, TokenPriceFeed::new(params, ethereum.clone())?)))
+            .collect::<Result<HashMap<_, _>, Error>>()?;
+
+        Ok(match mode.clone() {
             PriceServiceMode::Fixed {
                 gas_price,
                 token_prices,
             } => PriceService {
                 mode,
+                ethereum,
                 gas_price,
                 token_prices,
+                token_feeds,
+                ticker: None,
+                sampler: Mutex::new(None),
+                calibrated: false,
             },
-            PriceServiceMode::Calibrate => PriceService {
+            PriceServiceMode::Calibrate(params) => PriceService {
                 mode,
-                gas_price: *FIXED_GAS_PRICE,
+                ethereum,
+                gas_price: calibrate::cap(
+                    *FIXED_GAS_PRICE,
+                    params.min_gas_price,
+                    params.max_gas_price,
+                ),
                 token_prices: HashMap::new(),
+                token_feeds,
+                ticker: Some(Interval::new_interval(params.interval)),
+                sampler: Mutex::new(None),
+                calibrated: false,
             },
-        }
+        })
     }
-}
 
-impl Default for PriceService {
-    fn default() -> PriceService {
-        PriceService {
-            mode: PriceServiceMode::Fixed {
-                gas_price: *FIXED_GAS_PRICE,
-                token_prices: {
-                    let prices = HashMap::new();
-                    prices
-                },
-            },
-            gas_price: *FIXED_GAS_PRICE,
-            token_prices: HashMap::new(),
-        }
+    fn new_sampler(&self, block_percentile: u32) -> GasPriceSampler {
+        let ethereum = self.ethereum.lock();
+        Box::new(
+            ethereum
+                .gas_price()
+                .join(ethereum.block_gas_prices(BlockId::Latest))
+                .map(move |(node_gas_price, block_gas_prices)| {
+                    calibrate::sample(node_gas_price, block_gas_prices, block_percentile)
+                })
+                .map_err(|err| {
+                    warn!(target: "pricer", "Failed to sample gas price, error: {}", err);
+                }),
+        )
     }
-}
 
-impl Drop for PriceService {
-    fn drop(&mut self) {}
+    fn update_gas_price(&mut self, params: &CalibrateParams, sample: U256) {
+        let smoothed = match self.calibrated {
+            true => calibrate::smooth(self.gas_price, sample, params.smoothing_percent),
+            false => sample,
+        };
+
+        self.gas_price = calibrate::cap(smoothed, params.min_gas_price, params.max_gas_price);
+        self.calibrated = true;
+        info!(target: "pricer",
+            "Gas price sample: {}, calibrated gas price: {}", sample, self.gas_price);
+    }
 }
 
-impl traits::PriceService for PriceService {
+impl<E> traits::PriceService for PriceService<E>
+where
+    E: EthereumService,
+{
     type PricerError = Error;
 
     fn gas_price(&self) -> Box<Future<Item = U256, Error = Self::PricerError> + Send> {
@@ -111,22 +204,72 @@ impl traits::PriceService for PriceService {
         &self,
         token: Address,
     ) -> Box<Future<Item = U256, Error = Self::PricerError> + Send> {
-        Box::new(futures::future::ok(
-            self.token_prices
-                .get(&token)
-                .unwrap_or(&U256::from(100))
-                .clone(),
-        ))
+        if let Some(feed) = self.token_feeds.get(&token) {
+            return feed.token_price();
+        }
+
+        match self.token_prices.get(&token) {
+            Some(price) => Box::new(futures::future::ok(*price)),
+            None => Box::new(futures::future::err(Error::from(
+                ErrorKind::UnknownTokenPrice(token),
+            ))),
+        }
     }
 }
 
-impl Stream for PriceService {
+impl<E> Stream for PriceService<E>
+where
+    E: EthereumService,
+{
     type Item = ();
     type Error = Error;
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let params = match self.mode {
+            PriceServiceMode::Calibrate(ref params) => params.clone(),
+            PriceServiceMode::Fixed { .. } => return Ok(Async::NotReady),
+        };
+
         loop {
-            return Ok(Async::NotReady);
+            let ticked = match self.ticker {
+                Some(ref mut ticker) => match ticker.poll() {
+                    Ok(Async::Ready(Some(_))) => true,
+                    Ok(Async::Ready(None)) | Ok(Async::NotReady) => false,
+                    Err(err) => {
+                        warn!(target: "pricer", "Gas price ticker error: {}", err);
+                        false
+                    }
+                },
+                None => false,
+            };
+
+            if !ticked {
+                break;
+            }
+
+            // skip this tick if last sampling is not finished
+            if self.sampler.lock().is_none() {
+                let sampler = self.new_sampler(params.percentile);
+                *self.sampler.lock() = Some(sampler);
+            }
+        }
+
+        let sample = match *self.sampler.lock() {
+            Some(ref mut sampler) => match sampler.poll() {
+                Ok(Async::Ready(sample)) => Some(Some(sample)),
+                Ok(Async::NotReady) => None,
+                Err(_) => Some(None),
+            },
+            None => None,
+        };
+
+        if let Some(sample) = sample {
+            *self.sampler.lock() = None;
+            if let Some(sample) = sample {
+                self.update_gas_price(&params, sample);
+            }
         }
+
+        Ok(Async::NotReady)
     }
 }
@@ -0,0 +1,121 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::{Address, U256};
+use futures::Future;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+use contract_abi::{ExchangePairAbiDecoder, ExchangePairAbiEncoder};
+use traits::EthereumService;
+
+use super::{reserve_price, Error, ErrorKind, TokenPriceFuture, TokenPriceSource};
+
+/// Computes token price from reserves of a token/WETH pair contract with Uniswap V2 interface
+pub struct ExchangePriceSource<E>
+where
+    E: EthereumService,
+{
+    pair: Address,
+    weth: Address,
+    ethereum: Arc<Mutex<E>>,
+}
+
+impl<E> ExchangePriceSource<E>
+where
+    E: EthereumService,
+{
+    pub fn new(pair: Address, weth: Address, ethereum: Arc<Mutex<E>>) -> ExchangePriceSource<E> {
+        ExchangePriceSource {
+            pair,
+            weth,
+            ethereum,
+        }
+    }
+}
+
+impl<E> TokenPriceSource for ExchangePriceSource<E>
+where
+    E: EthereumService + 'static,
+{
+    fn token_price(&self, token: Address) -> TokenPriceFuture {
+        let (pair, weth) = (self.pair, self.weth);
+        let (token0, token1, reserves) = {
+            let ethereum = self.ethereum.lock();
+            (
+                ethereum.call_contract(pair, ExchangePairAbiEncoder::token0()),
+                ethereum.call_contract(pair, ExchangePairAbiEncoder::token1()),
+                ethereum.call_contract(pair, ExchangePairAbiEncoder::get_reserves()),
+            )
+        };
+
+        Box::new(
+            token0
+                .join3(token1, reserves)
+                .map_err(|err| Error::from(ErrorKind::Ethereum(err.to_string())))
+                .and_then(move |(token0, token1, reserves)| {
+                    let tokens = (
+                        ExchangePairAbiDecoder::token0(&token0)?,
+                        ExchangePairAbiDecoder::token1(&token1)?,
+                    );
+                    let reserves = ExchangePairAbiDecoder::get_reserves(&reserves)?;
+
+                    let (token_reserve, ether_reserve) =
+                        pair_reserves(&token, &weth, tokens, reserves).ok_or_else(|| {
+                            Error::from(ErrorKind::InvalidExchangePair(pair, token))
+                        })?;
+                    reserve_price(token_reserve, ether_reserve)
+                        .ok_or_else(|| Error::from(ErrorKind::EmptyReserve(pair)))
+                }),
+        )
+    }
+}
+
+/// Returns (token reserve, WETH reserve) of a pair, returns `None` if the pair does not consist
+/// of the token and WETH
+fn pair_reserves(
+    token: &Address,
+    weth: &Address,
+    tokens: (Address, Address),
+    reserves: (U256, U256),
+) -> Option<(U256, U256)> {
+    match tokens {
+        (token0, token1) if token0 == *token && token1 == *weth => Some(reserves),
+        (token0, token1) if token0 == *weth && token1 == *token => Some((reserves.1, reserves.0)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pair_reserves() {
+        let (token, weth, other) = (Address::from(1), Address::from(2), Address::from(3));
+        let reserves = (U256::from(10), U256::from(20));
+
+        assert_eq!(
+            pair_reserves(&token, &weth, (token, weth), reserves),
+            Some(reserves)
+        );
+        assert_eq!(
+            pair_reserves(&token, &weth, (weth, token), reserves),
+            Some((U256::from(20), U256::from(10)))
+        );
+        assert_eq!(pair_reserves(&token, &weth, (token, other), reserves), None);
+        assert_eq!(pair_reserves(&token, &weth, (other, weth), reserves), None);
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::{clean_0x, Address, U256};
+use futures::future;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use super::{Error, ErrorKind, TokenPriceFuture, TokenPriceSource};
+
+/// Reads token prices from a TOML or JSON file like `{ "0x1234...": "1000000000" }`, prices are
+/// decimal strings in wei per 10^18 token units. The file is reloaded once it is modified.
+pub struct FilePriceSource {
+    path: PathBuf,
+    prices: Mutex<(Option<SystemTime>, HashMap<Address, U256>)>,
+}
+
+impl FilePriceSource {
+    pub fn new(path: PathBuf) -> FilePriceSource {
+        FilePriceSource {
+            path,
+            prices: Mutex::new((None, HashMap::new())),
+        }
+    }
+
+    fn price_of(&self, token: &Address) -> Result<U256, Error> {
+        let modified = fs::metadata(&self.path)?.modified()?;
+
+        let mut prices = self.prices.lock();
+        if prices.0 != Some(modified) {
+            let content = fs::read_to_string(&self.path)?;
+            *prices = (Some(modified), parse_price_file(&self.path, &content)?);
+            info!(target: "pricer",
+                "Load {} token price(s) from {:?}", prices.1.len(), self.path);
+        }
+
+        match prices.1.get(token) {
+            Some(price) => Ok(*price),
+            None => Err(Error::from(ErrorKind::UnknownTokenPrice(*token))),
+        }
+    }
+}
+
+impl TokenPriceSource for FilePriceSource {
+    fn token_price(&self, token: Address) -> TokenPriceFuture {
+        Box::new(future::result(self.price_of(&token)))
+    }
+}
+
+fn parse_price_file(path: &Path, content: &str) -> Result<HashMap<Address, U256>, Error> {
+    let prices: HashMap<String, String> = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str(content)?,
+        _ => toml::from_str(content)?,
+    };
+
+    prices
+        .into_iter()
+        .map(|(token, price)| {
+            let address = Address::from_str(clean_0x(&token)).map_err(|_| {
+                Error::from(ErrorKind::InvalidPriceFile(format!(
+                    "invalid token address {}",
+                    token
+                )))
+            })?;
+            let price = U256::from_dec_str(&price)
+                .map_err(|_| Error::from(ErrorKind::InvalidPrice(price.clone())))?;
+            Ok((address, price))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_price_file() {
+        let token = Address::from("3830f7aF866fae79e4f6b277be17593bf96bee3b");
+
+        let content = r#"{ "0x3830f7aF866fae79e4f6b277be17593bf96bee3b": "1000" }"#;
+        let prices = parse_price_file(Path::new("prices.json"), content).unwrap();
+        assert_eq!(prices.get(&token), Some(&U256::from(1000)));
+
+        let content = r#""0x3830f7aF866fae79e4f6b277be17593bf96bee3b" = "2000""#;
+        let prices = parse_price_file(Path::new("prices.toml"), content).unwrap();
+        assert_eq!(prices.get(&token), Some(&U256::from(2000)));
+
+        let content = r#""0x3830f7aF866fae79e4f6b277be17593bf96bee3b" = "abc""#;
+        assert!(parse_price_file(Path::new("prices.toml"), content).is_err());
+    }
+}
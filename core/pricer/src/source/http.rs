@@ -0,0 +1,162 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::Address;
+use futures::{Future, Stream};
+use hyper::client::HttpConnector;
+use hyper::{Body, Client, Uri};
+use hyper_rustls::HttpsConnector;
+use serde_json::Value as JsonValue;
+use std::str::FromStr;
+
+use super::{ether_price_to_token_price, Error, ErrorKind, TokenPriceFuture, TokenPriceSource};
+
+// threads resolving host names of price endpoints
+const DNS_THREADS: usize = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+enum JsonPathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Fetches token price in ether per token from a JSON endpoint
+pub struct HttpPriceSource {
+    url: Uri,
+    json_path: Vec<JsonPathSegment>,
+    decimals: u8,
+    client: Client<HttpsConnector<HttpConnector>, Body>,
+}
+
+impl HttpPriceSource {
+    pub fn new(url: String, json_path: String, decimals: u8) -> Result<HttpPriceSource, Error> {
+        let url = Uri::from_str(&url).map_err(|_| Error::from(ErrorKind::InvalidPriceUrl(url)))?;
+        let json_path = parse_json_path(&json_path)?;
+
+        Ok(HttpPriceSource {
+            url,
+            json_path,
+            decimals,
+            // the connector accepts both http and https URLs
+            client: Client::builder()
+                .keep_alive(true)
+                .build(HttpsConnector::new(DNS_THREADS)),
+        })
+    }
+}
+
+impl TokenPriceSource for HttpPriceSource {
+    fn token_price(&self, _token: Address) -> TokenPriceFuture {
+        let json_path = self.json_path.clone();
+        let decimals = self.decimals;
+
+        Box::new(
+            self.client
+                .get(self.url.clone())
+                .and_then(|res| res.into_body().concat2())
+                .from_err::<Error>()
+                .and_then(move |data| {
+                    let value: JsonValue = serde_json::from_slice(&data)?;
+                    match select(&value, &json_path) {
+                        Some(JsonValue::String(price)) => {
+                            ether_price_to_token_price(price, decimals)
+                        }
+                        Some(JsonValue::Number(price)) => {
+                            ether_price_to_token_price(&price.to_string(), decimals)
+                        }
+                        _ => Err(Error::from(ErrorKind::InvalidPrice(value.to_string()))),
+                    }
+                }),
+        )
+    }
+}
+
+/// Parses a JSONPath of child keys and array indexes, e.g. `$.data[0].price` or `$['price']`
+fn parse_json_path(json_path: &str) -> Result<Vec<JsonPathSegment>, Error> {
+    let invalid_path = || Error::from(ErrorKind::InvalidJsonPath(json_path.to_owned()));
+
+    let mut rest = json_path.trim();
+    if rest.starts_with('$') {
+        rest = &rest[1..];
+    }
+
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        if rest.starts_with('.') {
+            let end = rest[1..]
+                .find(|c| c == '.' || c == '[')
+                .map(|end| end + 1)
+                .unwrap_or_else(|| rest.len());
+            let key = &rest[1..end];
+            if key.is_empty() {
+                return Err(invalid_path());
+            }
+            segments.push(JsonPathSegment::Key(key.to_owned()));
+            rest = &rest[end..];
+        } else if rest.starts_with('[') {
+            let end = rest.find(']').ok_or_else(invalid_path)?;
+            let inner = rest[1..end].trim();
+            let quoted = (inner.starts_with('\'') && inner.ends_with('\''))
+                || (inner.starts_with('"') && inner.ends_with('"'));
+            if quoted && inner.len() >= 2 {
+                segments.push(JsonPathSegment::Key(inner[1..inner.len() - 1].to_owned()));
+            } else {
+                let index = inner.parse().map_err(|_| invalid_path())?;
+                segments.push(JsonPathSegment::Index(index));
+            }
+            rest = &rest[end + 1..];
+        } else {
+            return Err(invalid_path());
+        }
+    }
+
+    Ok(segments)
+}
+
+fn select<'a>(value: &'a JsonValue, json_path: &[JsonPathSegment]) -> Option<&'a JsonValue> {
+    json_path
+        .iter()
+        .fold(Some(value), |value, segment| match (value, segment) {
+            (Some(value), JsonPathSegment::Key(key)) => value.get(key.as_str()),
+            (Some(value), JsonPathSegment::Index(index)) => value.get(*index),
+            (None, _) => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_path() {
+        let value: JsonValue =
+            serde_json::from_str(r#"{ "data": [{ "price": "0.0015" }], "eth": 0.5 }"#).unwrap();
+
+        let json_path = parse_json_path("$.data[0].price").unwrap();
+        assert_eq!(
+            select(&value, &json_path),
+            Some(&JsonValue::String("0.0015".to_owned()))
+        );
+
+        let json_path = parse_json_path("$['eth']").unwrap();
+        assert_eq!(select(&value, &json_path), value.get("eth"));
+
+        let json_path = parse_json_path("$.data[1].price").unwrap();
+        assert_eq!(select(&value, &json_path), None);
+
+        assert!(parse_json_path("$.data[x]").is_err());
+        assert!(parse_json_path("$..price").is_err());
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+mod exchange;
+mod file;
+mod http;
+
+use ethereum_types::{Address, U256};
+use futures::{future, Future};
+use parking_lot::Mutex;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use traits::EthereumService;
+
+use super::error::{Error, ErrorKind};
+use super::ETHER;
+
+pub use self::exchange::ExchangePriceSource;
+pub use self::file::FilePriceSource;
+pub use self::http::HttpPriceSource;
+
+pub type TokenPriceFuture = Box<Future<Item = U256, Error = Error> + Send>;
+
+/// Source of token prices, a token price is the amount of wei per 10^18 token units
+pub trait TokenPriceSource: Send + Sync {
+    fn token_price(&self, token: Address) -> TokenPriceFuture;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TokenPriceSourceParams {
+    /// TOML or JSON file mapping token addresses to prices, reloaded when modified
+    File(PathBuf),
+
+    /// JSON endpoint, the price in ether per token is selected by JSONPath
+    Http {
+        url: String,
+        json_path: String,
+        decimals: u8,
+    },
+
+    /// Reserves of token/WETH pair contract of a decentralized exchange
+    Exchange { pair: Address, weth: Address },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenPriceFeedParams {
+    pub token: Address,
+    pub source: TokenPriceSourceParams,
+
+    // how long a fetched price is used before fetching a new one
+    pub max_age: Duration,
+}
+
+/// Token price source with the latest fetched price
+pub struct TokenPriceFeed {
+    token: Address,
+    source: Box<TokenPriceSource>,
+    max_age: Duration,
+    latest: Arc<Mutex<Option<(U256, Instant)>>>,
+}
+
+impl TokenPriceFeed {
+    pub fn new<E>(params: TokenPriceFeedParams, ethereum: Arc<Mutex<E>>) -> Result<Self, Error>
+    where
+        E: EthereumService + 'static,
+    {
+        let source: Box<TokenPriceSource> = match params.source {
+            TokenPriceSourceParams::File(path) => Box::new(FilePriceSource::new(path)),
+            TokenPriceSourceParams::Http {
+                url,
+                json_path,
+                decimals,
+            } => Box::new(HttpPriceSource::new(url, json_path, decimals)?),
+            TokenPriceSourceParams::Exchange { pair, weth } => {
+                Box::new(ExchangePriceSource::new(pair, weth, ethereum))
+            }
+        };
+
+        Ok(TokenPriceFeed {
+            token: params.token,
+            source,
+            max_age: params.max_age,
+            latest: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Returns the latest price if it is not older than `max_age`, otherwise fetches a new one
+    pub fn token_price(&self) -> TokenPriceFuture {
+        if let Some((price, fetched_at)) = *self.latest.lock() {
+            if fetched_at.elapsed() <= self.max_age {
+                return Box::new(future::ok(price));
+            }
+        }
+
+        let token = self.token;
+        let latest = self.latest.clone();
+        Box::new(
+            self.source
+                .token_price(token)
+                .then(move |result| match result {
+                    Ok(price) => {
+                        *latest.lock() = Some((price, Instant::now()));
+                        Ok(price)
+                    }
+                    Err(err) => {
+                        warn!(target: "pricer",
+                            "Failed to fetch price of token {:?}, error: {}", token, err);
+                        Err(Error::from(ErrorKind::StaleTokenPrice(token)))
+                    }
+                }),
+        )
+    }
+}
+
+/// Converts a price in ether per token into wei per 10^18 token units
+pub fn ether_price_to_token_price(ether_price: &str, decimals: u8) -> Result<U256, Error> {
+    let invalid_price = || Error::from(ErrorKind::InvalidPrice(ether_price.to_owned()));
+
+    // the mantissa and the exponent of a decimal number, e.g. "1.5e-3"
+    let lowercase = ether_price.trim().to_lowercase();
+    let mut parts = lowercase.splitn(2, 'e');
+    let mantissa = parts.next().unwrap_or("");
+    let exponent: i64 = match parts.next() {
+        Some(exponent) => exponent.parse().map_err(|_| invalid_price())?,
+        None => 0,
+    };
+
+    let mut parts = mantissa.splitn(2, '.');
+    let integer = parts.next().unwrap_or("");
+    let fraction = parts.next().unwrap_or("");
+    let digits = format!("{}{}", integer, fraction);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid_price());
+    }
+
+    // wei per 10^18 token units is ether per token * 10^18 * 10^(18 - decimals)
+    let shift = exponent
+        .checked_add(36 - decimals as i64)
+        .and_then(|shift| shift.checked_sub(fraction.len() as i64))
+        .ok_or_else(invalid_price)?;
+    let value = U256::from_dec_str(&digits).map_err(|_| invalid_price())?;
+    let ten = U256::from(10);
+    // 10^78 overflows U256
+    if shift >= 78 {
+        Err(invalid_price())
+    } else if shift >= 0 {
+        value
+            .checked_mul(ten.pow(U256::from(shift)))
+            .ok_or_else(invalid_price)
+    } else if shift > -78 {
+        Ok(value / ten.pow(U256::from(-shift)))
+    } else {
+        Ok(U256::zero())
+    }
+}
+
+/// Computes token price from reserves of a token/WETH pair
+pub fn reserve_price(token_reserve: U256, ether_reserve: U256) -> Option<U256> {
+    if token_reserve.is_zero() {
+        return None;
+    }
+
+    Some(ether_reserve * *ETHER / token_reserve)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ether_price_to_token_price() {
+        let price = ether_price_to_token_price("0.0015", 18).unwrap();
+        assert_eq!(price, U256::from(15) * U256::from(10).pow(U256::from(14)));
+
+        let price = ether_price_to_token_price("1.5e-3", 18).unwrap();
+        assert_eq!(price, U256::from(15) * U256::from(10).pow(U256::from(14)));
+
+        let price = ether_price_to_token_price("2", 6).unwrap();
+        assert_eq!(price, U256::from(2) * U256::from(10).pow(U256::from(30)));
+
+        assert!(ether_price_to_token_price("abc", 18).is_err());
+        assert!(ether_price_to_token_price("", 18).is_err());
+    }
+
+    #[test]
+    fn test_ether_price_with_large_exponent() {
+        assert!(ether_price_to_token_price("1e100", 18).is_err());
+        assert!(ether_price_to_token_price("1e60", 18).is_err());
+        assert!(ether_price_to_token_price("1e9223372036854775807", 0).is_err());
+        assert!(ether_price_to_token_price("1e99999999999999999999", 18).is_err());
+
+        let price = ether_price_to_token_price("1e59", 18).unwrap();
+        assert_eq!(price, U256::from(10).pow(U256::from(77)));
+
+        let price = ether_price_to_token_price("1e-100", 18).unwrap();
+        assert_eq!(price, U256::zero());
+        let price = ether_price_to_token_price("1e-9223372036854775808", 18).unwrap();
+        assert_eq!(price, U256::zero());
+    }
+
+    #[test]
+    fn test_reserve_price() {
+        let price = reserve_price(U256::from(1000), U256::from(2));
+        assert_eq!(price, Some(U256::from(2) * *ETHER / U256::from(1000)));
+        assert_eq!(reserve_price(U256::zero(), U256::from(2)), None);
+    }
+}
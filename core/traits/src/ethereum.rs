@@ -20,7 +20,7 @@ use futures::{sync::mpsc, Future, Stream};
 
 use types::{
     AccountState, BlockId, Currency, EthRpcTransaction, EthRpcTransactionReceipt,
-    EthTransactionConfirmation, GasEstimation,
+    EthTransactionConfirmation, EthereumEndpointState, GasEstimation,
 };
 
 pub type AccountStateFuture<Error> = Box<Future<Item = AccountState, Error = Error> + Send>;
@@ -28,6 +28,8 @@ pub type BytesFuture<Error> = Box<Future<Item = Vec<u8>, Error = Error> + Send>;
 pub type BoolFuture<Error> = Box<Future<Item = bool, Error = Error> + Send>;
 pub type U256Future<Error> = Box<Future<Item = U256, Error = Error> + Send>;
 pub type H256Future<Error> = Box<Future<Item = H256, Error = Error> + Send>;
+pub type StringFuture<Error> = Box<Future<Item = String, Error = Error> + Send>;
+pub type U256VecFuture<Error> = Box<Future<Item = Vec<U256>, Error = Error> + Send>;
 pub type EthRpcTransactionFuture<Error> =
     Box<Future<Item = Option<EthRpcTransaction>, Error = Error> + Send>;
 pub type EthRpcTransactionReceiptFuture<Error> =
@@ -37,6 +39,9 @@ pub type EthRpcTransactionConfirmationFuture<Error> =
 
 pub trait BlockInfoProvider<Error>: Send + Sync {
     fn get_block_number(&self) -> U256Future<Error>;
+
+    /// Returns hash of the block with given number on the canonical chain
+    fn get_block_hash(&self, block_number: U256) -> H256Future<Error>;
 }
 
 pub trait TransactionFetcher<Error>: Send + Sync {
@@ -55,6 +60,12 @@ pub trait TransactionBroadcaster<Error>: Send + Sync {
 pub trait GasEstimator<Error>: Send + Sync {
     fn block_gas_limit(&self, block_id: BlockId) -> U256Future<Error>;
     fn estimate_gas(&self, gas_estimate: GasEstimation) -> U256Future<Error>;
+
+    /// Returns gas price suggested by Ethereum node
+    fn gas_price(&self) -> U256Future<Error>;
+
+    /// Returns gas prices of all transactions in a block
+    fn block_gas_prices(&self, block_id: BlockId) -> U256VecFuture<Error>;
 }
 
 pub trait AccountStateProvider<Error>: Send + Sync {
@@ -62,10 +73,17 @@ pub trait AccountStateProvider<Error>: Send + Sync {
     fn nonce_of(&self, account: Address, currency: Currency) -> U256Future<Error>;
     fn state_of(&self, account: Address, currency: Currency) -> AccountStateFuture<Error>;
     fn code_of(&self, account: Address) -> BytesFuture<Error>;
+
+    /// Calls a constant function of contract and returns its output
+    fn call_contract(&self, contract: Address, data: Vec<u8>) -> BytesFuture<Error>;
 }
 
 pub trait TokenStateProvider<Error>: Send + Sync {
     fn token_delegate_enable(&self, token_contract: Address) -> BoolFuture<Error>;
+
+    fn token_symbol(&self, token_contract: Address) -> StringFuture<Error>;
+
+    fn token_decimals(&self, token_contract: Address) -> U256Future<Error>;
 }
 
 pub trait EthereumService:
@@ -93,6 +111,9 @@ pub trait EthereumService:
 
     /// Ruturns current endpoints count used by Ethereum Service
     fn endpoint_count(&self) -> usize;
+
+    /// Returns health state of each endpoint
+    fn endpoint_states(&self) -> Vec<EthereumEndpointState>;
 }
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
@@ -106,6 +127,8 @@ pub enum EthereumMonitorResponse {
     BlockHash(H256),
     BlockNumber(U256),
     Transaction(H256),
+    // transaction is removed from canonical chain by a chain reorganization
+    TransactionReorged(H256),
     Request(H256),
 }
 
@@ -27,7 +27,8 @@ mod pricer;
 
 pub use self::ethereum::{
     AccountStateFuture, BoolFuture, BytesFuture, EthRpcTransactionConfirmationFuture,
-    EthRpcTransactionFuture, EthRpcTransactionReceiptFuture, H256Future, U256Future,
+    EthRpcTransactionFuture, EthRpcTransactionReceiptFuture, H256Future, StringFuture,
+    U256Future, U256VecFuture,
 };
 pub use self::ethereum::{
     AccountStateProvider, BlockInfoProvider, EthereumMonitor, EthereumMonitorResponse,
@@ -13,11 +13,13 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
-use ethereum_types::Address;
+use ethereum_types::{Address, H256};
 use futures::{Future, Stream};
 use std::sync::Arc;
 use std::time::Duration;
 
+use types::{CollationFilter, CollationPage, CollationRecord, CollationRecordList};
+
 pub trait MachineService: Sync + Send + Stream {
     type MachineError: ::std::error::Error + Send + 'static + ToString;
     type MachineParams;
@@ -25,6 +27,7 @@ pub trait MachineService: Sync + Send + Stream {
     type SignedRequest;
     type RelayerMode;
     type RelayerInfo;
+    type KeyPair;
 
     /// Starts relayer machine service
     fn start(&mut self) -> bool;
@@ -54,9 +57,16 @@ pub trait MachineService: Sync + Send + Stream {
         params: Self::MachineParams,
     ) -> Result<Option<Self::RelayerInfo>, Self::MachineError>;
 
-    /// Removes a relayer from relayer service machine
+    /// Removes a relayer from relayer service machine, a relayer with in-flight collation is
+    /// removed after the collation is finished
     fn remove_relayer(&mut self, address: &Address) -> Result<(), Self::MachineError>;
 
+    /// Returns parameters of a new relayer account with given key pair
+    fn relayer_params(&self, keypair: Self::KeyPair) -> Self::MachineParams;
+
+    /// Returns mode of relayers created on start
+    fn default_relayer_mode(&self) -> Self::RelayerMode;
+
     fn contains_relayer(&self, address: &Address) -> bool;
 
     fn relayer_mode(&self, relayer_address: &Address) -> Option<Self::RelayerMode>;
@@ -76,4 +86,14 @@ pub trait MachineService: Sync + Send + Stream {
     fn relayer_info(&self, relayer_address: &Address) -> Option<Self::RelayerInfo>;
 
     fn dispatcher_contracts(&self) -> Vec<Address>;
+
+    /// Returns the collation record which contains a transaction with given hash
+    fn collation(&self, transaction_hash: &H256) -> Option<CollationRecord>;
+
+    /// Returns a page of collation records matching the filter, from the newest to the oldest
+    fn collation_history(
+        &self,
+        filter: &CollationFilter,
+        page: &CollationPage,
+    ) -> CollationRecordList;
 }
@@ -14,10 +14,13 @@
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 use ethereum_types::{Address, U256};
+use futures::sync::mpsc;
 use futures::{Future, Stream};
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use types::{RequestReceipt, RequestStatus};
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum PoolRequestTag {
     Invalid,
@@ -47,6 +50,8 @@ pub trait PoolService: Sync + Send + Stream {
 
     fn all_requests(&self) -> Vec<Arc<Self::SignedRequest>>;
 
+    fn future_requests(&self) -> Vec<Arc<Self::SignedRequest>>;
+
     fn ready_requests(
         &mut self,
         new_tag: Option<PoolRequestTag>,
@@ -59,6 +64,33 @@ pub trait PoolService: Sync + Send + Stream {
 
     fn mark_by_hashes(&mut self, hash: &[Self::Hash], tag: PoolRequestTag);
 
+    /// Marks a request as invalid with the reason why it is invalid
+    fn mark_invalid(&mut self, hash: &Self::Hash, reason: String);
+
+    /// Returns the reason why a request is marked as invalid
+    fn invalid_reason(&self, hash: &Self::Hash) -> Option<String>;
+
+    /// Records that requests are broadcasted by relayer in the transaction with given hash
+    fn mark_broadcasted(
+        &mut self,
+        hashes: &[Self::Hash],
+        relayer: &Self::Address,
+        tx_hash: &Self::Hash,
+    );
+
+    /// Records the number of block which contains the transaction of requests
+    fn mark_mined(&mut self, hashes: &[Self::Hash], block_number: U256);
+
+    /// Returns receipt of a request, receipts are kept for a while after requests leave the pool
+    fn request_receipt(&self, hash: &Self::Hash) -> Option<RequestReceipt>;
+
+    /// Returns a stream of status changes of a request, the current status is sent first and
+    /// the stream ends after a terminal status or once the request leaves the pool
+    fn watch_request(
+        &mut self,
+        hash: &Self::Hash,
+    ) -> Option<mpsc::UnboundedReceiver<RequestStatus>>;
+
     fn remove_by_hash(&mut self, hash: &Self::Hash) -> Option<Arc<Self::SignedRequest>>;
 
     fn remove_by_hashes(&mut self, hash: &[Self::Hash]) -> Vec<Arc<Self::SignedRequest>>;
@@ -98,5 +130,8 @@ pub trait PoolService: Sync + Send + Stream {
 
     fn set_relayers(&mut self, relayers: Vec<Address>);
 
+    /// Returns addresses of relayers which execute requests in pool
+    fn relayers(&self) -> Vec<Address>;
+
     fn set_dispatcher(&mut self, dispatcher: Address);
 }
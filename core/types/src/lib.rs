@@ -42,11 +42,18 @@ pub use self::ethereum_rpc::{
     EthRpcBytes, EthRpcCallRequest, EthRpcH160, EthRpcH256, EthRpcH512, EthRpcTransaction,
     EthRpcTransactionReceipt, EthRpcU128, EthRpcU256, EthRpcU64,
 };
-pub use self::relayer_rpc::{RelayerRpcRequest, RelayerRpcToken};
+pub use self::relayer_rpc::{
+    RelayerRpcFeeQuote, RelayerRpcImportError, RelayerRpcImportResult, RelayerRpcRequest,
+    RelayerRpcToken,
+};
 pub use self::request::{
     signature, DelegateMode, Request, RequestError, SignedRequest, UnverifiedRequest,
 };
-pub use self::state::AccountState;
+pub use self::state::{
+    AccountState, CollationFilter, CollationOutcome, CollationPage, CollationRecord,
+    CollationRecordList, CollationRequestFailure, EthereumEndpointState, RequestReceipt,
+    RequestState, RequestStatus,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BlockId {
@@ -0,0 +1,41 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::{Address, U256};
+
+/// Quote of token fee for a token transfer request which is not signed yet
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FeeQuote {
+    pub token: Address,
+
+    #[serde(rename = "gasAmount")]
+    // adjusted gas amount of relaying the request in a transaction of its own
+    pub gas_amount: U256,
+
+    #[serde(rename = "gasPrice")]
+    pub gas_price: U256,
+
+    #[serde(rename = "recommendedFee")]
+    // fee in token units which covers the request relayed in a transaction of its own
+    pub recommended_fee: U256,
+
+    #[serde(rename = "minimumFee")]
+    // fee in token units which only covers the request relayed in a batch
+    pub minimum_fee: U256,
+
+    #[serde(rename = "validUntil")]
+    // unix timestamp in seconds, gas price and token price may change after it
+    pub valid_until: u64,
+}
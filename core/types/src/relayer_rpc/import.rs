@@ -0,0 +1,46 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::H256;
+
+/// Result of importing a token transfer request of a batch
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ImportResult {
+    // hash of the request, it is absent if the request can not be decoded
+    pub hash: Option<H256>,
+    pub error: Option<ImportError>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ImportError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl ImportResult {
+    pub fn imported(hash: H256) -> ImportResult {
+        ImportResult {
+            hash: Some(hash),
+            error: None,
+        }
+    }
+
+    pub fn failed(hash: Option<H256>, code: i64, message: String) -> ImportResult {
+        ImportResult {
+            hash,
+            error: Some(ImportError { code, message }),
+        }
+    }
+}
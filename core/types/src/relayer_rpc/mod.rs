@@ -14,11 +14,17 @@
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 pub mod error;
+mod fee;
+mod import;
 mod request;
 mod token;
 
 use super::{signature, DelegateMode, EthRpcBytes, Request, RequestError, SignedRequest};
 
 pub use self::error::{Error as RelayerRpcError, ErrorKind as RelayerRpcErrorKind};
+pub use self::fee::FeeQuote as RelayerRpcFeeQuote;
+pub use self::import::{
+    ImportError as RelayerRpcImportError, ImportResult as RelayerRpcImportResult,
+};
 pub use self::request::Request as RelayerRpcRequest;
 pub use self::token::Token as RelayerRpcToken;
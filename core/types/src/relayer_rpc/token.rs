@@ -13,11 +13,20 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
-use ethereum_types::Address;
+use ethereum_types::{Address, U256};
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Token {
-    name: String,
-    symbol: String,
-    address: Address,
+    pub address: Address,
+
+    // metadata fields are absent if they can not be fetched from chain
+    pub symbol: Option<String>,
+    pub decimals: Option<U256>,
+
+    #[serde(rename = "isDelegateEnable")]
+    pub is_delegate_enable: Option<bool>,
+
+    #[serde(rename = "feePrice")]
+    // amount of token units charged per gas, it is absent if token price is unknown
+    pub fee_price: Option<U256>,
 }
@@ -0,0 +1,164 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::{Address, H256, U256};
+
+/// Final outcome of a broadcasted collation transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CollationOutcome {
+    // transaction is broadcasted but not confirmed yet
+    #[serde(rename = "pending")]
+    Pending,
+
+    // transaction is mined and at least one request is executed
+    #[serde(rename = "executed")]
+    Executed,
+
+    // transaction is mined but none of requests is executed
+    #[serde(rename = "failed")]
+    Failed,
+
+    // transaction is dropped and can not be broadcasted again
+    #[serde(rename = "dropped")]
+    Dropped,
+
+    // relayer is stopped before the outcome is resolved
+    #[serde(rename = "unknown")]
+    Unknown,
+}
+
+/// A request which is not executed in a mined collation transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollationRequestFailure {
+    pub hash: H256,
+
+    pub reason: String,
+}
+
+/// Record of a broadcasted collation transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollationRecord {
+    #[serde(rename = "transactionHash")]
+    // hash of the latest broadcasted transaction
+    pub transaction_hash: H256,
+
+    #[serde(rename = "transactionHashes")]
+    // hashes of all transactions broadcasted for this collation, the latest one is the last
+    pub transaction_hashes: Vec<H256>,
+
+    pub relayer: Address,
+
+    pub nonce: U256,
+
+    #[serde(rename = "gasPrice")]
+    pub gas_price: U256,
+
+    #[serde(rename = "gasLimit")]
+    pub gas_limit: U256,
+
+    #[serde(rename = "gasUsed")]
+    pub gas_used: Option<U256>,
+
+    #[serde(rename = "blockNumber")]
+    // number of the block which contains the transaction
+    pub block_number: Option<U256>,
+
+    #[serde(rename = "requestHashes")]
+    pub request_hashes: Vec<H256>,
+
+    #[serde(rename = "failedRequests")]
+    pub failed_requests: Vec<CollationRequestFailure>,
+
+    pub outcome: CollationOutcome,
+
+    // reason why the transaction is dropped
+    pub reason: Option<String>,
+
+    #[serde(rename = "broadcastedAt")]
+    // unix timestamp in seconds when the first transaction is broadcasted
+    pub broadcasted_at: u64,
+
+    #[serde(rename = "updatedAt")]
+    // unix timestamp in seconds of the latest change
+    pub updated_at: u64,
+
+    #[serde(rename = "finishedAt")]
+    // unix timestamp in seconds when the outcome is resolved
+    pub finished_at: Option<u64>,
+}
+
+impl CollationRecord {
+    /// Returns `true` if the record satisfies all conditions of the filter
+    pub fn matches(&self, filter: &CollationFilter) -> bool {
+        filter.relayer.map_or(true, |relayer| self.relayer == relayer)
+            && filter.outcome.map_or(true, |outcome| self.outcome == outcome)
+            && filter
+                .from_time
+                .map_or(true, |time| self.broadcasted_at >= time)
+            && filter.to_time.map_or(true, |time| self.broadcasted_at <= time)
+            && filter
+                .request_hash
+                .map_or(true, |hash| self.request_hashes.contains(&hash))
+    }
+}
+
+/// Conditions of querying collation records, a missing condition matches any record
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CollationFilter {
+    pub relayer: Option<Address>,
+
+    pub outcome: Option<CollationOutcome>,
+
+    #[serde(rename = "fromTime")]
+    // unix timestamp in seconds, inclusive
+    pub from_time: Option<u64>,
+
+    #[serde(rename = "toTime")]
+    // unix timestamp in seconds, inclusive
+    pub to_time: Option<u64>,
+
+    #[serde(rename = "requestHash")]
+    // hash of a request which is contained in the collation
+    pub request_hash: Option<H256>,
+}
+
+/// A page of collation records, records are sorted from the newest to the oldest
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CollationPage {
+    pub offset: usize,
+
+    pub limit: usize,
+}
+
+impl Default for CollationPage {
+    fn default() -> CollationPage {
+        CollationPage {
+            offset: 0,
+            limit: 20,
+        }
+    }
+}
+
+/// Collation records of a page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollationRecordList {
+    // count of all matched records
+    pub total: usize,
+
+    pub offset: usize,
+
+    pub records: Vec<CollationRecord>,
+}
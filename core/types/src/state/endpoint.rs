@@ -0,0 +1,53 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::U256;
+
+/// Health state of an Ethereum endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EthereumEndpointState {
+    pub endpoint: String,
+
+    // whether the endpoint is picked to serve requests
+    pub healthy: bool,
+
+    #[serde(rename = "latencyMillis")]
+    // moving average of request latency
+    pub latency_millis: u64,
+
+    #[serde(rename = "errorRatePercent")]
+    // moving average of failed requests in percent
+    pub error_rate_percent: u32,
+
+    #[serde(rename = "requestCount")]
+    // count of requests sent to the endpoint
+    pub request_count: u64,
+
+    #[serde(rename = "errorCount")]
+    // count of failed requests
+    pub error_count: u64,
+
+    #[serde(rename = "blockNumber")]
+    // latest block number reported by the endpoint
+    pub block_number: Option<U256>,
+
+    #[serde(rename = "blockLag")]
+    // how many blocks the endpoint is behind the highest endpoint
+    pub block_lag: u64,
+
+    #[serde(rename = "ejectedSecs")]
+    // remaining seconds before an ejected endpoint is retried
+    pub ejected_secs: Option<u64>,
+}
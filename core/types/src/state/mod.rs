@@ -14,5 +14,16 @@
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 mod account;
+mod collation;
+mod endpoint;
+mod receipt;
+mod request;
 
 pub use self::account::AccountState;
+pub use self::collation::{
+    CollationFilter, CollationOutcome, CollationPage, CollationRecord, CollationRecordList,
+    CollationRequestFailure,
+};
+pub use self::endpoint::EthereumEndpointState;
+pub use self::receipt::{RequestReceipt, RequestState};
+pub use self::request::RequestStatus;
@@ -0,0 +1,61 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::{Address, H256, U256};
+
+/// State of a token transfer request, including states after it leaves the pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RequestState {
+    #[serde(rename = "ready")]
+    Ready,
+
+    #[serde(rename = "processing")]
+    Processing,
+
+    #[serde(rename = "executed")]
+    Executed,
+
+    #[serde(rename = "invalid")]
+    Invalid,
+
+    // removed from pool before it is executed
+    #[serde(rename = "dropped")]
+    Dropped,
+}
+
+/// Receipt of a token transfer request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestReceipt {
+    pub hash: H256,
+
+    pub state: RequestState,
+
+    // reason why the request is invalid or dropped
+    pub reason: Option<String>,
+
+    // address of the relayer which relays the request
+    pub relayer: Address,
+
+    #[serde(rename = "transactionHash")]
+    // hash of the collation transaction which contains the request
+    pub transaction_hash: Option<H256>,
+
+    #[serde(rename = "blockNumber")]
+    // number of the block which contains the collation transaction
+    pub block_number: Option<U256>,
+
+    // count of blocks mined on top of the block which contains the collation transaction
+    pub confirmations: U256,
+}
@@ -0,0 +1,53 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::H256;
+
+/// Status of a token transfer request pushed to subscribers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status")]
+pub enum RequestStatus {
+    #[serde(rename = "ready")]
+    Ready,
+
+    #[serde(rename = "processing")]
+    Processing,
+
+    #[serde(rename = "broadcasted")]
+    Broadcasted {
+        #[serde(rename = "transactionHash")]
+        // hash of the collation transaction which contains the request
+        transaction_hash: H256,
+    },
+
+    #[serde(rename = "executed")]
+    Executed,
+
+    #[serde(rename = "invalid")]
+    Invalid {
+        // reason why the request is invalid
+        reason: Option<String>,
+    },
+}
+
+impl RequestStatus {
+    /// Returns `true` if the status will not change anymore
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            RequestStatus::Executed | RequestStatus::Invalid { .. } => true,
+            _ => false,
+        }
+    }
+}
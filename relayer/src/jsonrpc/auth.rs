@@ -0,0 +1,451 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::H256;
+use keccak_hash::{keccak, KECCAK_EMPTY};
+use parking_lot::Mutex;
+use rustc_hex::{FromHex, ToHex};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::rpc_apis::{Api, ApiSet};
+use super::{Client, RateLimit, RateLimiter};
+
+// block size of Keccak-256, it is used for padding keys of HMAC
+const KECCAK_BLOCK_SIZE: usize = 136;
+
+// a HMAC signature is accepted within this many seconds of its timestamp
+const HMAC_MAX_SKEW_SECS: u64 = 60;
+
+// login attempts allowed at once per client, logins are refilled at one per second
+const LOGIN_BURST: u32 = 5;
+
+// count of HMAC nonces kept within the skew window, signatures with new nonces are rejected
+// when it is reached
+const MAX_NONCES: usize = 10240;
+
+// max length of HMAC nonces
+const MAX_NONCE_LEN: usize = 64;
+
+/// API key of JSON-RPC clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    // identifier of the key, clients authenticating with HMAC send it
+    pub id: String,
+    // secret of the key, clients authenticating with bearer token send it
+    pub secret: String,
+    // APIs the key is allowed to call
+    pub apis: ApiSet,
+}
+
+/// Credentials carried by a JSON-RPC call, the format is the same as the value of HTTP
+/// `Authorization` header:
+///
+/// * `Bearer <secret>`
+/// * `HMAC <id>:<timestamp>:<nonce>:<signature>`, signature is hex of HMAC-Keccak256 of
+/// `<id>:<timestamp>:<nonce>:<body hash>` signed with the secret, timestamp is in seconds since
+/// Unix epoch, nonce is used only once and body hash is hex of Keccak-256 of the HTTP request
+/// body, or of an empty body for `auth_login`
+#[derive(Debug, Clone)]
+pub enum Credentials {
+    Bearer(String),
+    Hmac {
+        id: String,
+        timestamp: u64,
+        nonce: String,
+        signature: Vec<u8>,
+    },
+}
+
+impl Credentials {
+    pub fn parse(value: &str) -> Option<Credentials> {
+        let mut parts = value.trim().splitn(2, ' ');
+        let scheme = parts.next()?;
+        let value = parts.next()?.trim();
+
+        match scheme.to_lowercase().as_str() {
+            "bearer" => Some(Credentials::Bearer(value.to_owned())),
+            "hmac" => {
+                let mut parts = value.rsplitn(4, ':');
+                let signature = parts.next()?.from_hex().ok()?;
+                let nonce = parts.next()?.to_owned();
+                let timestamp = parts.next()?.parse().ok()?;
+                let id = parts.next()?.to_owned();
+                if nonce.is_empty() || nonce.len() > MAX_NONCE_LEN {
+                    return None;
+                }
+                Some(Credentials::Hmac {
+                    id,
+                    timestamp,
+                    nonce,
+                    signature,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Authenticates credentials with API keys and authorizes calls of protected APIs
+pub struct Authenticator {
+    keys: Vec<ApiKey>,
+    // login attempts of clients, whether they succeed or not
+    login_limiter: RateLimiter<Client>,
+    // timestamps of HMAC nonces which are used, keyed by API key identifier and nonce
+    nonces: Mutex<HashMap<(String, String), u64>>,
+}
+
+impl Authenticator {
+    pub fn new(keys: Vec<ApiKey>) -> Authenticator {
+        Authenticator {
+            keys,
+            login_limiter: RateLimiter::new(&RateLimit {
+                burst: LOGIN_BURST,
+                per_second: 1,
+            }),
+            nonces: Default::default(),
+        }
+    }
+
+    /// Returns `true` if calls of `api` need credentials
+    pub fn is_protected(api: &Api) -> bool {
+        match api {
+            Api::Admin | Api::Relayer => true,
+            _ => false,
+        }
+    }
+
+    /// Returns index of the API key matching `credentials`, `body_hash` is hash of the request
+    /// carrying the credentials and it is required by HMAC
+    fn authenticate(&self, credentials: &Credentials, body_hash: Option<&H256>) -> Option<usize> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs())
+            .unwrap_or(0);
+        self.authenticate_at(credentials, body_hash, now)
+    }
+
+    fn authenticate_at(
+        &self,
+        credentials: &Credentials,
+        body_hash: Option<&H256>,
+        now: u64,
+    ) -> Option<usize> {
+        match credentials {
+            Credentials::Bearer(secret) => self
+                .keys
+                .iter()
+                .position(|key| constant_time_eq(key.secret.as_bytes(), secret.as_bytes())),
+            Credentials::Hmac {
+                id,
+                timestamp,
+                nonce,
+                signature,
+            } => {
+                let skew = match now > *timestamp {
+                    true => now - timestamp,
+                    false => timestamp - now,
+                };
+                if skew > HMAC_MAX_SKEW_SECS {
+                    return None;
+                }
+
+                let body_hash = body_hash?.to_hex();
+                let message = format!("{}:{}:{}:{}", id, timestamp, nonce, body_hash);
+                let index = self.keys.iter().position(|key| {
+                    key.id == *id
+                        && constant_time_eq(
+                            &hmac(key.secret.as_bytes(), message.as_bytes()),
+                            signature,
+                        )
+                })?;
+
+                // a signature is accepted only once within the skew window
+                let mut nonces = self.nonces.lock();
+                nonces.retain(|_, timestamp| *timestamp + HMAC_MAX_SKEW_SECS >= now);
+                let key = (id.clone(), nonce.clone());
+                if nonces.contains_key(&key) || nonces.len() >= MAX_NONCES {
+                    return None;
+                }
+                nonces.insert(key, *timestamp);
+                Some(index)
+            }
+        }
+    }
+
+    /// Returns index of the API key matching `credentials` of a login, the connection of
+    /// `client` keeps the key for its further calls. Login attempts are rate limited per client
+    /// to slow down guessing of secrets.
+    pub fn login(&self, client: Client, credentials: &Credentials) -> Option<usize> {
+        if !self.login_limiter.acquire(client) {
+            return None;
+        }
+
+        self.authenticate(credentials, Some(&KECCAK_EMPTY))
+    }
+
+    /// Returns index of the API key authorizing calls of a request, the key is matched by
+    /// `credentials` of the request or it is the key the connection logged in with.
+    /// Credentials are authenticated only once for all calls of a batch request since HMAC
+    /// nonces are used only once.
+    pub fn authorize(
+        &self,
+        credentials: Option<&Credentials>,
+        body_hash: Option<H256>,
+        login: Option<usize>,
+    ) -> Option<usize> {
+        match credentials {
+            Some(credentials) => self.authenticate(credentials, body_hash.as_ref()),
+            None => login,
+        }
+    }
+
+    /// Returns `true` if the call of `api` is authorized by the API key of index `key`
+    pub fn is_authorized(&self, api: &Api, key: Option<usize>) -> bool {
+        if !Self::is_protected(api) {
+            return true;
+        }
+
+        key.map_or(false, |index| self.keys[index].apis.apis().contains(api))
+    }
+}
+
+/// Returns HMAC-Keccak256 of `message`
+fn hmac(secret: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut key = [0u8; KECCAK_BLOCK_SIZE];
+    match secret.len() > KECCAK_BLOCK_SIZE {
+        true => key[..32].copy_from_slice(&*keccak(secret)),
+        false => key[..secret.len()].copy_from_slice(secret),
+    }
+
+    let mut inner: Vec<u8> = key.iter().map(|byte| byte ^ 0x36).collect();
+    inner.extend_from_slice(message);
+
+    let mut outer: Vec<u8> = key.iter().map(|byte| byte ^ 0x5c).collect();
+    outer.extend_from_slice(&*keccak(&inner));
+
+    keccak(&outer).to_vec()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = r#"{"jsonrpc":"2.0","method":"admin_listRelayers","params":[],"id":1}"#;
+    const TIMESTAMP: u64 = 1_540_000_000;
+
+    fn authenticator() -> Authenticator {
+        let key = |id: &str, secret: &str, apis: &[&str]| ApiKey {
+            id: id.to_owned(),
+            secret: secret.to_owned(),
+            apis: ApiSet::from_strings(apis.iter().map(|api| api.to_string()).collect()),
+        };
+        Authenticator::new(vec![
+            key("admin", "secret", &["admin"]),
+            key("root", "root-secret", &["all"]),
+        ])
+    }
+
+    fn hmac_credentials(id: &str, secret: &str, timestamp: u64, nonce: &str) -> Credentials {
+        let message = format!("{}:{}:{}:{}", id, timestamp, nonce, keccak(BODY).to_hex());
+        let signature = hmac(secret.as_bytes(), message.as_bytes()).to_hex();
+        let value = format!("HMAC {}:{}:{}:{}", id, timestamp, nonce, signature);
+        Credentials::parse(&value).unwrap()
+    }
+
+    #[test]
+    fn test_parse_credentials() {
+        match Credentials::parse("Bearer  change-me ") {
+            Some(Credentials::Bearer(secret)) => assert_eq!(secret, "change-me"),
+            credentials => panic!("unexpected credentials {:?}", credentials),
+        }
+
+        match Credentials::parse("hmac my:key:1540000000:n1:0aff") {
+            Some(Credentials::Hmac {
+                id,
+                timestamp,
+                nonce,
+                signature,
+            }) => {
+                assert_eq!(id, "my:key");
+                assert_eq!(timestamp, TIMESTAMP);
+                assert_eq!(nonce, "n1");
+                assert_eq!(signature, vec![0x0a, 0xff]);
+            }
+            credentials => panic!("unexpected credentials {:?}", credentials),
+        }
+
+        let long_nonce = format!(
+            "HMAC admin:1540000000:{}:0aff",
+            "n".repeat(MAX_NONCE_LEN + 1)
+        );
+        for value in [
+            "",
+            "Bearer",
+            "Basic dXNlcjpwYXNz",
+            "HMAC admin:1540000000:0aff",
+            "HMAC admin:now:n1:0aff",
+            "HMAC admin:1540000000:n1:xyz",
+            "HMAC admin:1540000000::0aff",
+            &long_nonce[..],
+        ]
+        .iter()
+        {
+            assert!(Credentials::parse(value).is_none(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_hmac_known_vectors() {
+        assert_eq!(
+            hmac(b"key", b"The quick brown fox jumps over the lazy dog").to_hex(),
+            "74547bc8c8e1ef02aec834ca60ff24cc316d4c2244a360fe17448cb53410bed4"
+        );
+        // keys longer than a block are hashed
+        assert_eq!(
+            hmac(&[b'k'; 200], b"message").to_hex(),
+            "d83a9f642bc974b09694e90db9757c6abd67214ee1c53c25845504db2057f082"
+        );
+
+        let body_hash = keccak(BODY);
+        assert_eq!(
+            body_hash.to_hex(),
+            "5e948de566cb7305e62a80251eb79fd869b5f10f3512722748140219a0149ebf"
+        );
+        let credentials = Credentials::parse(
+            "HMAC admin:1540000000:n1:\
+             f7f56c405ad766a5548a9d830bd8fd69020cbd95ecdccd6df9694c7b0514dbcc",
+        )
+        .unwrap();
+        let authenticator = authenticator();
+        assert_eq!(
+            authenticator.authenticate_at(&credentials, Some(&body_hash), TIMESTAMP),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_hmac_skew_window() {
+        let authenticator = authenticator();
+        let body_hash = keccak(BODY);
+        let authenticate = |nonce: &str, now: u64| {
+            let credentials = hmac_credentials("admin", "secret", TIMESTAMP, nonce);
+            authenticator.authenticate_at(&credentials, Some(&body_hash), now)
+        };
+
+        assert_eq!(authenticate("n1", TIMESTAMP - HMAC_MAX_SKEW_SECS), Some(0));
+        assert_eq!(authenticate("n2", TIMESTAMP + HMAC_MAX_SKEW_SECS), Some(0));
+        assert_eq!(authenticate("n3", TIMESTAMP - HMAC_MAX_SKEW_SECS - 1), None);
+        assert_eq!(authenticate("n4", TIMESTAMP + HMAC_MAX_SKEW_SECS + 1), None);
+    }
+
+    #[test]
+    fn test_hmac_rejects_replay_and_other_body() {
+        let authenticator = authenticator();
+        let body_hash = keccak(BODY);
+        let credentials = hmac_credentials("admin", "secret", TIMESTAMP, "n1");
+
+        assert_eq!(
+            authenticator.authenticate_at(&credentials, Some(&keccak("{}")), TIMESTAMP),
+            None
+        );
+        assert_eq!(
+            authenticator.authenticate_at(&credentials, None, TIMESTAMP),
+            None
+        );
+        assert_eq!(
+            authenticator.authenticate_at(&credentials, Some(&body_hash), TIMESTAMP),
+            Some(0)
+        );
+        assert_eq!(
+            authenticator.authenticate_at(&credentials, Some(&body_hash), TIMESTAMP),
+            None
+        );
+
+        // other keys and wrong secrets
+        let credentials = hmac_credentials("root", "secret", TIMESTAMP, "n2");
+        assert_eq!(
+            authenticator.authenticate_at(&credentials, Some(&body_hash), TIMESTAMP),
+            None
+        );
+        let credentials = hmac_credentials("root", "root-secret", TIMESTAMP, "n1");
+        assert_eq!(
+            authenticator.authenticate_at(&credentials, Some(&body_hash), TIMESTAMP),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"", b""));
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"secret", b""));
+    }
+
+    #[test]
+    fn test_is_authorized_per_api_set() {
+        let authenticator = authenticator();
+        let admin =
+            authenticator.authorize(Some(&Credentials::Bearer("secret".to_owned())), None, None);
+        let root = authenticator.authorize(
+            Some(&Credentials::Bearer("root-secret".to_owned())),
+            None,
+            None,
+        );
+        let unknown = authenticator.authorize(
+            Some(&Credentials::Bearer("change-me".to_owned())),
+            None,
+            None,
+        );
+        assert_eq!((admin, root, unknown), (Some(0), Some(1), None));
+
+        for key in [admin, root, unknown].iter() {
+            assert!(authenticator.is_authorized(&Api::Token, *key));
+            assert!(authenticator.is_authorized(&Api::Pool, *key));
+        }
+        assert!(authenticator.is_authorized(&Api::Admin, admin));
+        assert!(!authenticator.is_authorized(&Api::Relayer, admin));
+        assert!(authenticator.is_authorized(&Api::Admin, root));
+        assert!(authenticator.is_authorized(&Api::Relayer, root));
+        assert!(!authenticator.is_authorized(&Api::Admin, unknown));
+        assert!(!authenticator.is_authorized(&Api::Relayer, unknown));
+
+        // connections are authorized by the key they logged in with
+        assert_eq!(authenticator.authorize(None, None, Some(1)), Some(1));
+        assert_eq!(authenticator.authorize(None, None, None), None);
+    }
+
+    #[test]
+    fn test_login_attempts_are_limited() {
+        let authenticator = authenticator();
+        let (client, other) = (Client::Session(1), Client::Session(2));
+        let valid = Credentials::Bearer("secret".to_owned());
+        let invalid = Credentials::Bearer("guess".to_owned());
+
+        assert_eq!(authenticator.login(client, &valid), Some(0));
+        for _ in 1..LOGIN_BURST {
+            assert_eq!(authenticator.login(client, &invalid), None);
+        }
+        assert_eq!(authenticator.login(client, &valid), None);
+        assert_eq!(authenticator.login(other, &valid), Some(0));
+    }
+}
@@ -13,6 +13,37 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::U256;
+
+// fee of a token transfer request does not cover its gas cost
+pub const FEE_TOO_LOW: i64 = -32010;
+
+// client or sender of token transfer request exceeds its rate limit
+pub const RATE_LIMITED: i64 = -32011;
+
+// client is not authenticated or its API key is not allowed to call the method
+pub const UNAUTHORIZED: i64 = -32012;
+
+// token transfer request is already in the pool
+pub const ALREADY_IMPORTED: i64 = -32013;
+
+// pool is full and token transfer request is too cheap to replace any request in it
+pub const TOO_CHEAP: i64 = -32014;
+
+// gas amount of token transfer request can not be estimated, e.g. the transfer would fail
+pub const GAS_ESTIMATION_FAILED: i64 = -32015;
+
+// token of token transfer request is not allowed by the pool
+pub const TOKEN_NOT_SUPPORTED: i64 = -32016;
+
+#[derive(Serialize)]
+struct FeeTooLowData {
+    fee: U256,
+
+    #[serde(rename = "minimumFee")]
+    minimum_fee: U256,
+}
+
 #[allow(unused)]
 pub fn unimplemented() -> jsonrpc_core::Error {
     jsonrpc_core::Error {
@@ -24,7 +55,7 @@ pub fn unimplemented() -> jsonrpc_core::Error {
 
 #[allow(unused)]
 pub fn internal<E: ::std::fmt::Debug>(e: E) -> jsonrpc_core::Error {
-    warn!("Unknown error: {:?}", e);
+    warn!(target: "system", "Unknown error: {:?}", e);
     jsonrpc_core::Error {
         code: jsonrpc_core::ErrorCode::InternalError,
         message: "Unknown error occured".into(),
@@ -32,6 +63,58 @@ pub fn internal<E: ::std::fmt::Debug>(e: E) -> jsonrpc_core::Error {
     }
 }
 
+pub fn rate_limited() -> jsonrpc_core::Error {
+    jsonrpc_core::Error {
+        code: jsonrpc_core::ErrorCode::ServerError(RATE_LIMITED),
+        message: "Rate limit exceeded, try again later".into(),
+        data: None,
+    }
+}
+
+pub fn unauthorized(message: &str) -> jsonrpc_core::Error {
+    jsonrpc_core::Error {
+        code: jsonrpc_core::ErrorCode::ServerError(UNAUTHORIZED),
+        message: message.into(),
+        data: None,
+    }
+}
+
+/// Converts an error of importing a token transfer request into pool
+pub fn pool_import(err: pool::Error) -> jsonrpc_core::Error {
+    let code = match err.kind() {
+        pool::ErrorKind::FeeTooLow(_, fee, min_fee) => {
+            return jsonrpc_core::Error {
+                code: jsonrpc_core::ErrorCode::ServerError(FEE_TOO_LOW),
+                message: err.to_string(),
+                data: serde_json::to_value(FeeTooLowData {
+                    fee: *fee,
+                    minimum_fee: *min_fee,
+                })
+                .ok(),
+            };
+        }
+        pool::ErrorKind::AlreadyImported(_) => {
+            jsonrpc_core::ErrorCode::ServerError(ALREADY_IMPORTED)
+        }
+        pool::ErrorKind::TooCheapToEnter(_, _)
+        | pool::ErrorKind::TooCheapToReplace(_, _)
+        | pool::ErrorKind::MemoryLimitReached(_) => jsonrpc_core::ErrorCode::ServerError(TOO_CHEAP),
+        pool::ErrorKind::TokenTransferRequestGasEstimationFailed => {
+            jsonrpc_core::ErrorCode::ServerError(GAS_ESTIMATION_FAILED)
+        }
+        pool::ErrorKind::NotSupportedToken => {
+            jsonrpc_core::ErrorCode::ServerError(TOKEN_NOT_SUPPORTED)
+        }
+        _ => jsonrpc_core::ErrorCode::InvalidParams,
+    };
+
+    jsonrpc_core::Error {
+        code,
+        message: err.to_string(),
+        data: None,
+    }
+}
+
 error_chain! {
     foreign_links {
 
@@ -41,3 +124,28 @@ error_chain! {
 
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_too_low_error_data() {
+        let err = pool_import(
+            pool::ErrorKind::FeeTooLow("0x01".to_owned(), U256::from(1), U256::from(26)).into(),
+        );
+
+        assert_eq!(err.code, jsonrpc_core::ErrorCode::ServerError(FEE_TOO_LOW));
+        let data = err.data.unwrap();
+        assert_eq!(data["fee"], "0x1");
+        assert_eq!(data["minimumFee"], "0x1a");
+    }
+
+    #[test]
+    fn test_minimum_fee_overflow_is_invalid_params() {
+        let err = pool_import(pool::ErrorKind::MinimumFeeOverflow("0x01".to_owned()).into());
+
+        assert_eq!(err.code, jsonrpc_core::ErrorCode::InvalidParams);
+        assert!(err.data.is_none());
+    }
+}
@@ -0,0 +1,268 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::H256;
+use futures::{Async, Poll, Stream};
+use jsonrpc_http_server::hyper::{self, Body, Chunk};
+use jsonrpc_http_server::RequestMiddlewareAction;
+use jsonrpc_pubsub::{PubSubMetadata, Session};
+use keccak_hash::keccak;
+use parking_lot::Mutex;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use super::{Client, Credentials, HttpRequest};
+
+/// Keccak-256 of the body of a HTTP request, it is set once the body is read
+#[derive(Clone, Default)]
+struct BodyHash(Arc<Mutex<Option<H256>>>);
+
+/// Body of a HTTP request which hashes its content while it is read
+struct HashedBody {
+    body: Body,
+    content: Vec<u8>,
+    hash: BodyHash,
+}
+
+impl Stream for HashedBody {
+    type Item = Chunk;
+    type Error = hyper::Error;
+
+    fn poll(&mut self) -> Poll<Option<Chunk>, hyper::Error> {
+        match self.body.poll()? {
+            Async::Ready(Some(chunk)) => {
+                self.content.extend_from_slice(&chunk);
+                Ok(Async::Ready(Some(chunk)))
+            }
+            Async::Ready(None) => {
+                *self.hash.0.lock() = Some(keccak(&self.content));
+                Ok(Async::Ready(None))
+            }
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+/// Request metadata of JSON-RPC, it carries the session of transports which support pubsub,
+/// the address of remote client if it is known and credentials of the client
+#[derive(Clone, Default)]
+pub struct Metadata {
+    session: Option<Arc<Session>>,
+    remote: Option<IpAddr>,
+    credentials: Option<Credentials>,
+    // hash of HTTP request body, HMAC signatures cover it
+    body_hash: Option<BodyHash>,
+    // identifier of IPC or WebSocket connection
+    session_id: Option<u64>,
+    // index of the API key the IPC or WebSocket connection logged in with, metadata is extracted
+    // once per connection so the login is dropped with the connection
+    login: Option<Arc<Mutex<Option<usize>>>>,
+}
+
+impl Metadata {
+    pub fn with_session(session: Arc<Session>, session_id: u64) -> Metadata {
+        Metadata {
+            session: Some(session),
+            session_id: Some(session_id),
+            login: Some(Default::default()),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_remote(remote: Option<IpAddr>) -> Metadata {
+        Metadata {
+            remote,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_session_id(session_id: u64) -> Metadata {
+        Metadata {
+            session_id: Some(session_id),
+            login: Some(Default::default()),
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub fn remote(&self) -> Option<IpAddr> {
+        self.remote
+    }
+
+    /// Returns the client for rate limiting, it is the remote address of HTTP clients and the
+    /// session of other clients
+    pub fn client(&self) -> Option<Client> {
+        self.remote
+            .map(Client::Remote)
+            .or_else(|| self.session_id.map(Client::Session))
+    }
+
+    #[inline]
+    pub fn credentials(&self) -> Option<&Credentials> {
+        self.credentials.as_ref()
+    }
+
+    #[inline]
+    pub fn session_id(&self) -> Option<u64> {
+        self.session_id
+    }
+
+    /// Returns index of the API key the connection logged in with
+    pub fn login(&self) -> Option<usize> {
+        self.login.as_ref().and_then(|login| *login.lock())
+    }
+
+    /// Keeps index of the API key for further calls of the connection, returns `false` if the
+    /// transport has no connections, e.g. HTTP.
+    pub fn set_login(&self, key: usize) -> bool {
+        match self.login {
+            Some(ref login) => {
+                *login.lock() = Some(key);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns hash of HTTP request body, it is `None` for other transports
+    pub fn body_hash(&self) -> Option<H256> {
+        self.body_hash.as_ref().and_then(|hash| *hash.0.lock())
+    }
+
+    /// Wraps body of a HTTP request to hash it while it is read, the hash is passed to metadata
+    /// of the request in its extensions.
+    pub fn hash_http_body(request: HttpRequest) -> RequestMiddlewareAction {
+        let (mut parts, body) = request.into_parts();
+        let hash = BodyHash::default();
+        parts.extensions.insert(hash.clone());
+        let body = Body::wrap_stream(HashedBody {
+            body,
+            content: Vec::new(),
+            hash,
+        });
+
+        HttpRequest::from_parts(parts, body).into()
+    }
+
+    /// Extracts address of remote client and credentials from headers of a HTTP request. The
+    /// HTTP server does not expose address of peers, so the address is read from headers set by
+    /// reverse proxies. The headers are ignored if no proxies are trusted, otherwise the client is
+    /// the last address in X-Forwarded-For which is not a trusted proxy.
+    pub fn from_http_request(request: &HttpRequest, trusted_proxies: &[IpAddr]) -> Metadata {
+        let headers = request.headers();
+        let values = |name: &str| {
+            headers
+                .get_all(name)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .map(|value| value.trim().parse::<IpAddr>())
+                .collect::<Vec<_>>()
+        };
+
+        let remote = match trusted_proxies.is_empty() {
+            true => None,
+            false => {
+                let forwarded = values("x-forwarded-for");
+                match forwarded.is_empty() {
+                    // an address which can not be parsed may be set by anyone, addresses before
+                    // it are not trusted
+                    false => forwarded
+                        .into_iter()
+                        .rev()
+                        .find(|addr| match addr {
+                            Ok(addr) => !trusted_proxies.contains(addr),
+                            Err(_) => true,
+                        })
+                        .and_then(|addr| addr.ok()),
+                    true => values("x-real-ip")
+                        .into_iter()
+                        .next()
+                        .and_then(|addr| addr.ok()),
+                }
+            }
+        };
+
+        Metadata {
+            credentials: headers
+                .get("authorization")
+                .and_then(|value| value.to_str().ok())
+                .and_then(Credentials::parse),
+            body_hash: request.extensions().get::<BodyHash>().cloned(),
+            ..Metadata::with_remote(remote)
+        }
+    }
+}
+
+impl jsonrpc_core::Metadata for Metadata {}
+
+impl PubSubMetadata for Metadata {
+    fn session(&self) -> Option<Arc<Session>> {
+        self.session.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpc_http_server::hyper::{Body, Request};
+
+    fn remote(headers: &[(&str, &str)], trusted_proxies: &[&str]) -> Option<IpAddr> {
+        let request = headers
+            .iter()
+            .fold(Request::builder(), |mut builder, (name, value)| {
+                builder.header(*name, *value);
+                builder
+            })
+            .body(Body::empty())
+            .unwrap();
+        let trusted_proxies: Vec<IpAddr> = trusted_proxies
+            .iter()
+            .map(|proxy| proxy.parse().unwrap())
+            .collect();
+
+        Metadata::from_http_request(&request, &trusted_proxies).remote()
+    }
+
+    #[test]
+    fn test_remote_from_trusted_proxies() {
+        let forwarded = [("x-forwarded-for", "1.1.1.1, 2.2.2.2, 10.0.0.2")];
+        let client = "2.2.2.2".parse().ok();
+
+        // headers are ignored without trusted proxies
+        assert_eq!(remote(&forwarded, &[]), None);
+        assert_eq!(remote(&forwarded, &["10.0.0.1"]), "10.0.0.2".parse().ok());
+        assert_eq!(remote(&forwarded, &["10.0.0.1", "10.0.0.2"]), client);
+        assert_eq!(
+            remote(
+                &[
+                    ("x-forwarded-for", "1.1.1.1"),
+                    ("x-forwarded-for", "2.2.2.2, 10.0.0.2"),
+                ],
+                &["10.0.0.2"],
+            ),
+            client
+        );
+        assert_eq!(
+            remote(
+                &[("x-forwarded-for", "2.2.2.2, unknown, 10.0.0.2")],
+                &["10.0.0.2"]
+            ),
+            None
+        );
+        assert_eq!(remote(&[("x-real-ip", "2.2.2.2")], &["10.0.0.2"]), client);
+        assert_eq!(remote(&[("x-real-ip", "2.2.2.2")], &[]), None);
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use futures::future::{self, Either};
+use futures::Future;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use jsonrpc_core::{Call, FutureResponse, Middleware, Output, Request, Response};
+
+use super::rpc_apis::Api;
+use super::{errors, Authenticator, Metadata, RateLimits};
+
+// calls of methods beyond this count are counted as unknown method, method names are sent by
+// clients and they must not grow the counter without bound
+const MAX_METHOD_COUNT: usize = 256;
+const UNKNOWN_METHOD: &str = "unknown";
+
+/// Counts JSON-RPC calls per method
+#[derive(Clone, Default)]
+pub struct CallCounter {
+    counts: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl CallCounter {
+    /// Returns count of calls per method
+    pub fn counts(&self) -> HashMap<String, u64> {
+        self.counts.lock().clone()
+    }
+
+    fn count(&self, call: &Call) {
+        let method = match *call {
+            Call::MethodCall(ref method_call) => &method_call.method,
+            Call::Notification(ref notification) => &notification.method,
+            _ => return,
+        };
+
+        let mut counts = self.counts.lock();
+        let method = match counts.contains_key(method) || counts.len() < MAX_METHOD_COUNT {
+            true => method.clone(),
+            false => UNKNOWN_METHOD.to_owned(),
+        };
+        *counts.entry(method).or_insert(0) += 1;
+    }
+}
+
+/// Middleware of JSON-RPC servers, it counts calls and rejects calls exceeding rate limits or
+/// calls which are not authorized
+#[derive(Clone)]
+pub struct ServerMiddleware {
+    call_counter: CallCounter,
+    rate_limits: RateLimits,
+    // calls of protected APIs are not checked if API keys are not configured
+    authenticator: Option<Arc<Authenticator>>,
+}
+
+impl ServerMiddleware {
+    pub fn new(
+        call_counter: CallCounter,
+        rate_limits: RateLimits,
+        authenticator: Option<Arc<Authenticator>>,
+    ) -> ServerMiddleware {
+        ServerMiddleware {
+            call_counter,
+            rate_limits,
+            authenticator,
+        }
+    }
+
+    /// Checks rate limit and authorization of a call, `key` is index of the API key authorizing
+    /// the request, returns output of the call if it is rejected
+    fn limit(
+        &self,
+        call: &Call,
+        meta: &Metadata,
+        key: Option<usize>,
+    ) -> Result<(), Option<Output>> {
+        let (method, id, jsonrpc) = match *call {
+            Call::MethodCall(ref method_call) => (
+                &method_call.method,
+                Some(method_call.id.clone()),
+                method_call.jsonrpc,
+            ),
+            Call::Notification(ref notification) => {
+                (&notification.method, None, notification.jsonrpc)
+            }
+            _ => return Ok(()),
+        };
+
+        let api = match Api::from_method(method) {
+            Some(api) => api,
+            None => return Ok(()),
+        };
+
+        if !self.rate_limits.acquire_client(&api, meta.client()) {
+            debug!(target: "system",
+                "reject call of {} from {:?}, rate limit exceeded", method, meta.remote()
+            );
+            return Err(id.map(|id| Output::from(Err(errors::rate_limited()), id, jsonrpc)));
+        }
+
+        let authorized = match self.authenticator {
+            Some(ref authenticator) => authenticator.is_authorized(&api, key),
+            None => true,
+        };
+        match authorized {
+            true => Ok(()),
+            false => {
+                debug!(target: "system",
+                    "reject call of {} from {:?}, unauthorized", method, meta.remote()
+                );
+                let err = errors::unauthorized("API key is missing or not allowed to call method");
+                Err(id.map(|id| Output::from(Err(err), id, jsonrpc)))
+            }
+        }
+    }
+
+    /// Returns index of the API key authorizing `request`, credentials are checked only if the
+    /// request calls protected APIs
+    fn authorize(&self, request: &Request, meta: &Metadata) -> Option<usize> {
+        let authenticator = self.authenticator.as_ref()?;
+        let is_protected = |call: &Call| {
+            let method = match *call {
+                Call::MethodCall(ref method_call) => &method_call.method,
+                Call::Notification(ref notification) => &notification.method,
+                _ => return false,
+            };
+            Api::from_method(method).map_or(false, |api| Authenticator::is_protected(&api))
+        };
+        let is_protected = match *request {
+            Request::Single(ref call) => is_protected(call),
+            Request::Batch(ref calls) => calls.iter().any(is_protected),
+        };
+
+        match is_protected {
+            true => authenticator.authorize(meta.credentials(), meta.body_hash(), meta.login()),
+            false => None,
+        }
+    }
+
+    /// Appends outputs of rejected calls to response of a batch request
+    fn merge(response: Option<Response>, rejected: Vec<Output>) -> Option<Response> {
+        let mut outputs = match response {
+            Some(Response::Single(output)) => vec![output],
+            Some(Response::Batch(outputs)) => outputs,
+            None => Vec::new(),
+        };
+        outputs.extend(rejected);
+
+        match outputs.is_empty() {
+            true => None,
+            false => Some(Response::Batch(outputs)),
+        }
+    }
+}
+
+impl Middleware<Metadata> for ServerMiddleware {
+    type Future = FutureResponse;
+
+    fn on_request<F, X>(&self, request: Request, meta: Metadata, next: F) -> Either<Self::Future, X>
+    where
+        F: FnOnce(Request, Metadata) -> X + Send,
+        X: Future<Item = Option<Response>, Error = ()> + Send + 'static,
+    {
+        let key = self.authorize(&request, &meta);
+        let mut rejected = Vec::new();
+        let request = match request {
+            Request::Single(call) => {
+                self.call_counter.count(&call);
+                match self.limit(&call, &meta, key) {
+                    Ok(()) => Request::Single(call),
+                    Err(output) => {
+                        let response = output.map(Response::Single);
+                        return Either::A(Box::new(future::ok(response)));
+                    }
+                }
+            }
+            Request::Batch(calls) => {
+                let calls: Vec<_> = calls
+                    .into_iter()
+                    .filter(|call| {
+                        self.call_counter.count(call);
+                        match self.limit(call, &meta, key) {
+                            Ok(()) => true,
+                            Err(output) => {
+                                rejected.extend(output);
+                                false
+                            }
+                        }
+                    })
+                    .collect();
+
+                if calls.is_empty() && !rejected.is_empty() {
+                    let response = Self::merge(None, rejected);
+                    return Either::A(Box::new(future::ok(response)));
+                }
+                Request::Batch(calls)
+            }
+        };
+
+        match rejected.is_empty() {
+            true => Either::B(next(request, meta)),
+            false => Either::A(Box::new(
+                next(request, meta).map(move |response| Self::merge(response, rejected)),
+            )),
+        }
+    }
+}
@@ -15,14 +15,21 @@
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 use super::rpc_apis;
 
+mod auth;
 mod errors;
+mod metadata;
+mod middleware;
+mod rate_limit;
 mod service;
 mod v1;
 
-type HttpServerBuilder = jsonrpc_http_server::ServerBuilder;
-type IpcServerBuilder = jsonrpc_ipc_server::ServerBuilder;
+type HttpServerBuilder = jsonrpc_http_server::ServerBuilder<Metadata, ServerMiddleware>;
+type IpcServerBuilder = jsonrpc_ipc_server::ServerBuilder<Metadata, ServerMiddleware>;
+type WsServerBuilder = jsonrpc_ws_server::ServerBuilder<Metadata, ServerMiddleware>;
 
-use jsonrpc_core::IoHandler as JsonRpcIoHandler;
+type JsonRpcIoHandler = jsonrpc_core::MetaIoHandler<Metadata, ServerMiddleware>;
+
+type HttpRequest = jsonrpc_http_server::hyper::Request<jsonrpc_http_server::hyper::Body>;
 
 pub use jsonrpc_ipc_server::{
     MetaExtractor as IpcMetaExtractor, RequestContext as IpcRequestContext,
@@ -30,6 +37,14 @@ pub use jsonrpc_ipc_server::{
 
 use super::service as relayer_service;
 
+pub use self::auth::ApiKey;
+pub use self::metadata::Metadata;
+pub use self::middleware::CallCounter;
+pub use self::rate_limit::{ApiRateLimit, RateLimit};
 pub use self::service::Service;
 pub use self::service::ServiceParams;
 pub use self::service::{HttpConfiguration, IpcConfiguration, WebSocketConfiguration};
+
+use self::auth::{Authenticator, Credentials};
+use self::middleware::ServerMiddleware;
+use self::rate_limit::{Client, RateLimiter, RateLimits};
@@ -0,0 +1,265 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::Address;
+use parking_lot::Mutex;
+use std::cmp;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::rpc_apis::Api;
+
+// count of buckets kept by a limiter, keys are sent by clients and they must not grow the
+// buckets without bound
+const MAX_BUCKETS: usize = 10240;
+
+/// Limit of a token bucket, `burst` calls are allowed at once and `per_second` calls are
+/// refilled every second
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_second: u32,
+}
+
+/// Rate limits of an API
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApiRateLimit {
+    // limit of calls per remote client
+    pub per_client: Option<RateLimit>,
+    // limit of token transfer requests per recovered request sender
+    pub per_sender: Option<RateLimit>,
+}
+
+/// Client of a JSON-RPC server, HTTP clients are identified by their addresses and clients of
+/// transports with sessions by their sessions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Client {
+    Remote(IpAddr),
+    Session(u64),
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Token bucket rate limiter, each key has its own bucket
+pub struct RateLimiter<K> {
+    burst: f64,
+    per_second: f64,
+    buckets: Mutex<HashMap<K, Bucket>>,
+}
+
+impl<K: Hash + Eq + Clone> RateLimiter<K> {
+    pub fn new(limit: &RateLimit) -> RateLimiter<K> {
+        RateLimiter {
+            burst: f64::from(limit.burst),
+            per_second: f64::from(limit.per_second),
+            buckets: Default::default(),
+        }
+    }
+
+    /// Takes a token from the bucket of `key`, returns `false` if the bucket is empty
+    pub fn acquire(&self, key: K) -> bool {
+        self.acquire_at(key, Instant::now())
+    }
+
+    fn acquire_at(&self, key: K, now: Instant) -> bool {
+        let mut buckets = self.buckets.lock();
+        if buckets.len() >= MAX_BUCKETS && !buckets.contains_key(&key) {
+            self.purge(&mut buckets, now);
+        }
+
+        let burst = self.burst;
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: burst,
+            updated_at: now,
+        });
+        bucket.tokens = self.refilled(bucket, now);
+        bucket.updated_at = now;
+
+        match bucket.tokens >= 1.0 {
+            true => {
+                bucket.tokens -= 1.0;
+                true
+            }
+            false => false,
+        }
+    }
+
+    fn refilled(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.updated_at);
+        let elapsed = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9;
+        (bucket.tokens + elapsed * self.per_second).min(self.burst)
+    }
+
+    /// Removes buckets which are full again, the fullest bucket is removed if all buckets are
+    /// in use.
+    fn purge(&self, buckets: &mut HashMap<K, Bucket>, now: Instant) {
+        buckets.retain(|_, bucket| self.refilled(bucket, now) < self.burst);
+        if buckets.len() < MAX_BUCKETS {
+            return;
+        }
+
+        let fullest = buckets
+            .iter()
+            .max_by(|a, b| {
+                a.1.tokens
+                    .partial_cmp(&b.1.tokens)
+                    .unwrap_or(cmp::Ordering::Equal)
+            })
+            .map(|(key, _)| key.clone());
+        if let Some(fullest) = fullest {
+            buckets.remove(&fullest);
+        }
+    }
+}
+
+/// Rate limiters of APIs served on a transport
+#[derive(Clone, Default)]
+pub struct RateLimits {
+    per_client: HashMap<Api, Arc<RateLimiter<Client>>>,
+    per_sender: HashMap<Api, Arc<RateLimiter<Address>>>,
+}
+
+impl RateLimits {
+    pub fn new(limits: &HashMap<Api, ApiRateLimit>) -> RateLimits {
+        limits
+            .iter()
+            .fold(RateLimits::default(), |mut rate_limits, (api, limit)| {
+                if let Some(ref limit) = limit.per_client {
+                    let limiter = Arc::new(RateLimiter::new(limit));
+                    rate_limits.per_client.insert(api.clone(), limiter);
+                }
+                if let Some(ref limit) = limit.per_sender {
+                    let limiter = Arc::new(RateLimiter::new(limit));
+                    rate_limits.per_sender.insert(api.clone(), limiter);
+                }
+                rate_limits
+            })
+    }
+
+    /// Returns `false` if a client exceeds its limit of calling `api`, calls of unknown clients
+    /// are rejected if `api` is limited since they would share a single bucket.
+    pub fn acquire_client(&self, api: &Api, client: Option<Client>) -> bool {
+        match (self.per_client.get(api), client) {
+            (Some(limiter), Some(client)) => limiter.acquire(client),
+            (Some(_), None) => false,
+            (None, _) => true,
+        }
+    }
+
+    /// Returns limiter of request senders of `api`
+    pub fn sender_limiter(&self, api: &Api) -> Option<Arc<RateLimiter<Address>>> {
+        self.per_sender.get(api).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn limiter(burst: u32, per_second: u32) -> RateLimiter<u64> {
+        RateLimiter::new(&RateLimit { burst, per_second })
+    }
+
+    #[test]
+    fn test_acquire_burst() {
+        let limiter = limiter(3, 1);
+        let now = Instant::now();
+
+        assert!((0..3).all(|_| limiter.acquire_at(1, now)));
+        assert!(!limiter.acquire_at(1, now));
+
+        // other keys have their own buckets
+        assert!(limiter.acquire_at(2, now));
+    }
+
+    #[test]
+    fn test_refill() {
+        let limiter = limiter(2, 4);
+        let now = Instant::now();
+
+        assert!(limiter.acquire_at(1, now));
+        assert!(limiter.acquire_at(1, now));
+        assert!(!limiter.acquire_at(1, now));
+
+        // a token is refilled every 250 milliseconds
+        assert!(!limiter.acquire_at(1, now + Duration::from_millis(200)));
+        assert!(limiter.acquire_at(1, now + Duration::from_millis(300)));
+        assert!(!limiter.acquire_at(1, now + Duration::from_millis(300)));
+
+        // refilled tokens never exceed the burst
+        let later = now + Duration::from_secs(60);
+        assert!((0..2).all(|_| limiter.acquire_at(1, later)));
+        assert!(!limiter.acquire_at(1, later));
+    }
+
+    #[test]
+    fn test_purge_buckets() {
+        let limiter = limiter(2, 1);
+        let now = Instant::now();
+        for key in 0..MAX_BUCKETS as u64 {
+            assert!(limiter.acquire_at(key, now));
+            // bucket of key 1 is the fullest one
+            if key != 1 {
+                assert!(limiter.acquire_at(key, now));
+            }
+        }
+        assert_eq!(limiter.buckets.lock().len(), MAX_BUCKETS);
+
+        // all buckets are in use, the fullest one is removed
+        assert!(limiter.acquire_at(MAX_BUCKETS as u64, now));
+        assert_eq!(limiter.buckets.lock().len(), MAX_BUCKETS);
+        assert!(!limiter.buckets.lock().contains_key(&1));
+
+        // buckets which are full again are removed
+        let later = now + Duration::from_secs(2);
+        assert!(limiter.acquire_at(MAX_BUCKETS as u64 + 1, later));
+        assert_eq!(limiter.buckets.lock().len(), 1);
+    }
+
+    #[test]
+    fn test_limit_per_client() {
+        let mut limits = HashMap::new();
+        limits.insert(
+            Api::Token,
+            ApiRateLimit {
+                per_client: Some(RateLimit {
+                    burst: 1,
+                    per_second: 1,
+                }),
+                per_sender: None,
+            },
+        );
+        let rate_limits = RateLimits::new(&limits);
+        let remote = Some(Client::Remote("10.0.0.1".parse().unwrap()));
+        let session = Some(Client::Session(1));
+
+        assert!(rate_limits.acquire_client(&Api::Token, remote));
+        assert!(!rate_limits.acquire_client(&Api::Token, remote));
+        assert!(rate_limits.acquire_client(&Api::Token, session));
+        assert!(!rate_limits.acquire_client(&Api::Token, session));
+        assert!(rate_limits.acquire_client(&Api::Admin, remote));
+
+        // unknown clients of limited APIs are rejected
+        assert!(!rate_limits.acquire_client(&Api::Token, None));
+        assert!(rate_limits.acquire_client(&Api::Admin, None));
+    }
+}
@@ -14,10 +14,14 @@
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 use futures::{sync::oneshot, Async, Future, Poll, Stream};
+use jsonrpc_pubsub::Session;
 use parking_lot::Mutex;
-use std::net::SocketAddr;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::thread;
+use tokio::runtime::{Runtime, TaskExecutor};
 
 use collation::FstRequestConverter;
 use ethereum::monitor::Service as EthereumMonitor;
@@ -26,11 +30,15 @@ use network::NetworkService;
 use pool::{ListAddressFilter, RequestVerifier, TokenSelector, VerifiedRequest};
 use pricer::PriceService;
 
-use super::relayer_service::ExitHandle;
+use super::relayer_service::{config::KeyStore, ExitHandle};
 use super::rpc_apis;
-use super::v1::{Admin, Network, Pool, Relayer, SystemInfo, Token};
-use super::v1::{AdminApi, NetworkApi, PoolApi, RelayerApi, SystemInfoApi, TokenApi};
-use super::{HttpServerBuilder, IpcServerBuilder, JsonRpcIoHandler};
+use super::v1::{Admin, Auth, Network, Pool, Relayer, SystemInfo, Token};
+use super::v1::{AdminApi, AuthApi, NetworkApi, PoolApi, RelayerApi, SystemInfoApi, TokenApi};
+use super::{
+    ApiKey, ApiRateLimit, Authenticator, CallCounter, HttpRequest, HttpServerBuilder,
+    IpcRequestContext, IpcServerBuilder, JsonRpcIoHandler, Metadata, RateLimits, ServerMiddleware,
+    WsServerBuilder,
+};
 
 type PoolService = pool::PoolService<
     EthereumService,
@@ -38,13 +46,13 @@ type PoolService = pool::PoolService<
     ListAddressFilter,
     VerifiedRequest,
     TokenSelector,
-    RequestVerifier<EthereumService>,
+    RequestVerifier<EthereumService, PriceService<EthereumService>>,
 >;
 type MachineService = machine::MachineService<
     EthereumService,
     EthereumMonitor<EthereumService>,
     PoolService,
-    PriceService,
+    PriceService<EthereumService>,
     FstRequestConverter,
 >;
 
@@ -58,6 +66,8 @@ pub struct ServiceParams {
     pub ipc_config: Option<IpcConfiguration>,
     pub http_config: Option<HttpConfiguration>,
     pub websocket_config: Option<WebSocketConfiguration>,
+    pub api_keys: Option<Vec<ApiKey>>,
+    pub rate_limits: HashMap<rpc_apis::Api, ApiRateLimit>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -73,6 +83,7 @@ pub struct HttpConfiguration {
     pub apis: rpc_apis::ApiSet,
     pub socket_address: SocketAddr,
     pub thread_count: usize,
+    pub trusted_proxies: Vec<IpAddr>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,7 +91,7 @@ pub struct WebSocketConfiguration {
     pub disable: bool,
     pub apis: rpc_apis::ApiSet,
     pub socket_address: SocketAddr,
-    pub thread_count: usize,
+    pub max_connections: usize,
 }
 
 pub struct Service {
@@ -95,6 +106,11 @@ impl Service {
         network_service: Arc<Mutex<NetworkService>>,
         pool_service: Arc<Mutex<PoolService>>,
         machine_service: Arc<Mutex<MachineService>>,
+        price_service: Arc<Mutex<PriceService<EthereumService>>>,
+        relayer_key_store: Option<KeyStore>,
+        config_file_path: PathBuf,
+        min_fee_margin_percent: Option<u32>,
+        call_counter: CallCounter,
     ) -> Service {
         let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
 
@@ -104,8 +120,24 @@ impl Service {
                     ipc_config,
                     http_config,
                     websocket_config,
+                    api_keys,
+                    rate_limits,
                 } = params;
 
+                // limits are shared by all servers, so clients and request senders can not
+                // bypass them by switching transports
+                let rate_limits = RateLimits::new(&rate_limits);
+
+                // every server has its own authenticator, login limits are not shared between them
+                let new_authenticator = || {
+                    api_keys
+                        .clone()
+                        .map(|api_keys| Arc::new(Authenticator::new(api_keys)))
+                };
+
+                // runtime for pushing subscription notifications
+                let runtime = Runtime::new().expect("failed to create JSON-RPC runtime");
+
                 let ipc_server = ipc_config.and_then(|ipc_config| {
                     let IpcConfiguration {
                         disable,
@@ -122,14 +154,27 @@ impl Service {
                         ipc_path, apis
                     );
                     Some(
-                        IpcServerBuilder::new(Self::new_handler(
-                            apis,
-                            exit_handler.clone(),
-                            ethereum_service.clone(),
-                            network_service.clone(),
-                            pool_service.clone(),
-                            machine_service.clone(),
-                        ))
+                        IpcServerBuilder::with_meta_extractor(
+                            Self::new_handler(
+                                apis,
+                                exit_handler.clone(),
+                                ethereum_service.clone(),
+                                network_service.clone(),
+                                pool_service.clone(),
+                                machine_service.clone(),
+                                price_service.clone(),
+                                relayer_key_store.clone(),
+                                config_file_path.clone(),
+                                min_fee_margin_percent,
+                                call_counter.clone(),
+                                rate_limits.clone(),
+                                new_authenticator(),
+                                runtime.executor(),
+                            ),
+                            |context: &IpcRequestContext| {
+                                Metadata::with_session_id(context.session_id)
+                            },
+                        )
                         .start(&ipc_path)
                         .unwrap(),
                     )
@@ -141,6 +186,7 @@ impl Service {
                         apis,
                         socket_address,
                         thread_count,
+                        trusted_proxies,
                     } = http_config;
 
                     if disable {
@@ -160,19 +206,82 @@ impl Service {
                             network_service.clone(),
                             pool_service.clone(),
                             machine_service.clone(),
+                            price_service.clone(),
+                            relayer_key_store.clone(),
+                            config_file_path.clone(),
+                            min_fee_margin_percent,
+                            call_counter.clone(),
+                            rate_limits.clone(),
+                            new_authenticator(),
+                            runtime.executor(),
                         ))
+                        .request_middleware(Metadata::hash_http_body)
+                        .meta_extractor(move |request: &HttpRequest| {
+                            Metadata::from_http_request(request, &trusted_proxies)
+                        })
                         .threads(thread_count)
                         .start_http(&socket_address)
                         .unwrap(),
                     )
                 });
 
+                let websocket_server = websocket_config.and_then(|websocket_config| {
+                    let WebSocketConfiguration {
+                        disable,
+                        apis,
+                        socket_address,
+                        max_connections,
+                    } = websocket_config;
+
+                    if disable {
+                        info!(target: "system", "JSON-RPC WebSocket server is disabled.");
+                        return None;
+                    }
+
+                    info!(target: "system",
+                        "Start JSON-RPC WebSocket server on {} with API: {}",
+                        socket_address, apis
+                    );
+                    Some(
+                        WsServerBuilder::with_meta_extractor(
+                            Self::new_handler(
+                                apis,
+                                exit_handler.clone(),
+                                ethereum_service.clone(),
+                                network_service.clone(),
+                                pool_service.clone(),
+                                machine_service.clone(),
+                                price_service.clone(),
+                                relayer_key_store.clone(),
+                                config_file_path.clone(),
+                                min_fee_margin_percent,
+                                call_counter.clone(),
+                                rate_limits.clone(),
+                                new_authenticator(),
+                                runtime.executor(),
+                            ),
+                            |context: &jsonrpc_ws_server::RequestContext| {
+                                let session = Arc::new(Session::new(context.sender()));
+                                Metadata::with_session(session, context.session_id)
+                            },
+                        )
+                        .max_connections(max_connections)
+                        .start(&socket_address)
+                        .unwrap(),
+                    )
+                });
+
                 // wait for shutdown signal
                 shutdown_receiver
                     .map(|_| info!(target: "system", "Stop JSON-RPC service"))
                     .wait()
                     .unwrap();
 
+                websocket_server.map(|websocket_server| {
+                    info!(target: "system", "Shutdown JSON-RPC WebSocket server");
+                    websocket_server.close();
+                });
+
                 http_server.map(|http_server| {
                     info!(target: "system", "Shutdown JSON-RPC HTTP server");
                     http_server.close();
@@ -182,6 +291,11 @@ impl Service {
                     info!(target: "system", "Shutdown JSON-RPC IPC server");
                     ipc_server.close();
                 });
+
+                runtime
+                    .shutdown_now()
+                    .wait()
+                    .expect("runtime shutdown never fails; qed");
             }
         });
 
@@ -200,14 +314,43 @@ impl Service {
         network_service: Arc<Mutex<NetworkService>>,
         pool_service: Arc<Mutex<PoolService>>,
         machine_service: Arc<Mutex<MachineService>>,
+        price_service: Arc<Mutex<PriceService<EthereumService>>>,
+        relayer_key_store: Option<KeyStore>,
+        config_file_path: PathBuf,
+        min_fee_margin_percent: Option<u32>,
+        call_counter: CallCounter,
+        rate_limits: RateLimits,
+        authenticator: Option<Arc<Authenticator>>,
+        executor: TaskExecutor,
     ) -> JsonRpcIoHandler {
-        let mut handler = jsonrpc_core::IoHandler::new();
+        let sender_limiter = rate_limits.sender_limiter(&rpc_apis::Api::Token);
+        let mut handler = JsonRpcIoHandler::with_middleware(ServerMiddleware::new(
+            call_counter,
+            rate_limits,
+            authenticator.clone(),
+        ));
+
+        match authenticator {
+            Some(authenticator) => handler.extend_with(Auth::new(authenticator).to_delegate()),
+            None => {
+                let apis = apis.apis();
+                if apis.contains(&rpc_apis::Api::Admin) || apis.contains(&rpc_apis::Api::Relayer) {
+                    warn!(target: "system",
+                        "JSON-RPC admin or relayer API is exposed without API keys"
+                    );
+                }
+            }
+        }
 
         for api in apis.apis().into_iter() {
             use rpc_apis::Api;
             match api {
                 Api::Admin => {
-                    let admin = Admin::new(exit_handler.clone(), ethereum_service.clone());
+                    let admin = Admin::new(
+                        exit_handler.clone(),
+                        ethereum_service.clone(),
+                        pool_service.clone(),
+                    );
                     handler.extend_with(admin.to_delegate());
                 }
                 Api::SystemInfo => {
@@ -226,11 +369,22 @@ impl Service {
                     handler.extend_with(pool.to_delegate());
                 }
                 Api::Relayer => {
-                    let relayer = Relayer::new(machine_service.clone());
+                    let relayer = Relayer::new(
+                        machine_service.clone(),
+                        relayer_key_store.clone(),
+                        config_file_path.clone(),
+                    );
                     handler.extend_with(relayer.to_delegate());
                 }
                 Api::Token => {
-                    let token = Token::new(ethereum_service.clone(), pool_service.clone());
+                    let token = Token::new(
+                        ethereum_service.clone(),
+                        pool_service.clone(),
+                        price_service.clone(),
+                        min_fee_margin_percent,
+                        sender_limiter.clone(),
+                        executor.clone(),
+                    );
                     handler.extend_with(token.to_delegate());
                 }
             }
@@ -251,7 +405,7 @@ impl Service {
                 thread_handler.join().expect("thread is not joined; qed");
             }
             None => {
-                warn!("emit shutdown when service is shutting down");
+                warn!(target: "system", "emit shutdown when service is shutting down");
             }
         }
     }
@@ -13,39 +13,93 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::Address;
 use parking_lot::Mutex;
 use std::sync::Arc;
 
 use jsonrpc_core::Result;
+use pool::{ListAddressFilter, ListAddressFilterMode};
 
 use super::traits::AdminApi;
 
-pub struct Admin<X, E>
+pub struct Admin<X, E, P>
 where
     X: 'static + traits::ExitHandle,
     E: 'static + traits::EthereumService,
+    P: 'static
+        + traits::PoolService<
+            SignedRequest = types::SignedRequest,
+            Address = Address,
+            Filter = ListAddressFilter,
+        >,
 {
     exit_hanlder: Arc<Mutex<X>>,
     ethereum_service: Arc<Mutex<E>>,
+    pool_service: Arc<Mutex<P>>,
 }
 
-impl<X, E> Admin<X, E>
+impl<X, E, P> Admin<X, E, P>
 where
     X: 'static + traits::ExitHandle,
     E: 'static + traits::EthereumService,
+    P: 'static
+        + traits::PoolService<
+            SignedRequest = types::SignedRequest,
+            Address = Address,
+            Filter = ListAddressFilter,
+        >,
 {
-    pub fn new(exit_hanlder: Arc<Mutex<X>>, ethereum_service: Arc<Mutex<E>>) -> Admin<X, E> {
+    pub fn new(
+        exit_hanlder: Arc<Mutex<X>>,
+        ethereum_service: Arc<Mutex<E>>,
+        pool_service: Arc<Mutex<P>>,
+    ) -> Admin<X, E, P> {
         Admin {
             exit_hanlder,
             ethereum_service,
+            pool_service,
         }
     }
+
+    /// Updates token filter of pool and returns if it is changed, requests of denied tokens
+    /// are removed from pool
+    fn update_filter<F>(&self, update: F) -> bool
+    where
+        F: FnOnce(&mut ListAddressFilter),
+    {
+        let mut pool = self.pool_service.lock();
+        let old_filter = pool.filter();
+        let mut filter = old_filter.clone();
+        update(&mut filter);
+
+        if filter.mode() == old_filter.mode() && filter.tokens() == old_filter.tokens() {
+            return false;
+        }
+
+        let count = pool.len();
+        pool.set_filter(filter);
+        let removed = count.saturating_sub(pool.len());
+        if removed > 0 {
+            info!(target: "pool",
+                "{} request(s) of denied token(s) are removed",
+                removed
+            );
+        }
+
+        true
+    }
 }
 
-impl<X, E> AdminApi for Admin<X, E>
+impl<X, E, P> AdminApi for Admin<X, E, P>
 where
     X: 'static + traits::ExitHandle,
     E: 'static + traits::EthereumService,
+    P: 'static
+        + traits::PoolService<
+            SignedRequest = types::SignedRequest,
+            Address = Address,
+            Filter = ListAddressFilter,
+        >,
 {
     fn shutdown(&self) -> Result<bool> {
         self.exit_hanlder.lock().shutdown();
@@ -60,11 +114,27 @@ where
         Ok(self.ethereum_service.lock().remove_endpoint(&endpoint))
     }
 
-    fn ethereum_endpoints(&self) -> Result<Vec<String>> {
-        Ok(self.ethereum_service.lock().endpoints())
+    fn ethereum_endpoints(&self) -> Result<Vec<types::EthereumEndpointState>> {
+        Ok(self.ethereum_service.lock().endpoint_states())
     }
 
     fn ethereum_endpoint_count(&self) -> Result<usize> {
         Ok(self.ethereum_service.lock().endpoint_count())
     }
+
+    fn add_allowed_token(&self, token: Address) -> Result<bool> {
+        Ok(self.update_filter(|filter| filter.allow_token(token)))
+    }
+
+    fn remove_allowed_token(&self, token: Address) -> Result<bool> {
+        Ok(self.update_filter(|filter| filter.deny_token(token)))
+    }
+
+    fn set_filter_mode(&self, mode: ListAddressFilterMode) -> Result<bool> {
+        Ok(self.update_filter(|filter| filter.set_mode(mode)))
+    }
+
+    fn list_tokens(&self) -> Result<ListAddressFilter> {
+        Ok(self.pool_service.lock().filter())
+    }
 }
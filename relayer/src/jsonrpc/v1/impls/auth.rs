@@ -0,0 +1,54 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use jsonrpc_core::Result;
+use std::sync::Arc;
+
+use super::super::super::{errors, Authenticator, Credentials, Metadata};
+use super::traits::AuthApi;
+
+pub struct Auth {
+    authenticator: Arc<Authenticator>,
+}
+
+impl Auth {
+    pub fn new(authenticator: Arc<Authenticator>) -> Auth {
+        Auth { authenticator }
+    }
+}
+
+impl AuthApi for Auth {
+    type Metadata = Metadata;
+
+    fn login(&self, meta: Self::Metadata, credentials: String) -> Result<bool> {
+        // HTTP is stateless, its clients send credentials in every request
+        let client = match (meta.session_id(), meta.client()) {
+            (Some(_), Some(client)) => client,
+            _ => {
+                return Err(errors::unauthorized(
+                    "login is not supported by this transport, send Authorization header instead",
+                ));
+            }
+        };
+
+        let authenticated = Credentials::parse(&credentials)
+            .and_then(|credentials| self.authenticator.login(client, &credentials))
+            .map_or(false, |key| meta.set_login(key));
+        match authenticated {
+            true => Ok(true),
+            false => Err(errors::unauthorized("invalid credentials")),
+        }
+    }
+}
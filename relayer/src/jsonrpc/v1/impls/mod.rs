@@ -16,6 +16,7 @@
 use super::traits;
 
 mod admin;
+mod auth;
 mod network;
 mod pool;
 mod relayer;
@@ -23,6 +24,7 @@ mod system_info;
 mod token;
 
 pub use self::admin::Admin;
+pub use self::auth::Auth;
 pub use self::network::Network;
 pub use self::pool::Pool;
 pub use self::relayer::Relayer;
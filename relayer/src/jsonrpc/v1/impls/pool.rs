@@ -13,6 +13,7 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::H256;
 use parking_lot::Mutex;
 use std::sync::Arc;
 
@@ -26,7 +27,7 @@ pub struct Pool<P> {
 
 impl<P> Pool<P>
 where
-    P: 'static + traits::PoolService<SignedRequest = types::SignedRequest>,
+    P: 'static + traits::PoolService<SignedRequest = types::SignedRequest, Hash = H256>,
 {
     pub fn new(pool: Arc<Mutex<P>>) -> Pool<P> {
         Pool { pool }
@@ -35,7 +36,7 @@ where
 
 impl<P> PoolApi for Pool<P>
 where
-    P: 'static + traits::PoolService<SignedRequest = types::SignedRequest>,
+    P: 'static + traits::PoolService<SignedRequest = types::SignedRequest, Hash = H256>,
 {
     fn version(&self) -> Result<String> {
         Ok("0.1.0".to_owned())
@@ -77,7 +78,16 @@ where
     }
 
     fn future_requests(&self) -> Result<Vec<types::RelayerRpcRequest>> {
-        let reqs = vec![];
-        Ok(reqs)
+        let pool = self.pool.lock();
+        let requests = pool
+            .future_requests()
+            .iter()
+            .map(|req| types::RelayerRpcRequest::from(req.as_ref()))
+            .collect();
+        Ok(requests)
+    }
+
+    fn invalid_reason(&self, hash: H256) -> Result<Option<String>> {
+        Ok(self.pool.lock().invalid_reason(&hash))
     }
 }
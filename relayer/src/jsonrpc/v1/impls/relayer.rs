@@ -13,41 +13,133 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::{Address, H256};
 use parking_lot::Mutex;
+use serde_json::Value;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
+use ethkey::KeyPair;
 use jsonrpc_core::{Error, ErrorCode, Result};
+use jsonrpc_macros::Trailing;
 
 use machine::MachineStatus;
 
+use service::config::{self, Configuration, KeyStore};
 use super::traits::RelayerApi;
 
 pub struct Relayer<M>
 where
-    M: 'static + traits::MachineService<MachineStatus = machine::MachineStatus>,
+    M: 'static
+        + traits::MachineService<MachineStatus = machine::MachineStatus, KeyPair = KeyPair>,
 {
     machine: Arc<Mutex<M>>,
+    key_store: Option<KeyStore>,
+    config_file_path: PathBuf,
 }
 
 impl<M> Relayer<M>
 where
-    M: 'static + traits::MachineService<MachineStatus = machine::MachineStatus>,
+    M: 'static
+        + traits::MachineService<MachineStatus = machine::MachineStatus, KeyPair = KeyPair>,
 {
-    pub fn new(machine: Arc<Mutex<M>>) -> Relayer<M> {
-        Relayer { machine }
+    pub fn new(
+        machine: Arc<Mutex<M>>,
+        key_store: Option<KeyStore>,
+        config_file_path: PathBuf,
+    ) -> Relayer<M> {
+        Relayer {
+            machine,
+            key_store,
+            config_file_path,
+        }
     }
 }
 
 impl<M> RelayerApi for Relayer<M>
 where
-    M: 'static + traits::MachineService<MachineStatus = machine::MachineStatus>,
+    M: 'static
+        + traits::MachineService<MachineStatus = machine::MachineStatus, KeyPair = KeyPair>,
 {
-    fn add_relayer(&self, _private_key: String) -> Result<bool> {
-        Ok(true)
+    fn add_relayer(&self, key_file: Value, password: String) -> Result<types::EthRpcH160> {
+        let key_file = key_file.to_string();
+        let keypair = match self.key_store {
+            Some(ref key_store) => key_store.insert(&key_file, &password),
+            None => KeyStore::recover_keypair(&key_file, &password),
+        }
+        .map_err(|err| Error {
+            code: ErrorCode::InvalidParams,
+            message: err.to_string(),
+            data: None,
+        })?;
+
+        let address = keypair.address();
+        if self.key_store.is_none() {
+            warn!(target: "relayer",
+                "relayer {:?} is not persisted, no key store is configured",
+                address
+            );
+        }
+
+        let mut machine = self.machine.lock();
+        let mode = machine.default_relayer_mode();
+        let params = machine.relayer_params(keypair);
+        match machine.add_relayer(mode, params) {
+            Ok(_) => Ok(types::EthRpcH160::from(address)),
+            Err(err) => Err(Error {
+                code: ErrorCode::InternalError,
+                message: err.to_string(),
+                data: None,
+            }),
+        }
     }
 
-    fn remove_relayer(&self, _address: String) -> Result<bool> {
+    fn remove_relayer(&self, address: Address) -> Result<bool> {
+        {
+            let mut machine = self.machine.lock();
+            if !machine.contains_relayer(&address) {
+                return Ok(false);
+            }
+
+            if let Err(err) = machine.remove_relayer(&address) {
+                return Err(Error {
+                    code: ErrorCode::InternalError,
+                    message: err.to_string(),
+                    data: None,
+                });
+            }
+        }
+
+        let internal_error = |err: config::Error| Error {
+            code: ErrorCode::InternalError,
+            message: err.to_string(),
+            data: None,
+        };
+
+        let removed_from_key_store = match self.key_store {
+            Some(ref key_store) => key_store.remove(&address).map_err(internal_error)?,
+            None => false,
+        };
+
+        // relayers of configuration file are restored on restart unless the file is updated
+        let removed_from_config_file =
+            Configuration::remove_relayer_from_file(&self.config_file_path, &address)
+                .map_err(internal_error)?;
+        if removed_from_config_file {
+            info!(target: "relayer",
+                "relayer {:?} is removed from configuration file {:?}",
+                address, self.config_file_path
+            );
+        }
+
+        if !removed_from_key_store && !removed_from_config_file {
+            warn!(target: "relayer",
+                "relayer {:?} is removed but it is neither in key store nor in configuration file",
+                address
+            );
+        }
+
         Ok(true)
     }
 
@@ -98,4 +190,21 @@ where
     fn status(&self) -> Result<MachineStatus> {
         Ok(self.machine.lock().status())
     }
+
+    fn collation(&self, transaction_hash: H256) -> Result<Option<types::CollationRecord>> {
+        Ok(self.machine.lock().collation(&transaction_hash))
+    }
+
+    fn collation_history(
+        &self,
+        filter: Trailing<types::CollationFilter>,
+        page: Trailing<types::CollationPage>,
+    ) -> Result<types::CollationRecordList> {
+        let filter: Option<types::CollationFilter> = filter.into();
+        let page: Option<types::CollationPage> = page.into();
+        Ok(self
+            .machine
+            .lock()
+            .collation_history(&filter.unwrap_or_default(), &page.unwrap_or_default()))
+    }
 }
@@ -14,38 +14,148 @@
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 use ethereum_types::{Address, H256, U256};
-use futures::Future;
+use futures::{sync::oneshot, Future, Sink, Stream};
 use jsonrpc_core::{BoxFuture, Error, ErrorCode, Result};
+use jsonrpc_macros::pubsub::Subscriber;
+use jsonrpc_macros::Trailing;
+use jsonrpc_pubsub::SubscriptionId;
 use parking_lot::Mutex;
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::runtime::TaskExecutor;
 
+use ethereum::monitor::confirmations;
+use pool::ListAddressFilterMode;
+
+use super::super::super::{errors, Metadata, RateLimiter};
 use super::traits::TokenApi;
 
-pub struct Token<E, P>
+const REQUEST_STATUS_SUBSCRIPTION: &str = "requestStatus";
+
+// a fee quote is valid for this many seconds, gas price and token price may change after it
+const FEE_QUOTE_VALID_SECS: u64 = 60;
+
+// metadata of token which never changes once the token contract is deployed
+#[derive(Clone)]
+struct TokenMetadata {
+    symbol: String,
+    decimals: U256,
+}
+
+pub struct Token<E, P, G>
 where
     E: 'static + traits::EthereumService,
-    P: 'static + traits::PoolService<SignedRequest = types::SignedRequest>,
+    P: 'static
+        + traits::PoolService<
+            SignedRequest = types::SignedRequest,
+            Hash = H256,
+            Filter = pool::ListAddressFilter,
+            PoolError = pool::Error,
+        >,
+    G: 'static + traits::PriceService,
 {
     ethereum: Arc<Mutex<E>>,
     pool: Arc<Mutex<P>>,
+    pricer: Arc<Mutex<G>>,
+    // minimum margin of request fee over its gas cost required by pool
+    min_fee_margin_percent: Option<u32>,
+    // rate limiter of token transfer requests per sender
+    sender_limiter: Option<Arc<RateLimiter<Address>>>,
+    executor: TaskExecutor,
+    // metadata of tokens fetched from chain
+    token_metadata: Arc<Mutex<HashMap<Address, TokenMetadata>>>,
+    // cancel handles of active subscriptions
+    subscriptions: Arc<Mutex<HashMap<SubscriptionId, oneshot::Sender<()>>>>,
+    next_subscription_id: AtomicUsize,
 }
 
-impl<E, P> Token<E, P>
+impl<E, P, G> Token<E, P, G>
 where
     E: 'static + traits::EthereumService,
-    P: 'static + traits::PoolService<SignedRequest = types::SignedRequest>,
+    P: 'static
+        + traits::PoolService<
+            SignedRequest = types::SignedRequest,
+            Hash = H256,
+            Filter = pool::ListAddressFilter,
+            PoolError = pool::Error,
+        >,
+    G: 'static + traits::PriceService,
 {
-    pub fn new(ethereum: Arc<Mutex<E>>, pool: Arc<Mutex<P>>) -> Token<E, P> {
-        Token { ethereum, pool }
+    pub fn new(
+        ethereum: Arc<Mutex<E>>,
+        pool: Arc<Mutex<P>>,
+        pricer: Arc<Mutex<G>>,
+        min_fee_margin_percent: Option<u32>,
+        sender_limiter: Option<Arc<RateLimiter<Address>>>,
+        executor: TaskExecutor,
+    ) -> Token<E, P, G> {
+        Token {
+            ethereum,
+            pool,
+            pricer,
+            min_fee_margin_percent,
+            sender_limiter,
+            executor,
+            token_metadata: Default::default(),
+            subscriptions: Default::default(),
+            next_subscription_id: AtomicUsize::new(1),
+        }
+    }
+
+    /// Returns `false` if sender of token transfer requests exceeds its rate limit
+    fn acquire_sender(&self, sender: &Address) -> bool {
+        match self.sender_limiter {
+            Some(ref limiter) => limiter.acquire(*sender),
+            None => true,
+        }
+    }
+
+    /// Returns symbol and decimals of token, they are cached once fetched. Returns `None` if
+    /// they can not be fetched.
+    fn token_metadata(&self, token: Address) -> BoxFuture<Option<TokenMetadata>> {
+        if let Some(metadata) = self.token_metadata.lock().get(&token) {
+            return Box::new(futures::future::ok(Some(metadata.clone())));
+        }
+
+        let ethereum = self.ethereum.lock();
+        let cache = self.token_metadata.clone();
+        Box::new(
+            ethereum
+                .token_symbol(token)
+                .join(ethereum.token_decimals(token))
+                .then(move |result| match result {
+                    Ok((symbol, decimals)) => {
+                        let metadata = TokenMetadata { symbol, decimals };
+                        cache.lock().insert(token, metadata.clone());
+                        Ok::<_, Error>(Some(metadata))
+                    }
+                    Err(err) => {
+                        warn!(target: "system",
+                            "failed to fetch metadata of token {:?}: {}", token, err
+                        );
+                        Ok(None)
+                    }
+                }),
+        )
     }
 }
 
-impl<E, P> TokenApi for Token<E, P>
+impl<E, P, G> TokenApi for Token<E, P, G>
 where
     E: 'static + traits::EthereumService,
-    P: 'static + traits::PoolService<SignedRequest = types::SignedRequest>,
+    P: 'static
+        + traits::PoolService<
+            SignedRequest = types::SignedRequest,
+            Hash = H256,
+            Filter = pool::ListAddressFilter,
+            PoolError = pool::Error,
+        >,
+    G: 'static + traits::PriceService,
 {
+    type Metadata = Metadata;
+
     fn is_delegate_enable(&self, token_contract: Address) -> BoxFuture<bool> {
         Box::new(
             self.ethereum
@@ -89,7 +199,7 @@ where
         let signed_req = match request.into_signed_request() {
             Ok(signed_req) => signed_req,
             Err(err) => {
-                warn!("invalid token transfer request {:?}", err);
+                warn!(target: "system", "invalid token transfer request {:?}", err);
                 return Box::new(futures::future::err(Error {
                     code: ErrorCode::InvalidParams,
                     message: "failed to deserialize token transfer request".to_owned(),
@@ -98,11 +208,15 @@ where
             }
         };
 
-        info!(
+        info!(target: "system",
             "received new token transafer request: {:?}",
             signed_req.hash()
         );
 
+        if !self.acquire_sender(signed_req.sender()) {
+            return Box::new(futures::future::err(errors::rate_limited()));
+        }
+
         Box::new(
             self.pool
                 .lock()
@@ -110,14 +224,10 @@ where
                 .then(|result| match result {
                     Ok(signed_req) => {
                         let hash = signed_req.hash();
-                        info!("import new token transafer request: {:?}", hash);
+                        info!(target: "system", "import new token transafer request: {:?}", hash);
                         Ok(*hash)
                     }
-                    Err(err) => Err(Error {
-                        code: ErrorCode::InvalidParams,
-                        message: err.to_string(),
-                        data: None,
-                    }),
+                    Err(err) => Err(errors::pool_import(err)),
                 }),
         )
     }
@@ -125,49 +235,419 @@ where
     fn send_token_transfer_requests(
         &self,
         requests: Vec<types::RelayerRpcRequest>,
-    ) -> BoxFuture<Vec<H256>> {
-        let signed_requests = requests
+    ) -> BoxFuture<Vec<types::RelayerRpcImportResult>> {
+        let imports: Vec<BoxFuture<types::RelayerRpcImportResult>> = requests
             .into_iter()
-            .fold(HashSet::new(), |mut reqs, request| {
-                request
-                    .into_signed_request()
-                    .map(|signed_req| {
-                        info!(
-                            "received new token transafer request: {:?}",
-                            signed_req.hash()
-                        );
-                        reqs.insert(signed_req);
+            .map(|request| -> BoxFuture<types::RelayerRpcImportResult> {
+                let signed_req = match request.into_signed_request() {
+                    Ok(signed_req) => signed_req,
+                    Err(err) => {
+                        warn!(target: "system", "invalid token transfer request {:?}", err);
+                        return Box::new(futures::future::ok(
+                            types::RelayerRpcImportResult::failed(
+                                None,
+                                ErrorCode::InvalidParams.code(),
+                                "failed to deserialize token transfer request".to_owned(),
+                            ),
+                        ));
+                    }
+                };
+
+                let hash = *signed_req.hash();
+                info!(target: "system", "received new token transafer request: {:?}", hash);
+
+                if !self.acquire_sender(signed_req.sender()) {
+                    let err = errors::rate_limited();
+                    return Box::new(futures::future::ok(types::RelayerRpcImportResult::failed(
+                        Some(hash),
+                        err.code.code(),
+                        err.message,
+                    )));
+                }
+
+                Box::new(
+                    self.pool
+                        .lock()
+                        .import(signed_req)
+                        .then(move |result| Ok::<_, Error>(import_result(hash, result))),
+                )
+            })
+            .collect();
+
+        Box::new(futures::future::join_all(imports))
+    }
+
+    fn get_request_status(&self, hash: H256) -> BoxFuture<types::RequestReceipt> {
+        let mut receipt = match self.pool.lock().request_receipt(&hash) {
+            Some(receipt) => receipt,
+            None => {
+                return Box::new(futures::future::err(Error {
+                    code: ErrorCode::InvalidParams,
+                    message: format!("unknown token transfer request {:?}", hash),
+                    data: None,
+                }));
+            }
+        };
+
+        let block_number = match receipt.block_number {
+            Some(block_number) => block_number,
+            None => return Box::new(futures::future::ok(receipt)),
+        };
+
+        Box::new(
+            self.ethereum
+                .lock()
+                .get_block_number()
+                .map(move |current| {
+                    // the same confirmation depth as the one Ethereum monitor waits for
+                    if let Some(count) = confirmations(block_number, current) {
+                        receipt.confirmations = count;
+                    }
+                    receipt
+                })
+                .map_err(|_| Error {
+                    code: ErrorCode::InternalError,
+                    message: "failed to fetch block number".to_owned(),
+                    data: None,
+                }),
+        )
+    }
+
+    fn supported_tokens(&self) -> BoxFuture<Vec<types::RelayerRpcToken>> {
+        let filter = self.pool.lock().filter();
+        // tokens allowed by a blacklist can not be enumerated
+        let tokens: Vec<Address> = match filter.mode() {
+            ListAddressFilterMode::Whitelist => filter.tokens().iter().cloned().collect(),
+            ListAddressFilterMode::Blacklist => Vec::new(),
+        };
+
+        let tokens: Vec<_> = tokens
+            .into_iter()
+            .map(|token| {
+                let token_price = self
+                    .pricer
+                    .lock()
+                    .token_price(token)
+                    .then(|price| Ok::<_, Error>(price.ok()));
+                // delegate state may be changed by token owner, it is not cached
+                let is_delegate_enable =
+                    self.ethereum
+                        .lock()
+                        .token_delegate_enable(token)
+                        .then(move |result| {
+                            if let Err(ref err) = result {
+                                warn!(target: "system",
+                                    "failed to fetch delegate state of token {:?}: {}", token, err
+                                );
+                            }
+                            Ok::<_, Error>(result.ok())
+                        });
+                self.token_metadata(token)
+                    .join3(is_delegate_enable, token_price)
+                    .map(move |(metadata, is_delegate_enable, token_price)| {
+                        (token, metadata, is_delegate_enable, token_price)
                     })
-                    .map_err(|_| {})
-                    .expect("; qed");
-                reqs
+            })
+            .collect();
+
+        let gas_price = self.pricer.lock().gas_price().map_err(|err| {
+            warn!(target: "system", "failed to fetch gas price: {}", err);
+            Error {
+                code: ErrorCode::InternalError,
+                message: "failed to fetch gas price".to_owned(),
+                data: None,
+            }
+        });
+
+        Box::new(
+            gas_price
+                .join(futures::future::join_all(tokens))
+                .map(|(gas_price, tokens)| {
+                    tokens
+                        .into_iter()
+                        .map(|(address, metadata, is_delegate_enable, token_price)| {
+                            types::RelayerRpcToken {
+                                address,
+                                symbol: metadata.as_ref().map(|metadata| metadata.symbol.clone()),
+                                decimals: metadata.map(|metadata| metadata.decimals),
+                                is_delegate_enable,
+                                fee_price: token_price.and_then(|price| {
+                                    pricer::wei_in_token_amount(&gas_price, &price)
+                                }),
+                            }
+                        })
+                        .collect()
+                }),
+        )
+    }
+
+    fn estimate_fee(
+        &self,
+        token: Address,
+        from: Address,
+        to: Address,
+        value: U256,
+        data: types::EthRpcBytes,
+        mode: Trailing<U256>,
+    ) -> BoxFuture<types::RelayerRpcFeeQuote> {
+        let mode: Option<U256> = mode.into();
+        let mode = mode.unwrap_or_else(U256::zero);
+        if mode > U256::from(0x03) {
+            return Box::new(futures::future::err(Error {
+                code: ErrorCode::InvalidParams,
+                message: format!("invalid delegate mode {}", mode),
+                data: None,
+            }));
+        }
+
+        // estimate with the relayer which pool verifies requests with
+        let relayer_address = self
+            .pool
+            .lock()
+            .relayers()
+            .first()
+            .cloned()
+            .unwrap_or(Address::zero());
+
+        let ethereum = self.ethereum.clone();
+        let origin_gas_amount = self
+            .ethereum
+            .lock()
+            .nonce_of(from, types::Currency::Token(token))
+            .and_then(move |nonce| {
+                let signed_request = types::Request {
+                    token_address: token,
+                    nonce,
+                    fee: U256::zero(),
+                    gas_amount: U256::zero(),
+                    receiver: to,
+                    value,
+                    data: data.into_vec(),
+                    delegate_mode: types::DelegateMode::from(mode),
+                    relayer_address,
+                }
+                .null_sign();
+
+                ethereum
+                    .lock()
+                    .estimate_gas(types::GasEstimation::TokenTransferRequest {
+                        relayer_address,
+                        signed_request,
+                    })
+            })
+            .map_err(|err| {
+                warn!(target: "system",
+                    "failed to estimate gas of token transfer request: {}", err
+                );
+                Error {
+                    code: ErrorCode::InvalidParams,
+                    message: "failed to estimate gas of token transfer request".to_owned(),
+                    data: None,
+                }
+            });
+
+        let block_gas_limit = self
+            .ethereum
+            .lock()
+            .block_gas_limit(types::BlockId::Latest)
+            .map_err(|err| {
+                warn!(target: "system", "failed to fetch block gas limit: {}", err);
+                Error {
+                    code: ErrorCode::InternalError,
+                    message: "failed to fetch block gas limit".to_owned(),
+                    data: None,
+                }
             });
 
-        // FIXME
-        Box::new(futures::future::ok(
-            signed_requests.into_iter().map(|req| *req.hash()).collect(),
-        ))
-
-        // let futures: Vec<_> = signed_requests
-        //     .into_iter()
-        //     .map(|req| self.pool.lock().import(req))
-        //     .collect();
-        // Box::new(futures::future::ok(Vec::new()))
-        //
-        // Box::new(futures::future::join_all(futures).and_then(|reqs| {
-        //     let hashes: Vec<_> = reqs
-        //         .iter()
-        //         .map(|req| {
-        //             let hash = req.hash();
-        //             info!("import new token transafer request: {:?}", hash);
-        //             *hash
-        //         }).collect();
-        //     hashes
-        // }))
+        let gas_price = self.pricer.lock().gas_price().map_err(|err| {
+            warn!(target: "system", "failed to fetch gas price: {}", err);
+            Error {
+                code: ErrorCode::InternalError,
+                message: "failed to fetch gas price".to_owned(),
+                data: None,
+            }
+        });
+
+        let token_price = self.pricer.lock().token_price(token).map_err(move |err| {
+            warn!(target: "system", "failed to fetch price of token {:?}: {}", token, err);
+            Error {
+                code: ErrorCode::InternalError,
+                message: format!("failed to fetch price of token {:?}", token),
+                data: None,
+            }
+        });
+
+        let margin_percent = self.min_fee_margin_percent.unwrap_or(0);
+        Box::new(
+            origin_gas_amount
+                .join4(block_gas_limit, gas_price, token_price)
+                .and_then(
+                    move |(origin_gas_amount, block_gas_limit, gas_price, token_price)| {
+                        // a request relayed alone pays the intrinsic gas and the safety margin of
+                        // a whole transaction, a request relayed in a batch only pays its own gas
+                        let gas_amount =
+                            machine::adjust_gas_amount(origin_gas_amount, block_gas_limit);
+                        let calibrated_gas_amount =
+                            pool::minus_intrinsic_gas_amount(origin_gas_amount);
+                        let fee = |gas_amount: U256| {
+                            pool::minimum_fee(gas_amount, gas_price, token_price, margin_percent)
+                        };
+
+                        match (fee(gas_amount), fee(calibrated_gas_amount)) {
+                            (Some(recommended_fee), Some(minimum_fee)) => {
+                                let now = SystemTime::now()
+                                    .duration_since(UNIX_EPOCH)
+                                    .map(|duration| duration.as_secs())
+                                    .unwrap_or(0);
+
+                                Ok(types::RelayerRpcFeeQuote {
+                                    token,
+                                    gas_amount,
+                                    gas_price,
+                                    recommended_fee,
+                                    minimum_fee,
+                                    valid_until: now + FEE_QUOTE_VALID_SECS,
+                                })
+                            }
+                            _ => Err(Error {
+                                code: ErrorCode::InternalError,
+                                message: format!(
+                                    "price of token {:?} is unknown or fee overflows",
+                                    token
+                                ),
+                                data: None,
+                            }),
+                        }
+                    },
+                ),
+        )
     }
 
-    fn supported_tokens(&self) -> Result<Vec<types::RelayerRpcToken>> {
-        let tokens = vec![];
-        Ok(tokens)
+    fn subscribe(
+        &self,
+        _metadata: Self::Metadata,
+        subscriber: Subscriber<types::RequestStatus>,
+        kind: String,
+        hash: H256,
+    ) {
+        if kind != REQUEST_STATUS_SUBSCRIPTION {
+            let _ = subscriber.reject(Error {
+                code: ErrorCode::InvalidParams,
+                message: format!("unknown subscription: {}", kind),
+                data: None,
+            });
+            return;
+        }
+
+        let statuses = match self.pool.lock().watch_request(&hash) {
+            Some(statuses) => statuses,
+            None => {
+                let _ = subscriber.reject(Error {
+                    code: ErrorCode::InvalidParams,
+                    message: format!("request {:?} is not in pool", hash),
+                    data: None,
+                });
+                return;
+            }
+        };
+
+        let id =
+            SubscriptionId::Number(self.next_subscription_id.fetch_add(1, Ordering::SeqCst) as u64);
+        let sink = match subscriber.assign_id(id.clone()) {
+            Ok(sink) => sink,
+            Err(_) => return,
+        };
+
+        info!(target: "system",
+            "subscribe status of token transfer request {:?} as {:?}", hash, id
+        );
+
+        let (cancel_sender, cancel_receiver) = oneshot::channel();
+        self.subscriptions.lock().insert(id.clone(), cancel_sender);
+
+        let subscriptions = self.subscriptions.clone();
+        let forward = statuses
+            .map(Ok::<_, Error>)
+            .forward(sink.sink_map_err(
+                |err| warn!(target: "system", "failed to notify subscriber: {:?}", err),
+            ))
+            .map(|_| ())
+            .select(cancel_receiver.map_err(|_| ()))
+            .then(move |_| {
+                subscriptions.lock().remove(&id);
+                Ok::<(), ()>(())
+            });
+        self.executor.spawn(forward);
+    }
+
+    fn unsubscribe(&self, id: SubscriptionId) -> Result<bool> {
+        match self.subscriptions.lock().remove(&id) {
+            Some(cancel_sender) => {
+                let _ = cancel_sender.send(());
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Converts the result of importing a token transfer request of a batch
+fn import_result<R>(
+    hash: H256,
+    result: ::std::result::Result<R, pool::Error>,
+) -> types::RelayerRpcImportResult {
+    match result {
+        Ok(_) => {
+            info!(target: "system", "import new token transafer request: {:?}", hash);
+            types::RelayerRpcImportResult::imported(hash)
+        }
+        Err(err) => {
+            let err = errors::pool_import(err);
+            types::RelayerRpcImportResult::failed(Some(hash), err.code.code(), err.message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_results_of_mixed_batch() {
+        let hash = "0x01".to_owned();
+        let results: Vec<::std::result::Result<(), pool::Error>> = vec![
+            Ok(()),
+            Err(pool::ErrorKind::AlreadyImported(hash.clone()).into()),
+            Err(pool::ErrorKind::TooCheapToEnter(hash.clone(), "0".to_owned()).into()),
+            Err(pool::ErrorKind::TokenTransferRequestGasEstimationFailed.into()),
+            Err(pool::ErrorKind::NotSupportedToken.into()),
+            Err(pool::ErrorKind::FeeTooLow(hash, U256::from(1), U256::from(2)).into()),
+        ];
+
+        let results: Vec<_> = results
+            .into_iter()
+            .enumerate()
+            .map(|(index, result)| import_result(H256::from(index as u64), result))
+            .collect();
+
+        for (index, result) in results.iter().enumerate() {
+            assert_eq!(result.hash, Some(H256::from(index as u64)));
+        }
+
+        let codes: Vec<_> = results
+            .iter()
+            .map(|result| result.error.as_ref().map(|err| err.code))
+            .collect();
+        assert_eq!(
+            codes,
+            vec![
+                None,
+                Some(errors::ALREADY_IMPORTED),
+                Some(errors::TOO_CHEAP),
+                Some(errors::GAS_ESTIMATION_FAILED),
+                Some(errors::TOKEN_NOT_SUPPORTED),
+                Some(errors::FEE_TOO_LOW),
+            ]
+        );
     }
 }
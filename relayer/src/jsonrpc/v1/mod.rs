@@ -16,5 +16,7 @@
 pub mod impls;
 mod traits;
 
-pub use self::impls::{Admin, Network, Pool, Relayer, SystemInfo, Token};
-pub use self::traits::{AdminApi, NetworkApi, PoolApi, RelayerApi, SystemInfoApi, TokenApi};
+pub use self::impls::{Admin, Auth, Network, Pool, Relayer, SystemInfo, Token};
+pub use self::traits::{
+    AdminApi, AuthApi, NetworkApi, PoolApi, RelayerApi, SystemInfoApi, TokenApi,
+};
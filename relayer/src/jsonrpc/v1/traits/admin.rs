@@ -13,7 +13,9 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::Address;
 use jsonrpc_core::Result;
+use pool::ListAddressFilterMode;
 
 build_rpc_trait! {
     pub trait AdminApi {
@@ -28,9 +30,21 @@ build_rpc_trait! {
         fn remove_ethereum_endpoint(&self, String) -> Result<bool>;
 
         #[rpc(name="admin_ethereumEndpoints")]
-        fn ethereum_endpoints(&self) -> Result<Vec<String>>;
+        fn ethereum_endpoints(&self) -> Result<Vec<types::EthereumEndpointState>>;
 
         #[rpc(name="admin_ethereumEndpointCount")]
         fn ethereum_endpoint_count(&self) -> Result<usize>;
+
+        #[rpc(name="admin_addAllowedToken")]
+        fn add_allowed_token(&self, Address) -> Result<bool>;
+
+        #[rpc(name="admin_removeAllowedToken")]
+        fn remove_allowed_token(&self, Address) -> Result<bool>;
+
+        #[rpc(name="admin_setFilterMode")]
+        fn set_filter_mode(&self, ListAddressFilterMode) -> Result<bool>;
+
+        #[rpc(name="admin_listTokens")]
+        fn list_tokens(&self) -> Result<pool::ListAddressFilter>;
     }
 }
@@ -15,6 +15,7 @@
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 #[macro_use]
 pub mod admin;
+pub mod auth;
 pub mod network;
 pub mod pool;
 pub mod relayer;
@@ -22,6 +23,7 @@ pub mod system_info;
 pub mod token;
 
 pub use self::admin::AdminApi;
+pub use self::auth::AuthApi;
 pub use self::network::NetworkApi;
 pub use self::pool::PoolApi;
 pub use self::relayer::RelayerApi;
@@ -13,6 +13,7 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::H256;
 use jsonrpc_core::Result;
 
 build_rpc_trait! {
@@ -32,5 +33,8 @@ build_rpc_trait! {
 
         #[rpc(name="pool_futureRequests")]
         fn future_requests(&self) -> Result<Vec<types::RelayerRpcRequest>>;
+
+        #[rpc(name="pool_invalidReason")]
+        fn invalid_reason(&self, H256) -> Result<Option<String>>;
     }
 }
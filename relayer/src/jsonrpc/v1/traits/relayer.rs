@@ -13,17 +13,20 @@
 
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::{Address, H256};
 use jsonrpc_core::Result;
+use jsonrpc_macros::Trailing;
 use machine::MachineStatus;
+use serde_json::Value;
 
 build_rpc_trait! {
     pub trait RelayerApi {
 
         #[rpc(name = "relayer_addRelayer")]
-        fn add_relayer(&self, String) -> Result<bool>;
+        fn add_relayer(&self, Value, String) -> Result<types::EthRpcH160>;
 
         #[rpc(name="relayer_removeRelayer")]
-        fn remove_relayer(&self, String) -> Result<bool>;
+        fn remove_relayer(&self, Address) -> Result<bool>;
 
         #[rpc(name = "relayer_relayers")]
         fn relayers(&self) -> Result<Vec<types::EthRpcH160>>;
@@ -48,5 +51,15 @@ build_rpc_trait! {
 
         #[rpc(name = "relayer_status")]
         fn status(&self) -> Result<MachineStatus>;
+
+        #[rpc(name = "relayer_collation")]
+        fn collation(&self, H256) -> Result<Option<types::CollationRecord>>;
+
+        #[rpc(name = "relayer_collationHistory")]
+        fn collation_history(
+            &self,
+            Trailing<types::CollationFilter>,
+            Trailing<types::CollationPage>
+        ) -> Result<types::CollationRecordList>;
     }
 }
@@ -15,9 +15,14 @@
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 use ethereum_types::{Address, H256, U256};
 use jsonrpc_core::{BoxFuture, Result};
+use jsonrpc_macros::Trailing;
+use jsonrpc_macros::pubsub::Subscriber;
+use jsonrpc_pubsub::SubscriptionId;
 
 build_rpc_trait! {
     pub trait TokenApi {
+        type Metadata;
+
         #[rpc(name="token_isDelegateEnable")]
         fn is_delegate_enable(&self, Address) -> BoxFuture<bool>;
 
@@ -31,9 +36,23 @@ build_rpc_trait! {
         fn send_token_transfer_request(&self, types::RelayerRpcRequest) -> BoxFuture<H256>;
 
         #[rpc(name="token_sendTokenTransferRequests")]
-        fn send_token_transfer_requests(&self, Vec<types::RelayerRpcRequest>) -> BoxFuture<Vec<H256>>;
+        fn send_token_transfer_requests(&self, Vec<types::RelayerRpcRequest>) -> BoxFuture<Vec<types::RelayerRpcImportResult>>;
+
+        #[rpc(name="token_getRequestStatus")]
+        fn get_request_status(&self, H256) -> BoxFuture<types::RequestReceipt>;
 
         #[rpc(name="token_supportedTokens")]
-        fn supported_tokens(&self) -> Result<Vec<types::RelayerRpcToken>>;
+        fn supported_tokens(&self) -> BoxFuture<Vec<types::RelayerRpcToken>>;
+
+        #[rpc(name="token_estimateFee")]
+        fn estimate_fee(&self, Address, Address, Address, U256, types::EthRpcBytes, Trailing<U256>) -> BoxFuture<types::RelayerRpcFeeQuote>;
+
+        #[pubsub(name="token_subscription")] {
+            #[rpc(name="token_subscribe")]
+            fn subscribe(&self, Self::Metadata, Subscriber<types::RequestStatus>, String, H256);
+
+            #[rpc(name="token_unsubscribe")]
+            fn unsubscribe(&self, SubscriptionId) -> Result<bool>;
+        }
     }
 }
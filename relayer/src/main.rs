@@ -34,6 +34,7 @@ extern crate ethereum_types;
 extern crate ethkey;
 extern crate ethstore;
 extern crate futures;
+extern crate keccak_hash;
 extern crate parking_lot;
 extern crate rustc_hex;
 
@@ -49,7 +50,7 @@ extern crate jsonrpc_macros;
 extern crate jsonrpc_http_server;
 extern crate jsonrpc_ipc_server;
 extern crate jsonrpc_pubsub;
-// extern crate jsonrpc_ws_server;
+extern crate jsonrpc_ws_server;
 
 extern crate collation;
 extern crate ethereum;
@@ -62,6 +63,7 @@ extern crate types;
 
 mod cli;
 mod jsonrpc;
+mod metrics;
 mod rpc_apis;
 mod run_cmd;
 mod server;
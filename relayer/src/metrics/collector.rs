@@ -0,0 +1,249 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::Address;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+use machine::{MachineStatus, RelayerState};
+use traits::PoolRequestTag;
+
+use super::encoder::{Encoder, MetricKind};
+use super::CallCounter;
+
+const POOL_REQUEST_TAGS: [(PoolRequestTag, &str); 4] = [
+    (PoolRequestTag::Ready, "ready"),
+    (PoolRequestTag::Processing, "processing"),
+    (PoolRequestTag::Executed, "executed"),
+    (PoolRequestTag::Invalid, "invalid"),
+];
+
+const RELAYER_STATES: [(RelayerState, &str); 5] = [
+    (RelayerState::Ready, "ready"),
+    (RelayerState::Preparing, "preparing"),
+    (RelayerState::GasEstimating, "gasEstimating"),
+    (RelayerState::TxBroadcasting, "transactionBroadcasting"),
+    (RelayerState::TxExecuting, "transactionExecuting"),
+];
+
+/// Collects metrics from services on each scrape
+pub struct Collector<E, P, M>
+where
+    E: 'static + traits::EthereumService,
+    P: 'static + traits::PoolService<Address = Address, PoolStatus = pool::Status>,
+    M: 'static + traits::MachineService<MachineStatus = MachineStatus>,
+{
+    ethereum_service: Arc<Mutex<E>>,
+    pool_service: Arc<Mutex<P>>,
+    machine_service: Arc<Mutex<M>>,
+    call_counter: CallCounter,
+}
+
+impl<E, P, M> Collector<E, P, M>
+where
+    E: 'static + traits::EthereumService,
+    P: 'static + traits::PoolService<Address = Address, PoolStatus = pool::Status>,
+    M: 'static + traits::MachineService<MachineStatus = MachineStatus>,
+{
+    pub fn new(
+        ethereum_service: Arc<Mutex<E>>,
+        pool_service: Arc<Mutex<P>>,
+        machine_service: Arc<Mutex<M>>,
+        call_counter: CallCounter,
+    ) -> Collector<E, P, M> {
+        Collector {
+            ethereum_service,
+            pool_service,
+            machine_service,
+            call_counter,
+        }
+    }
+
+    /// Returns current metrics in Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut encoder = Encoder::new();
+        self.encode_pool(&mut encoder);
+        self.encode_machine(&mut encoder);
+        self.encode_ethereum(&mut encoder);
+        self.encode_rpc(&mut encoder);
+        encoder.finish()
+    }
+
+    fn encode_pool(&self, encoder: &mut Encoder) {
+        let (tag_counts, mut token_status) = {
+            let pool = self.pool_service.lock();
+            let tag_counts: Vec<(&str, usize)> = POOL_REQUEST_TAGS
+                .iter()
+                .map(|(tag, label)| (*label, pool.count_by_tag(*tag)))
+                .collect();
+            let token_status: Vec<(Address, pool::Status)> = pool.status().into_iter().collect();
+            (tag_counts, token_status)
+        };
+        token_status.sort_by_key(|(token, _)| *token);
+
+        let name = "relayer_pool_requests";
+        encoder.family(name, MetricKind::Gauge, "Count of requests in pool per tag");
+        for (tag, count) in tag_counts {
+            encoder.sample(name, &[("tag", tag)], count);
+        }
+
+        let name = "relayer_pool_token_requests";
+        encoder.family(
+            name,
+            MetricKind::Gauge,
+            "Count of ready and future requests in pool per token",
+        );
+        for (token, status) in token_status.iter() {
+            let token = format!("{:?}", token);
+            encoder.sample(
+                name,
+                &[("token", token.as_str()), ("readiness", "ready")],
+                status.ready,
+            );
+            encoder.sample(
+                name,
+                &[("token", token.as_str()), ("readiness", "future")],
+                status.future,
+            );
+        }
+    }
+
+    fn encode_machine(&self, encoder: &mut Encoder) {
+        let status = self.machine_service.lock().status();
+        let relayers: Vec<_> = status
+            .relayer_infos()
+            .iter()
+            .map(|info| (format!("{:?}", info.address()), info))
+            .collect();
+
+        let name = "relayer_machine_working";
+        encoder.family(name, MetricKind::Gauge, "Whether relayer machine service is working");
+        encoder.sample(name, &[], status.is_working() as u8);
+
+        let name = "relayer_state";
+        encoder.family(name, MetricKind::Gauge, "Current state of relayer");
+        for (relayer, info) in relayers.iter() {
+            for (state, label) in RELAYER_STATES.iter() {
+                let value = (info.state() == *state) as u8;
+                encoder.sample(name, &[("relayer", relayer.as_str()), ("state", *label)], value);
+            }
+        }
+
+        let name = "relayer_state_seconds_total";
+        encoder.family(name, MetricKind::Counter, "Time spent by relayer in each state");
+        for (relayer, info) in relayers.iter() {
+            for (state, label) in RELAYER_STATES.iter() {
+                let millis = info.stats().state_millis().get(state).cloned().unwrap_or(0);
+                let value = millis as f64 / 1000.0;
+                encoder.sample(name, &[("relayer", relayer.as_str()), ("state", *label)], value);
+            }
+        }
+
+        let name = "relayer_pending_transactions";
+        encoder.family(name, MetricKind::Gauge, "Count of unconfirmed transactions of relayer");
+        for (relayer, info) in relayers.iter() {
+            encoder.sample(
+                name,
+                &[("relayer", relayer.as_str())],
+                info.pending_transaction_count(),
+            );
+        }
+
+        let name = "relayer_collations_total";
+        encoder.family(name, MetricKind::Counter, "Count of collations broadcasted by relayer");
+        for (relayer, info) in relayers.iter() {
+            encoder.sample(name, &[("relayer", relayer.as_str())], info.stats().collation_count());
+        }
+
+        let name = "relayer_gas_used_total";
+        encoder.family(name, MetricKind::Counter, "Gas used by executed transactions of relayer");
+        for (relayer, info) in relayers.iter() {
+            encoder.sample(name, &[("relayer", relayer.as_str())], info.stats().gas_used());
+        }
+
+        let name = "relayer_fees_total";
+        encoder.family(
+            name,
+            MetricKind::Counter,
+            "Fee of executed requests earned by relayer in token unit",
+        );
+        for (relayer, info) in relayers.iter() {
+            let mut fees: Vec<_> = info.stats().fees().iter().collect();
+            fees.sort_by_key(|(token, _)| **token);
+            for (token, fee) in fees {
+                let token = format!("{:?}", token);
+                encoder.sample(
+                    name,
+                    &[("relayer", relayer.as_str()), ("token", token.as_str())],
+                    fee,
+                );
+            }
+        }
+    }
+
+    fn encode_ethereum(&self, encoder: &mut Encoder) {
+        let states = self.ethereum_service.lock().endpoint_states();
+
+        let name = "relayer_ethereum_endpoint_healthy";
+        encoder.family(name, MetricKind::Gauge, "Whether Ethereum endpoint is healthy");
+        for state in states.iter() {
+            encoder.sample(name, &[("endpoint", state.endpoint.as_str())], state.healthy as u8);
+        }
+
+        let name = "relayer_ethereum_endpoint_latency_seconds";
+        encoder.family(
+            name,
+            MetricKind::Gauge,
+            "Moving average of Ethereum endpoint request latency",
+        );
+        for state in states.iter() {
+            let value = state.latency_millis as f64 / 1000.0;
+            encoder.sample(name, &[("endpoint", state.endpoint.as_str())], value);
+        }
+
+        let name = "relayer_ethereum_endpoint_requests_total";
+        encoder.family(name, MetricKind::Counter, "Count of requests sent to Ethereum endpoint");
+        for state in states.iter() {
+            encoder.sample(name, &[("endpoint", state.endpoint.as_str())], state.request_count);
+        }
+
+        let name = "relayer_ethereum_endpoint_errors_total";
+        encoder.family(name, MetricKind::Counter, "Count of failed Ethereum endpoint requests");
+        for state in states.iter() {
+            encoder.sample(name, &[("endpoint", state.endpoint.as_str())], state.error_count);
+        }
+
+        let name = "relayer_ethereum_endpoint_block_lag";
+        encoder.family(
+            name,
+            MetricKind::Gauge,
+            "Count of blocks Ethereum endpoint is behind the highest endpoint",
+        );
+        for state in states.iter() {
+            encoder.sample(name, &[("endpoint", state.endpoint.as_str())], state.block_lag);
+        }
+    }
+
+    fn encode_rpc(&self, encoder: &mut Encoder) {
+        let mut counts: Vec<_> = self.call_counter.counts().into_iter().collect();
+        counts.sort();
+
+        let name = "relayer_rpc_calls_total";
+        encoder.family(name, MetricKind::Counter, "Count of JSON-RPC calls per method");
+        for (method, count) in counts {
+            encoder.sample(name, &[("method", method.as_str())], count);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use std::fmt::{Display, Write};
+
+#[derive(Clone, Copy, Debug)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+}
+
+impl MetricKind {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+        }
+    }
+}
+
+/// Writes metrics in Prometheus text exposition format
+#[derive(Default)]
+pub struct Encoder {
+    output: String,
+}
+
+impl Encoder {
+    pub fn new() -> Encoder {
+        Encoder::default()
+    }
+
+    /// Starts a metric family, samples of the family must be written right after it
+    pub fn family(&mut self, name: &str, kind: MetricKind, help: &str) {
+        let _ = writeln!(
+            self.output,
+            "# HELP {} {}",
+            name,
+            help.replace('\\', "\\\\").replace('\n', "\\n")
+        );
+        let _ = writeln!(self.output, "# TYPE {} {}", name, kind.as_str());
+    }
+
+    pub fn sample<V: Display>(&mut self, name: &str, labels: &[(&str, &str)], value: V) {
+        self.output.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+                .collect();
+            let _ = write!(self.output, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.output, " {}", value);
+    }
+
+    pub fn finish(self) -> String {
+        self.output
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_families() {
+        let mut encoder = Encoder::new();
+        encoder.family("relayer_up", MetricKind::Gauge, "Whether relayer is up");
+        encoder.sample("relayer_up", &[], 1);
+        encoder.family("relayer_calls_total", MetricKind::Counter, "Count of calls");
+        encoder.sample(
+            "relayer_calls_total",
+            &[("method", "token_sendRequest"), ("result", "ok")],
+            42,
+        );
+
+        assert_eq!(
+            encoder.finish(),
+            "# HELP relayer_up Whether relayer is up\n\
+             # TYPE relayer_up gauge\n\
+             relayer_up 1\n\
+             # HELP relayer_calls_total Count of calls\n\
+             # TYPE relayer_calls_total counter\n\
+             relayer_calls_total{method=\"token_sendRequest\",result=\"ok\"} 42\n"
+        );
+    }
+
+    #[test]
+    fn test_escape_label_values_and_help() {
+        let mut encoder = Encoder::new();
+        encoder.family(
+            "relayer_errors",
+            MetricKind::Counter,
+            "Errors\nby \\ reason",
+        );
+        encoder.sample("relayer_errors", &[("reason", "a \"b\"\n\\c")], 1);
+
+        assert_eq!(
+            encoder.finish(),
+            "# HELP relayer_errors Errors\\nby \\\\ reason\n\
+             # TYPE relayer_errors counter\n\
+             relayer_errors{reason=\"a \\\"b\\\"\\n\\\\c\"} 1\n"
+        );
+    }
+}
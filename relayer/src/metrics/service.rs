@@ -0,0 +1,86 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::Address;
+use futures::future;
+use std::io;
+use std::net::SocketAddr;
+
+use jsonrpc_core::IoHandler;
+use jsonrpc_http_server::hyper::{self, Body, Method, Request, Response, StatusCode};
+use jsonrpc_http_server::{RequestMiddlewareAction, Server, ServerBuilder};
+
+use super::Collector;
+
+const METRICS_PATH: &str = "/metrics";
+
+// content type of Prometheus text exposition format
+const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceParams {
+    pub disable: bool,
+    pub socket_address: SocketAddr,
+}
+
+/// HTTP server which serves metrics at `/metrics`
+pub struct Service {
+    server: Option<Server>,
+}
+
+impl Service {
+    pub fn new<E, P, M>(params: ServiceParams, collector: Collector<E, P, M>) -> io::Result<Service>
+    where
+        E: 'static + traits::EthereumService,
+        P: 'static + traits::PoolService<Address = Address, PoolStatus = pool::Status>,
+        M: 'static + traits::MachineService<MachineStatus = machine::MachineStatus>,
+    {
+        if params.disable {
+            info!(target: "system", "Metrics server is disabled.");
+            return Ok(Service { server: None });
+        }
+
+        info!(target: "system", "Start metrics server on {}", params.socket_address);
+        let server = ServerBuilder::new(IoHandler::default())
+            .request_middleware(move |request: Request<Body>| {
+                let response = match (request.method(), request.uri().path()) {
+                    (&Method::GET, METRICS_PATH) => Response::builder()
+                        .header("Content-Type", METRICS_CONTENT_TYPE)
+                        .body(Body::from(collector.render())),
+                    _ => Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(Body::empty()),
+                }
+                .expect("response with valid header is always built; qed");
+
+                RequestMiddlewareAction::Respond {
+                    should_validate_hosts: false,
+                    response: Box::new(future::ok::<_, hyper::Error>(response)),
+                }
+            })
+            .start_http(&params.socket_address)?;
+
+        Ok(Service {
+            server: Some(server),
+        })
+    }
+
+    pub fn shutdown(&mut self) {
+        if let Some(server) = self.server.take() {
+            info!(target: "system", "Shutdown metrics server");
+            server.close();
+        }
+    }
+}
@@ -44,6 +44,16 @@ impl FromStr for Api {
     }
 }
 
+impl Api {
+    /// Returns API of a JSON-RPC method by namespace of the method
+    pub fn from_method(method: &str) -> Option<Api> {
+        method
+            .split('_')
+            .next()
+            .and_then(|namespace| namespace.parse().ok())
+    }
+}
+
 impl ToString for Api {
     fn to_string(&self) -> String {
         match self {
@@ -78,7 +78,7 @@ impl Command {
             }
         };
 
-        let service = match Service::new(config) {
+        let service = match Service::new(config, config_file_path) {
             Ok(service) => Arc::new(Mutex::new(service)),
             Err(err) => {
                 error!(target: "system", "Failed to initial service error: {}", err);
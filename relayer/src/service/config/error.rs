@@ -20,6 +20,8 @@ error_chain! {
         Io(std::io::Error);
         Json(serde_json::Error);
         EthKey(ethkey::Error);
+        TomlDe(toml::de::Error);
+        TomlSer(toml::ser::Error);
     }
 
     errors {
@@ -39,9 +41,30 @@ error_chain! {
                     address, keyfile, password_file, error)
         }
 
+        EncryptPrivateKeyFailed(error: String) {
+            description("Failed to encrypt private key")
+            display("Failed to encrypt private key, error: {}", error)
+        }
+
+        KeyStorePasswordRequired(keys_dir: String) {
+            description("Password file of key store is required")
+            display("Password file of key store {} is required", keys_dir)
+        }
+
         ResolveFilePathFailed(file_path: String) {
             description("Failed to resolve file path")
             display("Failed to resolve file path {}", file_path)
         }
+
+        InvalidTokenPriceFeed(token: Address, reason: String) {
+            description("Invalid token price feed")
+            display("Invalid price feed of token {:?}: {}", token, reason)
+        }
+
+        ClientRateLimitWithoutProxies(api: String) {
+            description("Rate limit per client requires trusted proxies")
+            display("Rate limit per client of API {} requires trusted proxies of JSON-RPC HTTP server",
+                    api)
+        }
     }
 }
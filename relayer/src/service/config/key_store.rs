@@ -0,0 +1,194 @@
+// Copyright 2017-2018 FST Network Pte. Ltd.
+// This file is part of FST Relayer.
+
+// FST Relayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// FST Relayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
+use ethereum_types::Address;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use ethkey::{KeyPair, Password};
+use ethstore::Crypto;
+
+use super::{Error, ErrorKind};
+
+const KEY_FILE_EXTENSION: &str = "json";
+
+// iterations of key derivation function used by parity-ethereum
+const KEY_ITERATIONS: u32 = 10240;
+
+/// Directory of relayer accounts added at runtime, each account is stored as a key file
+/// `<address>.json` encrypted with the password of the key store
+#[derive(Debug, Clone)]
+pub struct KeyStore {
+    dir: PathBuf,
+    password: Password,
+}
+
+impl KeyStore {
+    pub fn new(dir: PathBuf, password: Password) -> KeyStore {
+        KeyStore { dir, password }
+    }
+
+    /// Recovers key pair from content of an encrypted key file
+    pub fn recover_keypair(key_file_content: &str, password: &str) -> Result<KeyPair, Error> {
+        let value: serde_json::Value = serde_json::from_str(key_file_content)?;
+        let crypto = Crypto::from_str(&value["crypto"].to_string())?;
+        let secret = match crypto.secret(&Password::from(password.to_owned())) {
+            Ok(secret) => secret,
+            Err(err) => return Err(Error::from(ErrorKind::EthStore(err))),
+        };
+
+        Ok(KeyPair::from_secret(secret)?)
+    }
+
+    /// Returns content of a key file of the key pair encrypted with the password
+    pub fn encrypt_keypair(keypair: &KeyPair, password: &str) -> Result<String, Error> {
+        let crypto = Crypto::with_secret(
+            keypair.secret(),
+            &Password::from(password.to_owned()),
+            KEY_ITERATIONS,
+        )
+        .map_err(|err| ErrorKind::EncryptPrivateKeyFailed(format!("{:?}", err)))?;
+        let crypto: serde_json::Value = serde_json::from_str(&String::from(crypto))?;
+
+        let mut key_file = serde_json::Map::new();
+        key_file.insert(
+            "address".to_owned(),
+            format!("{:x}", keypair.address()).into(),
+        );
+        key_file.insert("crypto".to_owned(), crypto);
+        key_file.insert("version".to_owned(), 3.into());
+        Ok(serde_json::Value::Object(key_file).to_string())
+    }
+
+    /// Returns key pairs of all stored accounts
+    pub fn keypairs(&self) -> Result<Vec<KeyPair>, Error> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut keypairs = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let key_path = entry?.path();
+            if key_path.extension().and_then(|ext| ext.to_str()) != Some(KEY_FILE_EXTENSION) {
+                continue;
+            }
+
+            let keypair = Self::recover_keypair(&read_file(&key_path)?, self.password.as_str())?;
+            keypairs.push(keypair);
+        }
+
+        Ok(keypairs)
+    }
+
+    /// Stores an account and returns its key pair, the password must decrypt the key file.
+    /// The key is encrypted again with the password of the key store, the given password is
+    /// not persisted.
+    pub fn insert(&self, key_file_content: &str, password: &str) -> Result<KeyPair, Error> {
+        let keypair = Self::recover_keypair(key_file_content, password)?;
+        let key_file_content = Self::encrypt_keypair(&keypair, self.password.as_str())?;
+
+        fs::create_dir_all(&self.dir)?;
+        write_secret_file(&self.key_path(&keypair.address()), &key_file_content)?;
+        Ok(keypair)
+    }
+
+    /// Removes a stored account, returns `false` if the account is not stored
+    pub fn remove(&self, address: &Address) -> Result<bool, Error> {
+        let key_path = self.key_path(address);
+        if !key_path.exists() {
+            return Ok(false);
+        }
+
+        fs::remove_file(&key_path)?;
+        Ok(true)
+    }
+
+    fn key_path(&self, address: &Address) -> PathBuf {
+        self.dir
+            .join(format!("{:x}", address))
+            .with_extension(KEY_FILE_EXTENSION)
+    }
+}
+
+pub fn read_file(path: &Path) -> Result<String, Error> {
+    let mut content = String::new();
+    File::open(path)?.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+// key files are only readable by owner
+fn write_secret_file(path: &Path, content: &str) -> Result<(), Error> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path)?.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethkey::{Generator, Random};
+    use std::{env, process};
+
+    #[test]
+    fn test_insert_and_remove_keys() {
+        let dir = env::temp_dir().join(format!("relayer-keys-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let key_store = KeyStore::new(dir.clone(), Password::from("store".to_owned()));
+        assert!(key_store.keypairs().unwrap().is_empty());
+
+        let keypair = Random.generate().unwrap();
+        let key_file_content = KeyStore::encrypt_keypair(&keypair, "account").unwrap();
+        assert!(key_store.insert(&key_file_content, "wrong").is_err());
+        assert_eq!(
+            key_store
+                .insert(&key_file_content, "account")
+                .unwrap()
+                .address(),
+            keypair.address()
+        );
+
+        // only the key file encrypted with the password of the key store is persisted
+        let entries: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(entries, vec![key_store.key_path(&keypair.address())]);
+        let stored = read_file(&entries[0]).unwrap();
+        assert!(KeyStore::recover_keypair(&stored, "account").is_err());
+
+        let keypairs = key_store.keypairs().unwrap();
+        assert_eq!(keypairs.len(), 1);
+        assert_eq!(keypairs[0].secret(), keypair.secret());
+
+        let reopened = KeyStore::new(dir.clone(), Password::from("other".to_owned()));
+        assert!(reopened.keypairs().is_err());
+
+        assert!(key_store.remove(&keypair.address()).unwrap());
+        assert!(!key_store.remove(&keypair.address()).unwrap());
+        assert!(key_store.keypairs().unwrap().is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
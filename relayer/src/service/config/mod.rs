@@ -14,32 +14,48 @@
 // You should have received a copy of the GNU General Public License
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 use ethereum_types::{Address, U256};
+use std::cmp;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::Read;
+use std::io::Write;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::time::Duration;
 
 use ethkey::{KeyPair, Password};
-use ethstore::Crypto;
 
 use collation::{FstRequestConverter, RequestDispatcher};
 
 use super::{
-    EthereumMonitorParams, EthereumServiceParams, JsonRpcHttpConfig, JsonRpcIpcConfig,
-    JsonRpcServiceParams, MachineServiceParams, PriceServiceMode,
+    CalibrateParams, EthereumHealthParams, EthereumMonitorParams, EthereumQuorumParams,
+    EthereumServiceParams, JsonRpcApiKeyConfig, JsonRpcApiRateLimitConfig, JsonRpcHttpConfig,
+    JsonRpcIpcConfig, JsonRpcRateLimitConfig, JsonRpcServiceParams, JsonRpcWebSocketConfig,
+    MachineServiceParams, MetricsServiceParams, PriceServiceMode, TokenPriceFeedParams,
+    TokenPriceSourceParams,
 };
 
 use super::rpc_apis;
 
 mod error;
+mod key_store;
 pub use self::error::{Error, ErrorKind};
+pub use self::key_store::KeyStore;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EthereumService {
     pub ethereum_nodes: Vec<String>,
+
+    // options of endpoint health check
+    pub health_check_interval_secs: Option<u64>,
+    pub max_consecutive_failures: Option<u32>,
+    pub max_error_rate_percent: Option<u32>,
+    pub max_block_lag: Option<u64>,
+    pub ejection_secs: Option<u64>,
+
+    // options of quorum reads, quorum is disabled if quorum_size is not set
+    pub quorum_size: Option<usize>,
+    pub quorum_threshold: Option<usize>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,7 +70,21 @@ pub struct Machine {
     pub chain_id: Option<u64>,
     pub interval_secs: u64,
     pub confirmation_count: u32,
+    pub gas_price_bump_block_count: Option<u64>,
+    pub gas_price_bump_percent: Option<u32>,
+    pub max_pending_transactions: Option<usize>,
+
+    // options of profitability check, collations are always broadcasted if margin is none
+    pub min_profit_margin_percent: Option<u32>,
+    pub unprofitable_hold_back_secs: Option<u64>,
+
     pub relayers: HashMap<Address, Relayer>,
+    pub keys_dir: Option<String>,
+    pub keys_password_file: Option<String>,
+
+    // all collation records are kept in history file, the latest records are cached in memory
+    pub history_path: Option<String>,
+    pub max_history_records: Option<usize>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,12 +97,51 @@ pub struct Relayer {
 pub enum PricerMode {
     #[serde(rename = "fixed")]
     Fixed,
+    #[serde(rename = "calibrate")]
+    Calibrate,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Pricer {
     pub mode: PricerMode,
     pub fixed_gas_price_in_gwei: Option<u64>,
+
+    // options of calibrate mode
+    pub calibrate_interval_secs: Option<u64>,
+    pub calibrate_percentile: Option<u32>,
+    pub calibrate_smoothing_percent: Option<u32>,
+    pub min_gas_price_in_gwei: Option<u64>,
+    pub max_gas_price_in_gwei: Option<u64>,
+
+    pub tokens: Option<HashMap<Address, TokenPriceFeed>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TokenPriceSource {
+    #[serde(rename = "file")]
+    File,
+    #[serde(rename = "http")]
+    Http,
+    #[serde(rename = "dex")]
+    Dex,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenPriceFeed {
+    pub source: TokenPriceSource,
+    pub max_age_secs: Option<u64>,
+
+    // options of file source
+    pub path: Option<String>,
+
+    // options of http source
+    pub url: Option<String>,
+    pub json_path: Option<String>,
+    pub decimals: Option<u8>,
+
+    // options of dex source, the pair must consist of the token and WETH
+    pub pair: Option<Address>,
+    pub weth: Option<Address>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,13 +158,42 @@ pub struct Pool {
     pub interval_secs: u64,
 
     pub allow_tokens: Vec<Address>,
+
+    pub journal_path: Option<String>,
+
+    // count of receipts kept after requests leave the pool
+    pub max_receipts: Option<usize>,
+
+    // minimum margin of request fee over its gas cost, no fee check if none
+    pub min_fee_margin_percent: Option<u32>,
+
+    // requests staying in the pool longer than it are evicted, never evicted if none
+    pub max_age_secs: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpc {
     pub http: Option<JsonRpcHttp>,
     pub ipc: Option<JsonRpcIpc>,
-    // pub websocket: Option<JsonRpcWebSocket>,
+    pub websocket: Option<JsonRpcWebSocket>,
+
+    // API keys of clients, calls of admin and relayer APIs are not authenticated if none
+    pub api_keys: Option<Vec<JsonRpcApiKey>>,
+
+    // rate limits of APIs, keyed by API name, they are shared by all servers
+    pub rate_limits: Option<HashMap<String, JsonRpcRateLimit>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonRpcApiKey {
+    // identifier of key, used in HMAC credentials
+    pub id: String,
+
+    // secret sent as bearer token or used to sign HMAC credentials
+    pub secret: String,
+
+    // APIs which are allowed to be called with this key
+    pub apis: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -112,6 +210,31 @@ pub struct JsonRpcHttp {
     pub interface: String,
     pub port: u16,
     pub thread_count: usize,
+
+    // addresses of reverse proxies whose X-Forwarded-For and X-Real-IP headers are trusted, the
+    // server must be reachable only through these proxies since it can not see addresses of peers
+    pub trusted_proxies: Option<Vec<IpAddr>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonRpcRateLimit {
+    // limit of calls per client, HTTP clients are identified by address read from
+    // X-Forwarded-For or X-Real-IP header set by trusted proxies and IPC or WebSocket clients by
+    // their connection. HTTP server requires trusted proxies for it, and calls of HTTP clients
+    // of unknown address are rejected
+    pub per_client: Option<RateLimit>,
+
+    // limit of token transfer requests per request sender
+    pub per_sender: Option<RateLimit>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimit {
+    // count of calls allowed at once
+    pub burst: u32,
+
+    // count of calls refilled every second
+    pub per_second: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -120,7 +243,14 @@ pub struct JsonRpcWebSocket {
     pub apis: Vec<String>,
     pub interface: String,
     pub port: u16,
-    pub thread_count: usize,
+    pub max_connections: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Metrics {
+    pub disable: bool,
+    pub interface: String,
+    pub port: u16,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -145,6 +275,9 @@ pub struct Configuration {
     // pub network: Network,
     /// JSON-RPC Service
     pub jsonrpc: JsonRpc,
+
+    /// Metrics Service
+    pub metrics: Option<Metrics>,
 }
 
 impl Configuration {
@@ -152,28 +285,9 @@ impl Configuration {
         keyfile_path: &PathBuf,
         password_file: &PathBuf,
     ) -> Result<KeyPair, error::Error> {
-        fn read_file(file_path: &PathBuf) -> Result<String, error::Error> {
-            let mut file_content = String::new();
-            File::open(file_path)?.read_to_string(&mut file_content)?;
-
-            Ok(file_content)
-        }
-
-        let secret = {
-            let crypto = {
-                let mut key_file_content = read_file(keyfile_path)?;
-                let value: serde_json::Value = serde_json::from_str(&key_file_content)?;
-                Crypto::from_str(&value["crypto"].to_string())?
-            };
-
-            let password = Password::from(read_file(password_file)?.trim().to_owned());
-            match crypto.secret(&password) {
-                Ok(secret) => secret,
-                Err(err) => return Err(Error::from(ErrorKind::EthStore(err))),
-            }
-        };
-
-        Ok(KeyPair::from_secret(secret)?)
+        let key_file_content = key_store::read_file(keyfile_path)?;
+        let password = key_store::read_file(password_file)?;
+        KeyStore::recover_keypair(&key_file_content, password.trim())
     }
 
     pub fn interface(interface: &String) -> IpAddr {
@@ -194,8 +308,40 @@ impl Configuration {
     }
 
     pub fn ethereum_params(&self) -> EthereumServiceParams {
+        let default_health_params = EthereumHealthParams::default();
         EthereumServiceParams {
             ethereum_nodes: self.ethereum.ethereum_nodes.clone(),
+            health_check_interval: Duration::from_secs(
+                self.ethereum.health_check_interval_secs.unwrap_or(10),
+            ),
+            health_params: EthereumHealthParams {
+                max_consecutive_failures: self
+                    .ethereum
+                    .max_consecutive_failures
+                    .unwrap_or(default_health_params.max_consecutive_failures),
+                max_error_rate_percent: self
+                    .ethereum
+                    .max_error_rate_percent
+                    .unwrap_or(default_health_params.max_error_rate_percent),
+                max_block_lag: self
+                    .ethereum
+                    .max_block_lag
+                    .unwrap_or(default_health_params.max_block_lag),
+                ejection_duration: self
+                    .ethereum
+                    .ejection_secs
+                    .map(Duration::from_secs)
+                    .unwrap_or(default_health_params.ejection_duration),
+            },
+            quorum: self.ethereum.quorum_size.map(|size| {
+                let size = cmp::max(size, 1);
+                // majority of quorum by default
+                let threshold = self.ethereum.quorum_threshold.unwrap_or(size / 2 + 1);
+                EthereumQuorumParams {
+                    size,
+                    threshold: cmp::min(cmp::max(threshold, 1), size),
+                }
+            }),
         }
     }
 
@@ -250,15 +396,104 @@ impl Configuration {
             }
         }
 
+        if let Some(key_store) = self.relayer_key_store()? {
+            for keypair in key_store.keypairs()? {
+                info!(target: "system",
+                    "Relayer address {:?} is recovered from key store",
+                    keypair.address()
+                );
+                relayer_keypairs.push(keypair);
+            }
+        }
+
         Ok(MachineServiceParams {
             dispatcher: RequestDispatcher::new(config.dispatcher, FstRequestConverter::new()),
             chain_id: config.chain_id,
             interval: Duration::from_secs(config.interval_secs),
             relayer_keypairs,
             confirmation_count: config.confirmation_count,
+            gas_price_bump_block_count: config.gas_price_bump_block_count,
+            gas_price_bump_percent: config.gas_price_bump_percent.unwrap_or(12),
+            max_pending_transactions: config.max_pending_transactions.unwrap_or(1),
+            min_profit_margin_percent: config.min_profit_margin_percent,
+            unprofitable_hold_back: Duration::from_secs(
+                config.unprofitable_hold_back_secs.unwrap_or(60),
+            ),
+            max_history_records: config.max_history_records.unwrap_or(10240),
         })
     }
 
+    pub fn relayer_key_store(&self) -> Result<Option<KeyStore>, error::Error> {
+        let keys_dir = match self.machine.keys_dir {
+            Some(ref keys_dir) => keys_dir,
+            None => return Ok(None),
+        };
+
+        let path = match resolve_path(keys_dir) {
+            Some(path) => path,
+            None => {
+                return Err(Error::from(ErrorKind::ResolveFilePathFailed(
+                    keys_dir.clone(),
+                )))
+            }
+        };
+
+        let password_file = match self.machine.keys_password_file {
+            Some(ref password_file) => password_file,
+            None => {
+                return Err(Error::from(ErrorKind::KeyStorePasswordRequired(
+                    keys_dir.clone(),
+                )))
+            }
+        };
+
+        let password = match resolve_path(password_file) {
+            Some(password_path) => key_store::read_file(&password_path)?,
+            None => {
+                return Err(Error::from(ErrorKind::ResolveFilePathFailed(
+                    password_file.clone(),
+                )))
+            }
+        };
+
+        Ok(Some(KeyStore::new(
+            path,
+            Password::from(password.trim().to_owned()),
+        )))
+    }
+
+    /// Removes a relayer from the `[relayer.relayers]` table of a configuration file, returns
+    /// `false` if the relayer is not in the file
+    pub fn remove_relayer_from_file(file_path: &Path, address: &Address) -> Result<bool, Error> {
+        let mut config: toml::Value = toml::from_str(&key_store::read_file(file_path)?)?;
+
+        let removed = match config
+            .get_mut("relayer")
+            .and_then(|machine| machine.get_mut("relayers"))
+            .and_then(|relayers| relayers.as_table_mut())
+        {
+            Some(relayers) => {
+                let keys: Vec<_> = relayers
+                    .keys()
+                    .filter(|key| {
+                        Address::from_str(key.trim_start_matches("0x")).ok() == Some(*address)
+                    })
+                    .cloned()
+                    .collect();
+                for key in keys.iter() {
+                    relayers.remove(key);
+                }
+                !keys.is_empty()
+            }
+            None => false,
+        };
+
+        if removed {
+            File::create(file_path)?.write_all(toml::to_string(&config)?.as_bytes())?;
+        }
+        Ok(removed)
+    }
+
     pub fn pricer_params(&self) -> PriceServiceMode {
         match self.pricer.mode {
             PricerMode::Fixed => PriceServiceMode::Fixed {
@@ -266,6 +501,15 @@ impl Configuration {
                     * U256::from(1_000_000_000),
                 token_prices: HashMap::new(),
             },
+            PricerMode::Calibrate => PriceServiceMode::Calibrate(CalibrateParams {
+                interval: Duration::from_secs(self.pricer.calibrate_interval_secs.unwrap_or(15)),
+                percentile: self.pricer.calibrate_percentile.unwrap_or(60),
+                smoothing_percent: self.pricer.calibrate_smoothing_percent.unwrap_or(20),
+                min_gas_price: U256::from(self.pricer.min_gas_price_in_gwei.unwrap_or(1))
+                    * U256::from(1_000_000_000),
+                max_gas_price: U256::from(self.pricer.max_gas_price_in_gwei.unwrap_or(100))
+                    * U256::from(1_000_000_000),
+            }),
             // _ => PriceServiceMode::Fixed {
             //     gas_price: U256::from(5) * U256::from(1_000_000_000),
             //     token_prices: HashMap::new(),
@@ -273,13 +517,77 @@ impl Configuration {
         }
     }
 
-    pub fn jsonrpc_params(&self) -> JsonRpcServiceParams {
+    pub fn token_price_feeds(&self) -> Result<Vec<TokenPriceFeedParams>, error::Error> {
+        let tokens = match self.pricer.tokens {
+            Some(ref tokens) => tokens,
+            None => return Ok(vec![]),
+        };
+
+        tokens
+            .iter()
+            .map(|(token, feed)| {
+                let missing = |option: &str| {
+                    Error::from(ErrorKind::InvalidTokenPriceFeed(
+                        *token,
+                        format!("{} is required", option),
+                    ))
+                };
+
+                let source = match feed.source {
+                    TokenPriceSource::File => {
+                        let path = feed.path.as_ref().ok_or_else(|| missing("path"))?;
+                        match resolve_path(path) {
+                            Some(path) => TokenPriceSourceParams::File(path),
+                            None => {
+                                return Err(Error::from(ErrorKind::ResolveFilePathFailed(
+                                    path.clone(),
+                                )))
+                            }
+                        }
+                    }
+                    TokenPriceSource::Http => TokenPriceSourceParams::Http {
+                        url: feed.url.clone().ok_or_else(|| missing("url"))?,
+                        json_path: feed.json_path.clone().ok_or_else(|| missing("json_path"))?,
+                        decimals: feed.decimals.unwrap_or(18),
+                    },
+                    TokenPriceSource::Dex => TokenPriceSourceParams::Exchange {
+                        pair: feed.pair.ok_or_else(|| missing("pair"))?,
+                        weth: feed.weth.ok_or_else(|| missing("weth"))?,
+                    },
+                };
+
+                Ok(TokenPriceFeedParams {
+                    token: *token,
+                    source,
+                    max_age: Duration::from_secs(feed.max_age_secs.unwrap_or(300)),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns parameters of JSON-RPC service, fails if HTTP server limits calls per client
+    /// without trusted proxies since it can not identify its clients.
+    pub fn jsonrpc_params(&self) -> Result<JsonRpcServiceParams, error::Error> {
         let config = self.jsonrpc.clone();
+        let rate_limits = Self::rate_limits(config.rate_limits.unwrap_or_default());
+        if let Some(ref http) = config.http {
+            let apis = rpc_apis::ApiSet::from_strings(http.apis.clone()).apis();
+            let untrusted = http.trusted_proxies.as_ref().map_or(true, Vec::is_empty);
+            let limited_api = rate_limits
+                .iter()
+                .find(|(api, limit)| limit.per_client.is_some() && apis.contains(api));
+            if let (false, true, Some((api, _))) = (http.disable, untrusted, limited_api) {
+                let api = api.to_string();
+                return Err(Error::from(ErrorKind::ClientRateLimitWithoutProxies(api)));
+            }
+        }
+
         let http_config = config.http.map(|http| JsonRpcHttpConfig {
             disable: http.disable,
             apis: rpc_apis::ApiSet::from_strings(http.apis),
             socket_address: SocketAddr::new(Self::interface(&http.interface), http.port),
             thread_count: http.thread_count,
+            trusted_proxies: http.trusted_proxies.unwrap_or_default(),
         });
 
         let ipc_config = config.ipc.map(|ipc| JsonRpcIpcConfig {
@@ -288,21 +596,101 @@ impl Configuration {
             ipc_path: ipc.path,
         });
 
-        JsonRpcServiceParams {
+        let websocket_config = config.websocket.map(|websocket| JsonRpcWebSocketConfig {
+            disable: websocket.disable,
+            apis: rpc_apis::ApiSet::from_strings(websocket.apis),
+            socket_address: SocketAddr::new(Self::interface(&websocket.interface), websocket.port),
+            max_connections: websocket.max_connections,
+        });
+
+        let api_keys = config.api_keys.map(|api_keys| {
+            api_keys
+                .into_iter()
+                .map(|api_key| JsonRpcApiKeyConfig {
+                    id: api_key.id,
+                    secret: api_key.secret,
+                    apis: rpc_apis::ApiSet::from_strings(api_key.apis),
+                })
+                .collect()
+        });
+
+        Ok(JsonRpcServiceParams {
             http_config,
             ipc_config,
-            websocket_config: None,
-        }
+            websocket_config,
+            api_keys,
+            rate_limits,
+        })
+    }
+
+    fn rate_limits(
+        limits: HashMap<String, JsonRpcRateLimit>,
+    ) -> HashMap<rpc_apis::Api, JsonRpcApiRateLimitConfig> {
+        let rate_limit = |limit: RateLimit| JsonRpcRateLimitConfig {
+            burst: limit.burst,
+            per_second: limit.per_second,
+        };
+
+        limits
+            .into_iter()
+            .filter_map(|(api, limit)| match api.parse::<rpc_apis::Api>() {
+                Ok(api) => Some((
+                    api,
+                    JsonRpcApiRateLimitConfig {
+                        per_client: limit.per_client.map(rate_limit),
+                        per_sender: limit.per_sender.map(rate_limit),
+                    },
+                )),
+                Err(err) => {
+                    warn!(target: "system", "failed to parse rate limit of API: {}", err);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub fn metrics_params(&self) -> Option<MetricsServiceParams> {
+        self.metrics.as_ref().map(|metrics| MetricsServiceParams {
+            disable: metrics.disable,
+            socket_address: SocketAddr::new(Self::interface(&metrics.interface), metrics.port),
+        })
     }
 
     pub fn pool_params(&self) -> Pool {
         self.pool.clone()
     }
 
+    pub fn pool_journal_path(&self) -> Result<Option<PathBuf>, error::Error> {
+        match self.pool.journal_path {
+            Some(ref path) => match resolve_path(path) {
+                Some(path) => Ok(Some(path)),
+                None => Err(Error::from(ErrorKind::ResolveFilePathFailed(path.clone()))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn collation_history_path(&self) -> Result<Option<PathBuf>, error::Error> {
+        match self.machine.history_path {
+            Some(ref path) => match resolve_path(path) {
+                Some(path) => Ok(Some(path)),
+                None => Err(Error::from(ErrorKind::ResolveFilePathFailed(path.clone()))),
+            },
+            None => Ok(None),
+        }
+    }
+
     pub fn new_example() -> Configuration {
         Configuration {
             ethereum: EthereumService {
                 ethereum_nodes: vec!["http://127.0.0.1:8545".to_owned()],
+                health_check_interval_secs: Some(10),
+                max_consecutive_failures: Some(3),
+                max_error_rate_percent: Some(50),
+                max_block_lag: Some(5),
+                ejection_secs: Some(30),
+                quorum_size: Some(3),
+                quorum_threshold: Some(2),
             },
             ethereum_monitor: EthereumMonitor {
                 interval_millis: 1000,
@@ -315,12 +703,25 @@ impl Configuration {
                 interval_secs: 3,
 
                 allow_tokens: vec![Address::from("3830f7aF866fae79e4f6b277be17593bf96bee3b")],
+
+                journal_path: Some("$XDG_DATA_HOME/fst-relayer/pool-journal.jsonl".to_owned()),
+
+                max_receipts: Some(10240),
+
+                min_fee_margin_percent: Some(0),
+
+                max_age_secs: Some(3600),
             },
             machine: Machine {
                 disable: false,
                 chain_id: None,
                 interval_secs: 5,
                 confirmation_count: 12,
+                gas_price_bump_block_count: Some(20),
+                gas_price_bump_percent: Some(12),
+                max_pending_transactions: Some(4),
+                min_profit_margin_percent: Some(10),
+                unprofitable_hold_back_secs: Some(120),
                 dispatcher: Address::from("4ac3b5f5162b12f3f5c81a5db2348405e9462c23"),
                 relayers: {
                     let mut relayers = HashMap::new();
@@ -342,10 +743,39 @@ impl Configuration {
 
                     relayers
                 },
+                keys_dir: Some("$XDG_DATA_HOME/fst-relayer/keys".to_owned()),
+                keys_password_file: Some("/tmp/my-keys-passphrase".to_owned()),
+                history_path: Some(
+                    "$XDG_DATA_HOME/fst-relayer/collation-history.jsonl".to_owned(),
+                ),
+                max_history_records: Some(10240),
             },
             pricer: Pricer {
                 mode: PricerMode::Fixed,
                 fixed_gas_price_in_gwei: Some(5),
+                calibrate_interval_secs: Some(15),
+                calibrate_percentile: Some(60),
+                calibrate_smoothing_percent: Some(20),
+                min_gas_price_in_gwei: Some(1),
+                max_gas_price_in_gwei: Some(100),
+                tokens: {
+                    let mut tokens = HashMap::new();
+                    tokens.insert(
+                        Address::from("3830f7aF866fae79e4f6b277be17593bf96bee3b"),
+                        TokenPriceFeed {
+                            source: TokenPriceSource::File,
+                            max_age_secs: Some(300),
+                            path: Some("$XDG_DATA_HOME/fst-relayer/token-prices.toml".to_owned()),
+                            url: None,
+                            json_path: None,
+                            decimals: None,
+                            pair: None,
+                            weth: None,
+                        },
+                    );
+
+                    Some(tokens)
+                },
             },
             jsonrpc: JsonRpc {
                 http: Some(JsonRpcHttp {
@@ -354,13 +784,49 @@ impl Configuration {
                     interface: "local".to_owned(),
                     port: 4982,
                     thread_count: 2,
+                    trusted_proxies: Some(vec![IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))]),
                 }),
                 ipc: Some(JsonRpcIpc {
                     disable: false,
                     apis: vec!["token".to_owned()],
                     path: "/tmp/fst-relayer.ipc".to_owned(),
                 }),
+                websocket: Some(JsonRpcWebSocket {
+                    disable: false,
+                    apis: vec!["token".to_owned()],
+                    interface: "local".to_owned(),
+                    port: 4983,
+                    max_connections: 100,
+                }),
+                api_keys: Some(vec![JsonRpcApiKey {
+                    id: "admin".to_owned(),
+                    secret: "change-me".to_owned(),
+                    apis: vec!["admin".to_owned(), "relayer".to_owned()],
+                }]),
+                rate_limits: {
+                    let mut rate_limits = HashMap::new();
+                    rate_limits.insert(
+                        "token".to_owned(),
+                        JsonRpcRateLimit {
+                            per_client: Some(RateLimit {
+                                burst: 20,
+                                per_second: 5,
+                            }),
+                            per_sender: Some(RateLimit {
+                                burst: 10,
+                                per_second: 1,
+                            }),
+                        },
+                    );
+
+                    Some(rate_limits)
+                },
             },
+            metrics: Some(Metrics {
+                disable: false,
+                interface: "local".to_owned(),
+                port: 4984,
+            }),
         }
     }
 }
@@ -370,6 +836,13 @@ impl Default for Configuration {
         Configuration {
             ethereum: EthereumService {
                 ethereum_nodes: vec!["http://127.0.0.1:8545".to_owned()],
+                health_check_interval_secs: None,
+                max_consecutive_failures: None,
+                max_error_rate_percent: None,
+                max_block_lag: None,
+                ejection_secs: None,
+                quorum_size: None,
+                quorum_threshold: None,
             },
             ethereum_monitor: EthereumMonitor {
                 interval_millis: 1000,
@@ -382,6 +855,14 @@ impl Default for Configuration {
                 interval_secs: 3,
 
                 allow_tokens: Default::default(),
+
+                journal_path: None,
+
+                max_receipts: None,
+
+                min_fee_margin_percent: None,
+
+                max_age_secs: None,
             },
             machine: Machine {
                 disable: false,
@@ -390,10 +871,25 @@ impl Default for Configuration {
                 chain_id: None,
                 interval_secs: 3,
                 confirmation_count: 12,
+                gas_price_bump_block_count: None,
+                gas_price_bump_percent: None,
+                max_pending_transactions: None,
+                min_profit_margin_percent: None,
+                unprofitable_hold_back_secs: None,
+                keys_dir: None,
+                keys_password_file: None,
+                history_path: None,
+                max_history_records: None,
             },
             pricer: Pricer {
                 mode: PricerMode::Fixed,
                 fixed_gas_price_in_gwei: Some(1),
+                calibrate_interval_secs: None,
+                calibrate_percentile: None,
+                calibrate_smoothing_percent: None,
+                min_gas_price_in_gwei: None,
+                max_gas_price_in_gwei: None,
+                tokens: None,
             },
             jsonrpc: JsonRpc {
                 http: Some(JsonRpcHttp {
@@ -402,13 +898,18 @@ impl Default for Configuration {
                     interface: "local".to_owned(),
                     port: 4982, // 0x1376 == 4982
                     thread_count: 4,
+                    trusted_proxies: None,
                 }),
                 ipc: Some(JsonRpcIpc {
                     disable: false,
                     apis: vec!["token".to_owned()],
                     path: "/tmp/fst-relayer.ipc".to_owned(),
                 }),
+                websocket: None,
+                api_keys: None,
+                rate_limits: None,
             },
+            metrics: None,
         }
     }
 }
@@ -440,5 +941,54 @@ fn resolve_path(path_str: &String) -> Option<std::path::PathBuf> {
 
 #[cfg(test)]
 mod tests {
-    // use super::Configuration;
+    use super::Configuration;
+    use ethereum_types::Address;
+    use std::{env, fs, process};
+    use toml;
+
+    #[test]
+    fn test_profitability_options_in_relayer_section() {
+        let config = toml::to_string(&Configuration::new_example()).unwrap();
+        let section = config
+            .split("\n[")
+            .find(|section| section.starts_with("relayer]"))
+            .unwrap();
+
+        assert!(section.contains("min_profit_margin_percent = 10"));
+        assert!(section.contains("unprofitable_hold_back_secs = 120"));
+    }
+
+    #[test]
+    fn test_client_rate_limit_requires_trusted_proxies() {
+        let mut config = Configuration::new_example();
+        assert!(config.jsonrpc_params().is_ok());
+
+        config.jsonrpc.http.as_mut().unwrap().trusted_proxies = Some(Vec::new());
+        assert!(config.jsonrpc_params().is_err());
+        config.jsonrpc.http.as_mut().unwrap().trusted_proxies = None;
+        assert!(config.jsonrpc_params().is_err());
+
+        config.jsonrpc.http.as_mut().unwrap().disable = true;
+        assert!(config.jsonrpc_params().is_ok());
+    }
+
+    #[test]
+    fn test_remove_relayer_from_file() {
+        let path = env::temp_dir().join(format!("relayer-config-{}.toml", process::id()));
+        let config = Configuration::new_example();
+        fs::write(&path, toml::to_string(&config).unwrap()).unwrap();
+
+        let removed = Address::from("0101010101010101010101010101010101010101");
+        assert!(Configuration::remove_relayer_from_file(&path, &removed).unwrap());
+        assert!(!Configuration::remove_relayer_from_file(&path, &removed).unwrap());
+
+        let content = fs::read_to_string(&path).unwrap();
+        let reloaded: Configuration = toml::from_str(&content).unwrap();
+        let mut relayers: Vec<_> = reloaded.machine.relayers.keys().cloned().collect();
+        relayers.sort();
+        assert_eq!(relayers, vec![Address::from(0)]);
+        assert_eq!(reloaded.machine.dispatcher, config.machine.dispatcher);
+
+        let _ = fs::remove_file(&path);
+    }
 }
@@ -22,6 +22,9 @@ error_chain! {
         Json(serde_json::Error);
         EthKey(ethkey::Error);
         EthereumService(ethereum::service::Error);
+        Machine(machine::Error);
+        Pool(pool::Error);
+        PriceService(pricer::Error);
     }
 
     errors {
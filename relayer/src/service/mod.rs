@@ -15,20 +15,27 @@
 // along with FST Relayer. If not, see <http://www.gnu.org/licenses/>.
 use futures::{sync::mpsc, Async, Future, Poll, Stream};
 use parking_lot::Mutex;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio_signal::unix::{Signal, SIGHUP, SIGINT, SIGTERM};
 
 use collation::FstRequestConverter;
 use ethereum::monitor::{Params as EthereumMonitorParams, Service as EthereumMonitor};
-use ethereum::service::{Params as EthereumServiceParams, Service as EthereumService};
-use machine::{MachineService, MachineServiceParams, RelayerMode};
+use ethereum::service::{
+    HealthParams as EthereumHealthParams, Params as EthereumServiceParams,
+    QuorumParams as EthereumQuorumParams, Service as EthereumService,
+};
+use machine::{CollationHistory, MachineService, MachineServiceParams, RelayerMode};
 use network::{NetworkParams, NetworkService};
 use pool::{
-    ListAddressFilter, ListAddressFilterMode, PoolParams, PoolService, RequestVerifier,
-    TokenSelector, VerifiedRequest,
+    ListAddressFilter, ListAddressFilterMode, PoolJournal, PoolParams, PoolService,
+    RequestVerifier, TokenSelector, VerifiedRequest,
+};
+use pricer::{
+    CalibrateParams, Error as PriceServiceError, PriceService, PriceServiceMode,
+    TokenPriceFeedParams, TokenPriceSourceParams,
 };
-use pricer::{Error as PriceServiceError, PriceService, PriceServiceMode};
 
 use super::rpc_apis;
 
@@ -41,11 +48,20 @@ pub use self::error::{Error, ErrorKind};
 pub use self::exit_reason::ExitReason;
 
 use super::jsonrpc::{
+    ApiKey as JsonRpcApiKeyConfig,
+    ApiRateLimit as JsonRpcApiRateLimitConfig,
+    CallCounter,
     HttpConfiguration as JsonRpcHttpConfig,
     IpcConfiguration as JsonRpcIpcConfig,
+    RateLimit as JsonRpcRateLimitConfig,
     Service as JsonRpcService,
     ServiceParams as JsonRpcServiceParams,
-    // WebSocketConfiguration as JsonRpcWebSocketConfig,
+    WebSocketConfiguration as JsonRpcWebSocketConfig,
+};
+use super::metrics::{
+    Collector as MetricsCollector,
+    Service as MetricsService,
+    ServiceParams as MetricsServiceParams,
 };
 
 #[derive(Clone)]
@@ -64,7 +80,7 @@ impl traits::ExitHandle for ExitHandle {
 pub struct Service {
     ethereum_service: Arc<Mutex<EthereumService>>,
     ethereum_monitor_service: Arc<Mutex<EthereumMonitor<EthereumService>>>,
-    price_service: Arc<Mutex<PriceService>>,
+    price_service: Arc<Mutex<PriceService<EthereumService>>>,
     pool_service: Arc<
         Mutex<
             PoolService<
@@ -73,7 +89,7 @@ pub struct Service {
                 ListAddressFilter,
                 VerifiedRequest,
                 TokenSelector,
-                RequestVerifier<EthereumService>,
+                RequestVerifier<EthereumService, PriceService<EthereumService>>,
             >,
         >,
     >,
@@ -89,21 +105,22 @@ pub struct Service {
                     ListAddressFilter,
                     VerifiedRequest,
                     TokenSelector,
-                    RequestVerifier<EthereumService>,
+                    RequestVerifier<EthereumService, PriceService<EthereumService>>,
                 >,
-                PriceService,
+                PriceService<EthereumService>,
                 FstRequestConverter,
             >,
         >,
     >,
 
     jsonrpc_service: Box<JsonRpcService>,
+    metrics_service: Option<MetricsService>,
     exit_handle: Arc<Mutex<ExitHandle>>,
     exit_handler: Box<Future<Item = ExitReason, Error = ()> + Send>,
 }
 
 impl Service {
-    pub fn new(config: Configuration) -> Result<Service, Error> {
+    pub fn new(config: Configuration, config_file_path: PathBuf) -> Result<Service, Error> {
         let (exit_handler, exit_handle) = {
             let (sender, mut receiver) = mpsc::unbounded::<()>();
             let register_signal = |unix_signal: i32| {
@@ -156,6 +173,13 @@ impl Service {
             Arc::new(Mutex::new(NetworkService::new(params)))
         };
 
+        let price_service = {
+            let params = config.pricer_params();
+            info!(target: "system", "Start Price Service with mode: {:?}", params);
+            let token_feeds = config.token_price_feeds()?;
+            Arc::new(Mutex::new(PriceService::new(params, token_feeds, ethereum_service.clone())?))
+        };
+
         let pool_service = {
             let params = config.pool_params();
             info!(
@@ -172,15 +196,22 @@ impl Service {
                 },
             );
 
-            let request_verifier =
-                Arc::new(Mutex::new(RequestVerifier::new(ethereum_service.clone())));
+            let request_verifier = Arc::new(Mutex::new(RequestVerifier::new(
+                ethereum_service.clone(),
+                price_service.clone(),
+                params.min_fee_margin_percent,
+            )));
             let request_selector = Arc::new(TokenSelector::new());
             let interval = Duration::from_secs(1);
-            Arc::new(Mutex::new(PoolService::new(
+            let mut pool = PoolService::new(
                 PoolParams {
                     max_count: params.max_count,
                     max_per_sender: params.max_per_sender,
                     max_mem_usage: params.max_mem_usage,
+                    max_receipts: params
+                        .max_receipts
+                        .unwrap_or_else(|| PoolParams::default().max_receipts),
+                    max_age: params.max_age_secs.map(Duration::from_secs),
                 },
                 interval,
                 ethereum_service.clone(),
@@ -188,19 +219,30 @@ impl Service {
                 token_filter,
                 request_verifier,
                 request_selector,
-            )))
-        };
+            );
 
-        let price_service = {
-            let params = config.pricer_params();
-            info!(target: "system", "Start Price Service with mode: {:?}", params);
-            Arc::new(Mutex::new(PriceService::new(params)))
+            if let Some(journal_path) = config.pool_journal_path()? {
+                if let Some(parent) = journal_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+
+                let (journal, records) = PoolJournal::open(&journal_path)?;
+                info!(target: "system",
+                    "Restore {} request(s) from pool journal {:?}",
+                    records.len(),
+                    journal_path
+                );
+                pool.set_journal(journal, records);
+            }
+
+            Arc::new(Mutex::new(pool))
         };
 
         let machine_service = {
             // let relayer_mode = RelayerMode::NotBroadcastTransaction;
             let relayer_mode = RelayerMode::BroadcastTransaction;
             let params = config.machine_params()?;
+            let max_history_records = params.max_history_records;
 
             let mut machine = MachineService::new(
                 relayer_mode,
@@ -211,6 +253,20 @@ impl Service {
                 price_service.clone(),
             );
 
+            if let Some(history_path) = config.collation_history_path()? {
+                if let Some(parent) = history_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+
+                let history = CollationHistory::open(&history_path, max_history_records)?;
+                info!(target: "system",
+                    "Restore {} collation record(s) from history {:?}",
+                    history.len(),
+                    history_path
+                );
+                machine.set_history(history);
+            }
+
             {
                 use traits::MachineService;
                 let relayer_count = machine.relayer_count();
@@ -254,8 +310,11 @@ impl Service {
             Arc::new(Mutex::new(machine))
         };
 
+        // shared by JSON-RPC servers and metrics server
+        let call_counter = CallCounter::default();
+
         let jsonrpc_service = {
-            let params = config.jsonrpc_params();
+            let params = config.jsonrpc_params()?;
             info!(target: "system", "Start JSON-RPC service");
 
             Box::new(JsonRpcService::new(
@@ -265,9 +324,27 @@ impl Service {
                 network_service.clone(),
                 pool_service.clone(),
                 machine_service.clone(),
+                price_service.clone(),
+                config.relayer_key_store()?,
+                config_file_path,
+                config.pool_params().min_fee_margin_percent,
+                call_counter.clone(),
             ))
         };
 
+        let metrics_service = match config.metrics_params() {
+            Some(params) => {
+                let collector = MetricsCollector::new(
+                    ethereum_service.clone(),
+                    pool_service.clone(),
+                    machine_service.clone(),
+                    call_counter,
+                );
+                Some(MetricsService::new(params, collector)?)
+            }
+            None => None,
+        };
+
         Ok(Service {
             ethereum_service,
             ethereum_monitor_service,
@@ -278,6 +355,7 @@ impl Service {
             machine_service,
 
             jsonrpc_service,
+            metrics_service,
 
             exit_handler,
             exit_handle,
@@ -318,13 +396,18 @@ impl Stream for Service {
                 Ok(Async::Ready(exit)) => {
                     // TODO gracefully exit
                     self.jsonrpc_service.shutdown();
+                    if let Some(ref mut metrics_service) = self.metrics_service {
+                        metrics_service.shutdown();
+                    }
                     return Ok(Async::Ready(Some(exit)));
                 }
                 _ => {}
             }
 
-            // FIXME polling ethereum service here
-            // self.ethereum_service.
+            match self.ethereum_service.lock().poll() {
+                Err(_err) => return Err(()),
+                _ => {}
+            }
 
             match self.ethereum_monitor_service.lock().poll() {
                 Err(_err) => return Err(()),